@@ -0,0 +1,321 @@
+//  _  _       _             _  _
+// | || |  ___| |_ _ __ ___ | || |
+// | || |_/ __| __| '_ ` _ \| || |_
+// |__   _\__ | |_| | | | | |__   _|
+//   |_| |___/\__|_|_|_| |_|  |_|
+//! # ehatrom — EEPROM HAT library for Raspberry Pi HATs
+//! - [Documentation (docs.rs)](https://docs.rs/ehatrom)
+//! - [GitHub](https://github.com/4stm4/ehatrom)
+//!
+//! ## Per-unit authentication against a secure element (`sign` feature)
+//!
+//! A signed image proves the EEPROM contents came from you, but an EEPROM
+//! can be copied. HATs that carry a secure element next to the EEPROM can
+//! prove they are genuine: the host sends a random 32-byte challenge, the
+//! element signs it with an Ed25519 private key that never leaves it, and the
+//! host checks the response against the unit's public key.
+//!
+//! The public key is stored in the image as a custom atom,
+//! [`AUTH_KEY_MAGIC`] followed by the 32-byte Ed25519 key. Embed it before
+//! signing the image (see [`sign`](crate::sign)) so the key itself is covered
+//! by the signature.
+//!
+//! Elements are reached through the [`SecureElement`] trait.
+//! [`SimulatedSecureElement`] answers in software for tests and bring-up.
+//! With the `linux` feature, [`I2cSecureElement`] speaks a minimal protocol
+//! for custom element firmware (e.g. a microcontroller holding the key), and
+//! [`read_authenticated`] hooks [`authenticate`] into the EEPROM read through
+//! [`read_eeprom_image_checked`](crate::read_eeprom_image_checked), so the
+//! image is only returned for a genuine unit. [`authenticate`] itself does not
+//! allocate.
+//!
+//! Off-the-shelf parts such as the ATECC608 sign with ECDSA P-256, not
+//! Ed25519, and frame their commands differently; they are not supported.
+//!
+//! ```
+//! # #[cfg(feature = "alloc")] {
+//! use ehatrom::auth::{SimulatedSecureElement, authenticate};
+//!
+//! let mut chip = SimulatedSecureElement::new([9; 32]);
+//! let mut eeprom = ehatrom::parse_settings("product_id 0x0001\n").unwrap();
+//! eeprom.add_custom_atom(chip.key_atom().to_atom().to_vec());
+//! let image = eeprom.serialize();
+//!
+//! assert_eq!(authenticate(&image, &mut chip, &[0x42; 32]), Ok(()));
+//! # }
+//! ```
+
+use crate::sign::{PUBLIC_KEY_LENGTH, SIGNATURE_LENGTH, SigningKey, VerifyingKey};
+use crate::{AtomType, atoms};
+use core::fmt;
+use ed25519_dalek::{Signature, Signer};
+
+/// First bytes of an authentication public-key atom payload.
+pub const AUTH_KEY_MAGIC: [u8; 4] = *b"EHK1";
+/// Payload length of an authentication public-key atom.
+pub const AUTH_KEY_ATOM_LEN: usize = AUTH_KEY_MAGIC.len() + PUBLIC_KEY_LENGTH;
+/// Length of a challenge.
+pub const CHALLENGE_LEN: usize = 32;
+
+/// A secure element that signs challenges with its private key.
+pub trait SecureElement {
+    /// Transport or chip error.
+    type Error;
+
+    /// Signs `challenge` and returns the 64-byte Ed25519 signature.
+    fn respond(
+        &mut self,
+        challenge: &[u8; CHALLENGE_LEN],
+    ) -> Result<[u8; SIGNATURE_LENGTH], Self::Error>;
+}
+
+/// Why a unit did not authenticate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuthError<E> {
+    /// The HAT EEPROM could not be read.
+    Eeprom(crate::EhatromError),
+    /// The image has no authentication key atom.
+    NoKey,
+    /// The key atom does not hold a valid Ed25519 public key.
+    BadKey,
+    /// The secure element did not answer.
+    Transport(E),
+    /// The response is not a signature of the challenge by the unit's key.
+    BadResponse,
+}
+
+impl<E: fmt::Display> fmt::Display for AuthError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AuthError::Eeprom(e) => write!(f, "{e}"),
+            AuthError::NoKey => write!(f, "no authentication key atom"),
+            AuthError::BadKey => write!(f, "authentication key atom is not an Ed25519 key"),
+            AuthError::Transport(e) => write!(f, "secure element: {e}"),
+            AuthError::BadResponse => write!(f, "secure element response does not verify"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<E: fmt::Debug + fmt::Display> std::error::Error for AuthError<E> {}
+
+impl<E> From<crate::EhatromError> for AuthError<E> {
+    fn from(e: crate::EhatromError) -> Self {
+        AuthError::Eeprom(e)
+    }
+}
+
+/// An authentication public-key atom payload.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AuthKeyAtom(pub [u8; PUBLIC_KEY_LENGTH]);
+
+impl AuthKeyAtom {
+    /// Parses an authentication key atom payload.
+    pub fn from_atom(data: &[u8]) -> Option<Self> {
+        let rest = data.strip_prefix(&AUTH_KEY_MAGIC)?;
+        rest.try_into().ok().map(AuthKeyAtom)
+    }
+
+    /// The authentication key atom payload.
+    pub fn to_atom(&self) -> [u8; AUTH_KEY_ATOM_LEN] {
+        let mut out = [0u8; AUTH_KEY_ATOM_LEN];
+        out[..4].copy_from_slice(&AUTH_KEY_MAGIC);
+        out[4..].copy_from_slice(&self.0);
+        out
+    }
+}
+
+impl fmt::Display for AuthKeyAtom {
+    /// `ed25519 key ` and the first eight bytes in hex.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "ed25519 key ")?;
+        for byte in &self.0[..8] {
+            write!(f, "{byte:02x}")?;
+        }
+        write!(f, "…")
+    }
+}
+
+/// The first authentication key atom of `data`.
+pub fn find_auth_key(data: &[u8]) -> Option<AuthKeyAtom> {
+    atoms(data)
+        .filter(|atom| atom.kind() == AtomType::Custom)
+        .find_map(|atom| AuthKeyAtom::from_atom(atom.data))
+}
+
+/// Challenges `element` and checks its response against the key in `image`.
+///
+/// `challenge` must be fresh and unpredictable for every call, or a recorded
+/// response can be replayed.
+pub fn authenticate<S: SecureElement>(
+    image: &[u8],
+    element: &mut S,
+    challenge: &[u8; CHALLENGE_LEN],
+) -> Result<(), AuthError<S::Error>> {
+    let key = find_auth_key(image).ok_or(AuthError::NoKey)?;
+    let key = VerifyingKey::from_bytes(&key.0).map_err(|_| AuthError::BadKey)?;
+    let response = element.respond(challenge).map_err(AuthError::Transport)?;
+    key.verify_strict(challenge, &Signature::from_bytes(&response))
+        .map_err(|_| AuthError::BadResponse)
+}
+
+/// A secure element simulated in software.
+#[derive(Debug, Clone)]
+pub struct SimulatedSecureElement {
+    key: SigningKey,
+}
+
+impl SimulatedSecureElement {
+    /// A simulated chip whose private key is derived from `seed`.
+    pub fn new(seed: [u8; 32]) -> Self {
+        SimulatedSecureElement {
+            key: SigningKey::from_bytes(&seed),
+        }
+    }
+
+    /// The public key, as it is stored in the image.
+    pub fn key_atom(&self) -> AuthKeyAtom {
+        AuthKeyAtom(self.key.verifying_key().to_bytes())
+    }
+}
+
+impl SecureElement for SimulatedSecureElement {
+    type Error = core::convert::Infallible;
+
+    fn respond(
+        &mut self,
+        challenge: &[u8; CHALLENGE_LEN],
+    ) -> Result<[u8; SIGNATURE_LENGTH], Self::Error> {
+        Ok(self.key.sign(challenge).to_bytes())
+    }
+}
+
+/// A secure element on a Linux I2C bus running firmware that speaks this
+/// crate's challenge protocol.
+///
+/// The framing is a single write of [`I2cSecureElement::SIGN_CHALLENGE`]
+/// followed by the 32-byte challenge, then a 64-byte read of the Ed25519
+/// signature once the element has finished. There is no standard address;
+/// pass the one the firmware answers on. Elements with a different command
+/// set implement [`SecureElement`] themselves.
+#[cfg(all(feature = "linux", any(target_os = "linux", target_os = "android")))]
+pub struct I2cSecureElement {
+    dev: i2cdev::linux::LinuxI2CDevice,
+}
+
+#[cfg(all(feature = "linux", any(target_os = "linux", target_os = "android")))]
+impl I2cSecureElement {
+    /// Command byte that starts a challenge.
+    pub const SIGN_CHALLENGE: u8 = 0x01;
+    /// Longest time the element may take to sign.
+    const SIGN_TIME: std::time::Duration = std::time::Duration::from_millis(60);
+
+    /// Opens the element at 7-bit address `addr` on `dev_path`.
+    pub fn new(dev_path: &str, addr: u16) -> Result<Self, crate::EhatromError> {
+        let dev = i2cdev::linux::LinuxI2CDevice::new(dev_path, addr)
+            .map_err(|_| crate::EhatromError::DeviceNotFound)?;
+        Ok(I2cSecureElement { dev })
+    }
+}
+
+#[cfg(all(feature = "linux", any(target_os = "linux", target_os = "android")))]
+impl SecureElement for I2cSecureElement {
+    type Error = crate::EhatromError;
+
+    fn respond(
+        &mut self,
+        challenge: &[u8; CHALLENGE_LEN],
+    ) -> Result<[u8; SIGNATURE_LENGTH], Self::Error> {
+        use i2cdev::core::I2CDevice;
+
+        let mut command = [0u8; 1 + CHALLENGE_LEN];
+        command[0] = Self::SIGN_CHALLENGE;
+        command[1..].copy_from_slice(challenge);
+        self.dev
+            .write(&command)
+            .map_err(|_| crate::EhatromError::I2cError)?;
+        std::thread::sleep(Self::SIGN_TIME);
+        let mut response = [0u8; SIGNATURE_LENGTH];
+        self.dev
+            .read(&mut response)
+            .map_err(|_| crate::EhatromError::Timeout)?;
+        Ok(response)
+    }
+}
+
+/// Reads the HAT EEPROM on `dev_path`, authenticating the unit with
+/// `element` as part of the read. Returns the image only if the unit is
+/// genuine.
+#[cfg(all(feature = "linux", any(target_os = "linux", target_os = "android")))]
+pub fn read_authenticated<S: SecureElement>(
+    dev_path: &str,
+    element: &mut S,
+    challenge: &[u8; CHALLENGE_LEN],
+) -> Result<alloc::vec::Vec<u8>, AuthError<S::Error>> {
+    crate::read_eeprom_image_checked(dev_path, |image| authenticate(image, element, challenge))
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    use super::*;
+    use crate::parse_settings;
+    use alloc::vec::Vec;
+
+    fn image_with_key(key: AuthKeyAtom) -> Vec<u8> {
+        let mut eeprom = parse_settings("product_id 0x0001\nvendor \"ACME\"\n").unwrap();
+        eeprom.add_custom_atom(b"serial=42".to_vec());
+        eeprom.add_custom_atom(key.to_atom().to_vec());
+        eeprom.serialize()
+    }
+
+    #[test]
+    fn genuine_unit_authenticates_and_clones_do_not() {
+        let mut chip = SimulatedSecureElement::new([5; 32]);
+        let image = image_with_key(chip.key_atom());
+        assert_eq!(find_auth_key(&image), Some(chip.key_atom()));
+        assert_eq!(authenticate(&image, &mut chip, &[1; 32]), Ok(()));
+
+        // A copied EEPROM next to a different chip.
+        let mut clone = SimulatedSecureElement::new([6; 32]);
+        assert_eq!(
+            authenticate(&image, &mut clone, &[1; 32]),
+            Err(AuthError::BadResponse)
+        );
+
+        let bare = parse_settings("").unwrap().serialize();
+        assert_eq!(
+            authenticate(&bare, &mut chip, &[1; 32]),
+            Err(AuthError::NoKey)
+        );
+    }
+
+    #[test]
+    fn transport_errors_and_replayed_responses_are_reported() {
+        struct Replay([u8; SIGNATURE_LENGTH]);
+        impl SecureElement for Replay {
+            type Error = &'static str;
+            fn respond(&mut self, _: &[u8; CHALLENGE_LEN]) -> Result<[u8; 64], &'static str> {
+                Ok(self.0)
+            }
+        }
+        struct Absent;
+        impl SecureElement for Absent {
+            type Error = &'static str;
+            fn respond(&mut self, _: &[u8; CHALLENGE_LEN]) -> Result<[u8; 64], &'static str> {
+                Err("no ACK")
+            }
+        }
+
+        let mut chip = SimulatedSecureElement::new([5; 32]);
+        let image = image_with_key(chip.key_atom());
+        let recorded = chip.respond(&[1; 32]).unwrap();
+        assert_eq!(
+            authenticate(&image, &mut Replay(recorded), &[2; 32]),
+            Err(AuthError::BadResponse)
+        );
+        assert_eq!(
+            authenticate(&image, &mut Absent, &[2; 32]),
+            Err(AuthError::Transport("no ACK"))
+        );
+    }
+}
@@ -0,0 +1,437 @@
+//  _  _       _             _  _
+// | || |  ___| |_ _ __ ___ | || |
+// | || |_/ __| __| '_ ` _ \| || |_
+// |__   _\__ | |_| | | | | |__   _|
+//   |_| |___/\__|_|_|_| |_|  |_|
+//! # ehatrom — EEPROM HAT library for Raspberry Pi HATs
+//! - [Documentation (docs.rs)](https://docs.rs/ehatrom)
+//! - [GitHub](https://github.com/4stm4/ehatrom)
+//!
+//! ## TOML board descriptions (`toml` feature)
+//!
+//! A structured alternative to `eeprom_settings.txt` that compiles to the same
+//! [`Eeprom`] model as [`parse_settings`](crate::parse_settings):
+//!
+//! ```toml
+//! extends = "base.toml"          # optional; inherit and override another board
+//! current_supply = 1500          # power-supply atom (mA)
+//! dt_blob = "overlay.dtbo"       # device tree relative to this file; .dts is compiled
+//! # dt_overlay = "my-hat,addr=0x51"  # or: HAT+ overlay name instead of a blob
+//!
+//! [vendor_info]
+//! uuid = "12345678-9abc-def0-1234-56789abcdef0"
+//! product_id = 0x0001
+//! product_ver = 0x0002
+//! vendor = "ACME"
+//! product = "Test HAT"
+//!
+//! [bank0]                        # flags; bank1 is emitted only if present
+//! drive = 5
+//! slew = 1
+//! hysteresis = 1
+//! back_power = 2
+//!
+//! [bank0.pins.status_led]        # pins are named, so variants can override them
+//! gpio = 17
+//! func = "OUTPUT"
+//! pull = "DEFAULT"               # optional
+//! comment = "front-panel LED"    # optional, documentation only
+//!
+//! [bank1.pins.cam_enable]
+//! gpio = 40
+//! func = "ALT0"
+//!
+//! [[atom]]                       # custom atoms, in order; exactly one of:
+//! hex = "deadbeef"
+//! [[atom]]
+//! string = "serial:1234"
+//! [[atom]]
+//! file = "calibration.bin"
+//! ```
+//!
+//! With `extends`, the base file is loaded first and this file is merged on
+//! top of it: tables merge key by key (so a variant can override a single pin
+//! or vendor field), while scalars and arrays — including `[[atom]]` — are
+//! replaced. File paths are resolved relative to the file that names them.
+
+use crate::dts::compile_dts;
+use crate::schema::VendorDoc;
+use crate::utils::hex;
+use crate::{DtAtom, Eeprom, EepromHeader, GPIO_COUNT, GpioMapAtom, PinFunc, PinPull, encode_pin};
+use alloc::{
+    collections::BTreeMap,
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+use toml::{Table, Value};
+
+/// Maximum depth of an `extends` chain (guards against cycles).
+const MAX_EXTENDS_DEPTH: usize = 8;
+
+/// Error returned by [`load_board`] and [`parse_board`].
+#[derive(Debug)]
+pub enum BoardError {
+    /// A board file or an included file could not be read.
+    Io(PathBuf, std::io::Error),
+    /// A board file is not valid TOML.
+    Syntax(PathBuf, toml::de::Error),
+    /// The merged description does not describe a valid EEPROM.
+    Invalid(String),
+}
+
+impl core::fmt::Display for BoardError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            BoardError::Io(path, e) => write!(f, "{}: {e}", path.display()),
+            BoardError::Syntax(path, e) => write!(f, "{}: {e}", path.display()),
+            BoardError::Invalid(reason) => write!(f, "board description: {reason}"),
+        }
+    }
+}
+
+impl std::error::Error for BoardError {}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct BoardDoc {
+    vendor_info: VendorDoc,
+    #[serde(default)]
+    current_supply: Option<u32>,
+    #[serde(default)]
+    dt_blob: Option<String>,
+    #[serde(default)]
+    dt_overlay: Option<String>,
+    #[serde(default)]
+    bank0: Option<BankDoc>,
+    #[serde(default)]
+    bank1: Option<BankDoc>,
+    #[serde(default)]
+    atom: Vec<AtomDoc>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+struct BankDoc {
+    #[serde(default)]
+    drive: u8,
+    #[serde(default)]
+    slew: u8,
+    #[serde(default)]
+    hysteresis: u8,
+    #[serde(default)]
+    back_power: u8,
+    #[serde(default)]
+    pins: BTreeMap<String, PinDoc>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PinDoc {
+    gpio: u8,
+    func: PinFunc,
+    #[serde(default)]
+    pull: Option<PinPull>,
+    #[serde(default)]
+    #[allow(dead_code)]
+    comment: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct AtomDoc {
+    #[serde(default)]
+    hex: Option<String>,
+    #[serde(default)]
+    string: Option<String>,
+    #[serde(default)]
+    file: Option<String>,
+}
+
+/// Loads a TOML board description (following `extends`) into an [`Eeprom`].
+pub fn load_board(path: &Path) -> Result<Eeprom, BoardError> {
+    let table = load_table(path, 0)?;
+    compile(table)
+}
+
+/// Parses TOML board text into an [`Eeprom`]. Relative paths (`extends`,
+/// `dt_blob`, `[[atom]] file`) are resolved against `base_dir`.
+pub fn parse_board(text: &str, base_dir: &Path) -> Result<Eeprom, BoardError> {
+    let table = parse_table(text, base_dir, Path::new("<input>"), 0)?;
+    compile(table)
+}
+
+fn load_table(path: &Path, depth: usize) -> Result<Table, BoardError> {
+    let text = std::fs::read_to_string(path).map_err(|e| BoardError::Io(path.into(), e))?;
+    let dir = path.parent().unwrap_or(Path::new("."));
+    parse_table(&text, dir, path, depth)
+}
+
+/// Parses one file, resolves its relative paths and merges it over its base.
+fn parse_table(text: &str, dir: &Path, origin: &Path, depth: usize) -> Result<Table, BoardError> {
+    let mut table: Table =
+        toml::from_str(text).map_err(|e| BoardError::Syntax(origin.into(), e))?;
+    resolve_paths(&mut table, dir);
+
+    let Some(base) = table.remove("extends") else {
+        return Ok(table);
+    };
+    let Value::String(base) = base else {
+        return Err(BoardError::Invalid(
+            "`extends` must be a file path".to_string(),
+        ));
+    };
+    if depth >= MAX_EXTENDS_DEPTH {
+        return Err(BoardError::Invalid(format!(
+            "`extends` chain deeper than {MAX_EXTENDS_DEPTH} (cycle?)"
+        )));
+    }
+    let mut merged = load_table(&dir.join(base), depth + 1)?;
+    merge(&mut merged, table);
+    Ok(merged)
+}
+
+/// Makes `dt_blob` and `[[atom]] file` paths absolute relative to `dir`, so
+/// they keep pointing at the right file after merging into another board.
+fn resolve_paths(table: &mut Table, dir: &Path) {
+    let resolve = |v: &mut Value| {
+        if let Value::String(p) = v {
+            *p = dir.join(&*p).to_string_lossy().into_owned();
+        }
+    };
+    if let Some(v) = table.get_mut("dt_blob") {
+        resolve(v);
+    }
+    if let Some(Value::Array(atoms)) = table.get_mut("atom") {
+        for atom in atoms {
+            if let Some(v) = atom.as_table_mut().and_then(|t| t.get_mut("file")) {
+                resolve(v);
+            }
+        }
+    }
+}
+
+/// Deep-merges `over` into `base`: tables merge recursively, anything else
+/// replaces the base value.
+fn merge(base: &mut Table, over: Table) {
+    for (key, value) in over {
+        match (base.get_mut(&key), value) {
+            (Some(Value::Table(b)), Value::Table(o)) => merge(b, o),
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+fn compile(table: Table) -> Result<Eeprom, BoardError> {
+    let invalid = |reason: &str| BoardError::Invalid(reason.to_string());
+    let doc: BoardDoc = table
+        .try_into()
+        .map_err(|e: toml::de::Error| BoardError::Invalid(e.message().to_string()))?;
+
+    let vendor_info = doc.vendor_info.into_atom().map_err(invalid)?;
+    let gpio_map_bank0 = doc.bank0.unwrap_or_default().into_atom(0, GPIO_COUNT)?;
+    let gpio_map_bank1 = match doc.bank1 {
+        Some(bank) => Some(bank.into_atom(GPIO_COUNT as u8, crate::GPIO_COUNT_BANK1)?),
+        None => None,
+    };
+    if let Some(spec) = &doc.dt_overlay {
+        if doc.dt_blob.is_some() {
+            return Err(invalid("set only one of `dt_blob` and `dt_overlay`"));
+        }
+        DtAtom::overlay(spec).ok_or(invalid("dt_overlay: invalid overlay name or parameters"))?;
+    }
+    let dt_blob = match doc.dt_blob {
+        None => doc.dt_overlay.map(String::into_bytes),
+        Some(path) if path.ends_with(".dts") => {
+            let source = std::fs::read_to_string(&path)
+                .map_err(|e| BoardError::Io(path.as_str().into(), e))?;
+            let blob = compile_dts(&source)
+                .map_err(|e| BoardError::Invalid(format!("dt_blob {path}: {e}")))?;
+            Some(blob)
+        }
+        Some(path) => Some(read_file(&path)?),
+    };
+    let mut custom_atoms = Vec::with_capacity(doc.atom.len());
+    for (i, atom) in doc.atom.into_iter().enumerate() {
+        let data = match (atom.hex, atom.string, atom.file) {
+            (Some(h), None, None) => hex::decode(&h)
+                .ok_or_else(|| BoardError::Invalid(format!("atom {i}: invalid hex")))?,
+            (None, Some(s), None) => s.into_bytes(),
+            (None, None, Some(path)) => read_file(&path)?,
+            _ => {
+                return Err(BoardError::Invalid(format!(
+                    "atom {i}: set exactly one of `hex`, `string` or `file`"
+                )));
+            }
+        };
+        custom_atoms.push(data);
+    }
+
+    let mut eeprom = Eeprom {
+        header: EepromHeader::new(),
+        vendor_info,
+        gpio_map_bank0,
+        dt_blob,
+        gpio_map_bank1,
+        power_supply: doc.current_supply,
+        custom_atoms,
+    };
+    eeprom.update_header();
+    Ok(eeprom)
+}
+
+fn read_file(path: &str) -> Result<Vec<u8>, BoardError> {
+    std::fs::read(path).map_err(|e| BoardError::Io(path.into(), e))
+}
+
+impl BankDoc {
+    /// `base` is the BCM number of the bank's first pin; `len` its pin count.
+    fn into_atom(self, base: u8, len: usize) -> Result<GpioMapAtom, BoardError> {
+        let mut pins = [0u8; GPIO_COUNT];
+        let mut owner: [Option<&str>; GPIO_COUNT] = [None; GPIO_COUNT];
+        for (name, pin) in &self.pins {
+            let idx = pin
+                .gpio
+                .checked_sub(base)
+                .map(usize::from)
+                .filter(|&i| i < len)
+                .ok_or_else(|| {
+                    BoardError::Invalid(format!(
+                        "pin `{name}`: GPIO{} is not in this bank ({base}..{})",
+                        pin.gpio,
+                        base as usize + len - 1
+                    ))
+                })?;
+            if let Some(other) = owner[idx] {
+                return Err(BoardError::Invalid(format!(
+                    "GPIO{} is assigned to both `{other}` and `{name}`",
+                    pin.gpio
+                )));
+            }
+            owner[idx] = Some(name);
+            pins[idx] = encode_pin(pin.func, pin.pull.unwrap_or(PinPull::Default));
+        }
+        let mut atom = GpioMapAtom {
+            flags: 0,
+            power: 0,
+            pins,
+        };
+        atom.set_bank_flags(self.drive, self.slew, self.hysteresis, self.back_power)
+            .map_err(|e| BoardError::Invalid(e.to_string()))?;
+        Ok(atom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_settings;
+
+    const BASE: &str = r#"
+current_supply = 1500
+
+[vendor_info]
+uuid = "12345678-9abc-def0-1234-56789abcdef0"
+product_id = 0x0001
+product_ver = 0x0002
+vendor = "ACME"
+product = "Test HAT"
+
+[bank0]
+drive = 5
+back_power = 2
+
+[bank0.pins.button]
+gpio = 4
+func = "INPUT"
+pull = "UP"
+comment = "user button"
+
+[bank0.pins.led]
+gpio = 17
+func = "OUTPUT"
+
+[bank1.pins.cam]
+gpio = 40
+func = "ALT0"
+pull = "DOWN"
+
+[[atom]]
+string = "serial:1234"
+"#;
+
+    #[test]
+    fn matches_equivalent_settings_file() {
+        let board = parse_board(BASE, Path::new(".")).unwrap();
+        let mut settings = parse_settings(
+            "product_uuid 12345678-9abc-def0-1234-56789abcdef0
+product_id 0x0001
+product_ver 0x0002
+vendor \"ACME\"
+product \"Test HAT\"
+current_supply 1500
+gpio_drive 5
+back_power 2
+setgpio 4 INPUT UP
+setgpio 17 OUTPUT DEFAULT
+setgpio 40 ALT0 DOWN
+",
+        )
+        .unwrap();
+        settings.add_custom_atom(b"serial:1234".to_vec());
+        assert_eq!(board.serialize(), settings.serialize());
+    }
+
+    #[test]
+    fn extends_merges_and_resolves_paths() {
+        let dir = std::env::temp_dir().join(format!("ehatrom-board-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("base")).unwrap();
+        std::fs::write(dir.join("base/board.toml"), BASE).unwrap();
+        std::fs::write(dir.join("base/calib.bin"), [1u8, 2, 3]).unwrap();
+        let variant = r#"
+extends = "base/board.toml"
+
+[vendor_info]
+product_ver = 0x0003
+
+[bank0.pins.led]
+gpio = 18
+func = "OUTPUT"
+
+[[atom]]
+file = "base/calib.bin"
+"#;
+        let eeprom = parse_board(variant, &dir).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let product_ver = eeprom.vendor_info.product_ver;
+        assert_eq!(product_ver, 3);
+        assert_eq!(&eeprom.vendor_info.vendor[..4], b"ACME");
+        assert_eq!(eeprom.gpio_map_bank0.pins[17], 0);
+        assert_eq!(
+            eeprom.gpio_map_bank0.pins[18],
+            encode_pin(PinFunc::Output, PinPull::Default)
+        );
+        assert_eq!(eeprom.power_supply, Some(1500));
+        assert_eq!(eeprom.custom_atoms, [alloc::vec![1u8, 2, 3]]);
+    }
+
+    #[test]
+    fn rejects_duplicate_and_out_of_bank_pins() {
+        let dup = BASE.replace("gpio = 17", "gpio = 4");
+        let e = parse_board(&dup, Path::new(".")).unwrap_err();
+        assert!(e.to_string().contains("assigned to both"));
+
+        let wrong_bank = BASE.replace("gpio = 40", "gpio = 12");
+        let e = parse_board(&wrong_bank, Path::new(".")).unwrap_err();
+        assert!(e.to_string().contains("not in this bank"));
+
+        let bad_flags = BASE.replace("back_power = 2", "back_power = 3");
+        let e = parse_board(&bad_flags, Path::new(".")).unwrap_err();
+        assert!(e.to_string().contains("back_power out of range (0..2)"));
+    }
+}
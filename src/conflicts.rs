@@ -0,0 +1,360 @@
+//  _  _       _             _  _
+// | || |  ___| |_ _ __ ___ | || |
+// | || |_/ __| __| '_ ` _ \| || |_
+// |__   _\__ | |_| | | | | |__   _|
+//   |_| |___/\__|_|_|_| |_|  |_|
+//! # ehatrom — EEPROM HAT library for Raspberry Pi HATs
+//! - [Documentation (docs.rs)](https://docs.rs/ehatrom)
+//! - [GitHub](https://github.com/4stm4/ehatrom)
+//!
+//! ## Pin conflicts between stacked boards
+//!
+//! [`find_conflicts`] decodes the GPIO maps of several images with
+//! [`decode_pin`](crate::decode_pin) and reports every GPIO that more than one board claims.
+//! Boards that claim a pin the same way can share it, as HATs on one I2C bus
+//! do, and the pin is reported as [`ConflictKind::Shared`]. Different
+//! functions, two outputs, or opposing pulls are errors.
+//!
+//! Given the peripherals a `config.txt` turns on ([`enabled_peripherals`]),
+//! it also reports boards that claim their pins. A board that selects the
+//! peripheral's own function on the pin, such as `ALT0 (I2C1_SDA)` on GPIO 2,
+//! joins the bus rather than fighting it and is not reported. The ID EEPROM
+//! bus (GPIO 0/1) is always in the list.
+
+use crate::view::EepromView;
+use crate::{GPIO_COUNT, PinConfig, PinFunc, PinPull, Soc};
+use alloc::{string::String, vec::Vec};
+use core::fmt;
+
+/// A peripheral the Pi drives from its own GPIOs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Peripheral {
+    /// Name used in reports, e.g. `I2C1`.
+    pub name: &'static str,
+    /// BCM GPIO numbers it occupies.
+    pub pins: &'static [usize],
+    /// Prefix of the ALT function names that belong to it, e.g. `I2C1_`;
+    /// `None` if no board may claim the pins at all.
+    pub function: Option<&'static str>,
+}
+
+/// The HAT ID EEPROM bus, GPIO 0/1 (ID_SD/ID_SC).
+pub const ID_EEPROM: Peripheral = Peripheral {
+    name: "ID EEPROM bus",
+    pins: &[0, 1],
+    function: None,
+};
+/// `dtparam=i2c_arm=on`.
+pub const I2C1: Peripheral = Peripheral {
+    name: "I2C1",
+    pins: &[2, 3],
+    function: Some("I2C1_"),
+};
+/// `dtparam=spi=on`.
+pub const SPI0: Peripheral = Peripheral {
+    name: "SPI0",
+    pins: &[7, 8, 9, 10, 11],
+    function: Some("SPI0_"),
+};
+/// `enable_uart=1`. Either the PL011 or the mini UART may be on the pins.
+pub const UART_CONSOLE: Peripheral = Peripheral {
+    name: "UART console",
+    pins: &[14, 15],
+    function: Some("UART"),
+};
+/// `dtparam=i2s=on`.
+pub const I2S: Peripheral = Peripheral {
+    name: "I2S",
+    pins: &[18, 19, 20, 21],
+    function: Some("PCM_"),
+};
+
+/// The peripherals `config.txt` text enables, [`ID_EEPROM`] first.
+///
+/// Understands `dtparam=i2c_arm`/`i2c`/`i2c1`, `spi` and `i2s` (`=on`, or no
+/// value, and `=off`) and `enable_uart`. The last setting of a peripheral
+/// wins, as it does for the firmware. Conditional sections such as `[pi4]`
+/// are not evaluated; every line counts.
+pub fn enabled_peripherals(config: &str) -> Vec<Peripheral> {
+    let mut found = alloc::vec![ID_EEPROM];
+    let mut set = |p: Peripheral, on: bool| {
+        if !on {
+            found.retain(|&q| q != p);
+        } else if !found.contains(&p) {
+            found.push(p);
+        }
+    };
+    for line in config.lines() {
+        let line = line.split('#').next().unwrap_or("").trim();
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        match key.trim() {
+            "enable_uart" => set(UART_CONSOLE, value.trim() == "1"),
+            "dtparam" => {
+                for param in value.split(',') {
+                    let (name, on) = match param.split_once('=') {
+                        Some((name, v)) => (name.trim(), v.trim() == "on"),
+                        None => (param.trim(), true),
+                    };
+                    match name {
+                        "i2c_arm" | "i2c" | "i2c1" => set(I2C1, on),
+                        "spi" => set(SPI0, on),
+                        "i2s" => set(I2S, on),
+                        _ => {}
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    found
+}
+
+/// One board's configuration of a contested pin.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Claim {
+    /// Board name as passed to [`find_conflicts`].
+    pub board: String,
+    pub pin: PinConfig,
+    /// The ALT function name on the target SoC, if any.
+    pub function: Option<&'static str>,
+}
+
+impl fmt::Display for Claim {
+    /// `hat.bin ALT0 (I2C1_SDA), pull UP`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.board, self.pin.func.keyword())?;
+        if let Some(name) = self.function {
+            write!(f, " ({name})")?;
+        }
+        write!(f, ", pull {}", self.pin.pull.keyword())
+    }
+}
+
+/// What is wrong with a contested pin.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConflictKind {
+    /// Claimed identically by several boards; fine for a shared bus.
+    Shared,
+    /// The boards select different functions.
+    Function,
+    /// More than one board drives the pin as an output.
+    Output,
+    /// The boards ask for opposing pulls.
+    Pull,
+    /// A board claims a pin of an enabled Pi peripheral.
+    Peripheral(&'static str),
+}
+
+/// A GPIO claimed by more than one party.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Conflict {
+    /// BCM GPIO number.
+    pub gpio: usize,
+    pub kind: ConflictKind,
+    /// The boards claiming the pin, in input order.
+    pub claims: Vec<Claim>,
+}
+
+impl Conflict {
+    /// Everything but [`ConflictKind::Shared`] is an error.
+    pub fn is_error(&self) -> bool {
+        self.kind != ConflictKind::Shared
+    }
+}
+
+impl fmt::Display for Conflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let what = match self.kind {
+            ConflictKind::Shared => "shared by",
+            ConflictKind::Function => "different functions:",
+            ConflictKind::Output => "driven as an output by",
+            ConflictKind::Pull => "opposing pulls:",
+            ConflictKind::Peripheral(name) => {
+                write!(f, "GPIO {}: {name} pin claimed by", self.gpio)?;
+                return write_claims(f, &self.claims);
+            }
+        };
+        write!(f, "GPIO {}: {what}", self.gpio)?;
+        write_claims(f, &self.claims)
+    }
+}
+
+fn write_claims(f: &mut fmt::Formatter<'_>, claims: &[Claim]) -> fmt::Result {
+    for (i, claim) in claims.iter().enumerate() {
+        let sep = if i == 0 { " " } else { "; " };
+        write!(f, "{sep}{claim}")?;
+    }
+    Ok(())
+}
+
+/// Error returned by [`find_conflicts`] for an unreadable image.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ConflictError {
+    /// Index of the image in the input.
+    pub board: usize,
+    /// Human-readable reason.
+    pub reason: &'static str,
+}
+
+impl fmt::Display for ConflictError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "image {}: {}", self.board, self.reason)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ConflictError {}
+
+fn classify(claims: &[Claim]) -> ConflictKind {
+    let first = claims[0].pin;
+    if claims.iter().any(|c| c.pin.func != first.func) {
+        return ConflictKind::Function;
+    }
+    if first.func == PinFunc::Output {
+        return ConflictKind::Output;
+    }
+    let mut pulls = claims
+        .iter()
+        .map(|c| c.pin.pull)
+        .filter(|&p| p != PinPull::Default);
+    if let Some(pull) = pulls.next()
+        && pulls.any(|p| p != pull)
+    {
+        return ConflictKind::Pull;
+    }
+    ConflictKind::Shared
+}
+
+/// Finds the pins that several `boards` (name, image) claim, and the pins of
+/// `peripherals` that any board claims, naming ALT functions from `soc`.
+///
+/// Conflicts come in GPIO order.
+pub fn find_conflicts(
+    boards: &[(&str, &[u8])],
+    peripherals: &[Peripheral],
+    soc: Soc,
+) -> Result<Vec<Conflict>, ConflictError> {
+    let mut by_gpio: Vec<Vec<Claim>> = (0..GPIO_COUNT + crate::GPIO_COUNT_BANK1)
+        .map(|_| Vec::new())
+        .collect();
+    for (index, &(name, image)) in boards.iter().enumerate() {
+        let view = EepromView::new(image).map_err(|reason| ConflictError {
+            board: index,
+            reason,
+        })?;
+        for (gpio, pin) in view.used_pins() {
+            by_gpio[gpio].push(Claim {
+                board: name.into(),
+                pin,
+                function: soc.alt_function(gpio, pin.func),
+            });
+        }
+    }
+
+    let mut conflicts = Vec::new();
+    for (gpio, claims) in by_gpio.into_iter().enumerate() {
+        if let Some(peripheral) = peripherals.iter().find(|p| p.pins.contains(&gpio)) {
+            let fighting: Vec<Claim> = claims
+                .iter()
+                .filter(|c| match (peripheral.function, c.function) {
+                    (Some(prefix), Some(name)) => !name.starts_with(prefix),
+                    _ => true,
+                })
+                .cloned()
+                .collect();
+            if !fighting.is_empty() {
+                conflicts.push(Conflict {
+                    gpio,
+                    kind: ConflictKind::Peripheral(peripheral.name),
+                    claims: fighting,
+                });
+            }
+        }
+        if claims.len() > 1 {
+            conflicts.push(Conflict {
+                gpio,
+                kind: classify(&claims),
+                claims,
+            });
+        }
+    }
+    Ok(conflicts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{encode_pin, parse_settings};
+    use alloc::string::ToString;
+
+    fn image(pins: &str) -> Vec<u8> {
+        parse_settings(pins).unwrap().serialize()
+    }
+
+    #[test]
+    fn classifies_pins_claimed_by_several_boards() {
+        let a = image("setgpio 2 ALT0\nsetgpio 17 OUTPUT\nsetgpio 22 INPUT UP\nsetgpio 30 ALT3\n");
+        let b =
+            image("setgpio 2 ALT0\nsetgpio 17 OUTPUT\nsetgpio 22 INPUT DOWN\nsetgpio 30 INPUT\n");
+        let c = image("setgpio 5 OUTPUT\n");
+        let found = find_conflicts(&[("a", &a), ("b", &b), ("c", &c)], &[], Soc::Bcm2835).unwrap();
+        let kinds: Vec<_> = found.iter().map(|c| (c.gpio, c.kind)).collect();
+        assert_eq!(
+            kinds,
+            [
+                (2, ConflictKind::Shared),
+                (17, ConflictKind::Output),
+                (22, ConflictKind::Pull),
+                (30, ConflictKind::Function),
+            ]
+        );
+        assert!(!found[0].is_error() && found[1].is_error());
+        assert_eq!(
+            found[0].to_string(),
+            "GPIO 2: shared by a ALT0 (I2C1_SDA), pull DEFAULT; b ALT0 (I2C1_SDA), pull DEFAULT"
+        );
+
+        let bad = find_conflicts(&[("a", &a), ("x", &[0u8; 4])], &[], Soc::Bcm2835);
+        assert_eq!(bad.unwrap_err().board, 1);
+
+        // A bank1 atom with a full 28 pin bytes: the pins past GPIO 45 are
+        // not claims.
+        let mut long = image("setgpio 2 ALT0\n");
+        long.extend_from_slice(&[0x05, 0x00, 0x02, 0x00, 32, 0, 0, 0, 0, 0]);
+        long.extend_from_slice(&[encode_pin(PinFunc::Output, PinPull::Default); GPIO_COUNT]);
+        long.extend_from_slice(&[0, 0]);
+        long[6] += 1;
+        let (long, _) = crate::repair::repair(&long).unwrap();
+        let found = find_conflicts(&[("a", &a), ("long", &long)], &[], Soc::Bcm2835).unwrap();
+        assert_eq!(found.last().map(|c| c.gpio), Some(30));
+    }
+
+    #[test]
+    fn flags_pins_of_enabled_peripherals() {
+        let config = "# test\ndtparam=audio=on,i2c_arm=on\nenable_uart=1\ndtparam=spi=off\n";
+        let peripherals = enabled_peripherals(config);
+        assert_eq!(peripherals, [ID_EEPROM, I2C1, UART_CONSOLE]);
+        // The last setting wins.
+        let later = enabled_peripherals("dtparam=spi,i2s=on\ndtparam=i2c_arm=off,spi=off\n");
+        assert_eq!(later, [ID_EEPROM, I2S]);
+
+        let hat = image(
+            "setgpio 0 INPUT\nsetgpio 2 ALT0\nsetgpio 3 OUTPUT\nsetgpio 14 ALT5\nsetgpio 9 OUTPUT\n",
+        );
+        let found = find_conflicts(&[("hat", &hat)], &peripherals, Soc::Bcm2835).unwrap();
+        let kinds: Vec<_> = found.iter().map(|c| (c.gpio, c.kind)).collect();
+        assert_eq!(
+            kinds,
+            [
+                (0, ConflictKind::Peripheral("ID EEPROM bus")),
+                (3, ConflictKind::Peripheral("I2C1")),
+            ]
+        );
+        assert_eq!(
+            found[1].to_string(),
+            "GPIO 3: I2C1 pin claimed by hat OUTPUT, pull DEFAULT"
+        );
+    }
+}
@@ -0,0 +1,550 @@
+//  _  _       _             _  _
+// | || |  ___| |_ _ __ ___ | || |
+// | || |_/ __| __| '_ ` _ \| || |_
+// |__   _\__ | |_| | | | | |__   _|
+//   |_| |___/\__|_|_|_| |_|  |_|
+//! # ehatrom — EEPROM HAT library for Raspberry Pi HATs
+//! - [Documentation (docs.rs)](https://docs.rs/ehatrom)
+//! - [GitHub](https://github.com/4stm4/ehatrom)
+//!
+//! ## Typed custom-atom payloads
+//!
+//! Custom atoms (`0x0004`) are opaque bytes on the wire; the format has no
+//! sub-type. A [`CustomPayload`] gives a Rust type an encoding, and
+//! [`Eeprom::add_custom_payload`] / [`Eeprom::custom_payloads`] convert
+//! between the two. Since nothing in the atom says which type it holds,
+//! `decode` must reject anything that is not exactly its own encoding.
+//!
+//! A [`CodecRegistry`] is an ordered list of payload types used to describe
+//! custom atoms for display: the first codec that decodes an atom wins. The
+//! `Display` output of [`Eeprom`] (and so `ehatrom dump`) uses
+//! [`CodecRegistry::builtin`]; use [`Eeprom::display_with`] for your own. The
+//! built-in codecs, most specific first:
+//!
+//! - `AuthKeyAtom` — a secure-element public key (`sign` feature, see `ehatrom::auth`)
+//! - `SignatureAtom` — an Ed25519 image signature (`sign` feature, see `ehatrom::sign`)
+//! - [`ImageDigest`] — an embedded whole-image digest (see [`digest`](crate::digest))
+//! - [`Tlv`] — tagged records in the [`tlv`](crate::tlv) container format
+//! - [`KeyValues`] — `key=value` lines, each ending in `\n`
+//! - [`Text`] — UTF-8 text without control characters other than `\t`, `\r`, `\n`
+//!
+//! ```
+//! use ehatrom::custom::{CodecRegistry, CustomPayload};
+//!
+//! /// A serial number with its manufacture date.
+//! #[derive(Debug, PartialEq)]
+//! struct Serial {
+//!     number: u32,
+//!     year: u16,
+//!     week: u8,
+//! }
+//!
+//! impl CustomPayload for Serial {
+//!     const NAME: &'static str = "serial";
+//!
+//!     fn encode(&self) -> Vec<u8> {
+//!         let mut out = b"SN".to_vec();
+//!         out.extend_from_slice(&self.number.to_le_bytes());
+//!         out.extend_from_slice(&self.year.to_le_bytes());
+//!         out.push(self.week);
+//!         out
+//!     }
+//!
+//!     fn decode(data: &[u8]) -> Result<Self, &'static str> {
+//!         match data {
+//!             [b'S', b'N', n @ .., y0, y1, week] if n.len() == 4 => Ok(Serial {
+//!                 number: u32::from_le_bytes([n[0], n[1], n[2], n[3]]),
+//!                 year: u16::from_le_bytes([*y0, *y1]),
+//!                 week: *week,
+//!             }),
+//!             _ => Err("not a serial record"),
+//!         }
+//!     }
+//! }
+//!
+//! impl core::fmt::Display for Serial {
+//!     fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+//!         write!(f, "#{} made {}-W{:02}", self.number, self.year, self.week)
+//!     }
+//! }
+//!
+//! let mut eeprom = ehatrom::parse_settings("product_id 0x0001\n").unwrap();
+//! eeprom.add_custom_payload(&Serial { number: 42, year: 2026, week: 7 });
+//!
+//! let parsed = ehatrom::Eeprom::from_bytes(&eeprom.serialize()).unwrap();
+//! let serials: Vec<_> = parsed.custom_payloads::<Serial>().collect();
+//! assert_eq!(serials, [(0, Serial { number: 42, year: 2026, week: 7 })]);
+//!
+//! let mut codecs = CodecRegistry::builtin();
+//! codecs.register::<Serial>();
+//! assert!(parsed.display_with(&codecs).to_string().contains("serial: #42 made 2026-W07"));
+//! ```
+
+use crate::digest::ImageDigest;
+use crate::tlv::{self, TlvError, TlvWriter};
+use crate::{Eeprom, Soc};
+use alloc::{string::String, vec, vec::Vec};
+use core::fmt;
+
+/// A Rust type stored as the payload of a custom atom.
+pub trait CustomPayload: Sized {
+    /// Short name shown next to decoded atoms, e.g. in `dump`.
+    const NAME: &'static str;
+
+    /// The atom payload for this value.
+    fn encode(&self) -> Vec<u8>;
+
+    /// Parses a payload, rejecting anything `encode` would not produce.
+    fn decode(data: &[u8]) -> Result<Self, &'static str>;
+}
+
+/// UTF-8 text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Text(pub String);
+
+impl CustomPayload for Text {
+    const NAME: &'static str = "text";
+
+    fn encode(&self) -> Vec<u8> {
+        self.0.as_bytes().to_vec()
+    }
+
+    fn decode(data: &[u8]) -> Result<Self, &'static str> {
+        let text = core::str::from_utf8(data).map_err(|_| "not UTF-8")?;
+        if text.is_empty() {
+            return Err("empty text");
+        }
+        if text
+            .chars()
+            .any(|c| c.is_control() && !matches!(c, '\t' | '\r' | '\n'))
+        {
+            return Err("control characters in text");
+        }
+        Ok(Text(text.into()))
+    }
+}
+
+impl fmt::Display for Text {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "\"{}\"", self.0.escape_debug())
+    }
+}
+
+/// `key=value` pairs, encoded as one `key=value\n` line each. Keys are ASCII
+/// letters, digits, `_`, `-` and `.`; values are any text without control
+/// characters.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyValues(pub Vec<(String, String)>);
+
+impl KeyValues {
+    /// The value of the first pair named `key`.
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.0
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+impl CustomPayload for KeyValues {
+    const NAME: &'static str = "key=value";
+
+    fn encode(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        for (key, value) in &self.0 {
+            out.extend_from_slice(key.as_bytes());
+            out.push(b'=');
+            out.extend_from_slice(value.as_bytes());
+            out.push(b'\n');
+        }
+        out
+    }
+
+    fn decode(data: &[u8]) -> Result<Self, &'static str> {
+        let text = core::str::from_utf8(data).map_err(|_| "not UTF-8")?;
+        let body = text.strip_suffix('\n').ok_or("missing final newline")?;
+        let mut pairs = Vec::new();
+        for line in body.split('\n') {
+            let (key, value) = line.split_once('=').ok_or("line without `=`")?;
+            let key_ok = !key.is_empty()
+                && key
+                    .bytes()
+                    .all(|b| b.is_ascii_alphanumeric() || b"_-.".contains(&b));
+            if !key_ok {
+                return Err("invalid key");
+            }
+            if value.chars().any(char::is_control) {
+                return Err("control characters in value");
+            }
+            pairs.push((key.into(), value.into()));
+        }
+        Ok(KeyValues(pairs))
+    }
+}
+
+impl fmt::Display for KeyValues {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, (key, value)) in self.0.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{key}={value}")?;
+        }
+        Ok(())
+    }
+}
+
+/// One record of a [`Tlv`] container.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TlvRecord {
+    /// The tag, `0x0000`–`0x7FFF`.
+    pub tag: u16,
+    pub value: Vec<u8>,
+    /// Whether the record carries its own CRC-16.
+    pub crc: bool,
+}
+
+/// Tagged records in the [`tlv`] container format. The payload must hold at
+/// least one record and nothing else, and every record CRC must match.
+///
+/// Records are checked when they are pushed (tag up to `0x7FFF`, value up to
+/// 65535 bytes), so encoding cannot fail.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Tlv(Vec<TlvRecord>);
+
+impl Tlv {
+    /// An empty container.
+    pub fn new() -> Self {
+        Tlv(Vec::new())
+    }
+
+    /// The records, in order.
+    pub fn records(&self) -> &[TlvRecord] {
+        &self.0
+    }
+
+    /// Appends a record without a CRC.
+    pub fn push(&mut self, tag: u16, value: impl Into<Vec<u8>>) -> Result<&mut Self, TlvError> {
+        self.push_record(tag, value.into(), false)
+    }
+
+    /// Appends a record followed by its CRC-16.
+    pub fn push_with_crc(
+        &mut self,
+        tag: u16,
+        value: impl Into<Vec<u8>>,
+    ) -> Result<&mut Self, TlvError> {
+        self.push_record(tag, value.into(), true)
+    }
+
+    fn push_record(&mut self, tag: u16, value: Vec<u8>, crc: bool) -> Result<&mut Self, TlvError> {
+        if tag & tlv::CRC_FLAG != 0 {
+            return Err(TlvError::TagOutOfRange(tag));
+        }
+        if value.len() > usize::from(u16::MAX) {
+            return Err(TlvError::ValueTooLong(value.len()));
+        }
+        self.0.push(TlvRecord { tag, value, crc });
+        Ok(self)
+    }
+
+    /// The value of the first record with `tag`.
+    pub fn get(&self, tag: u16) -> Option<&[u8]> {
+        self.0
+            .iter()
+            .find(|r| r.tag == tag)
+            .map(|r| r.value.as_slice())
+    }
+}
+
+impl CustomPayload for Tlv {
+    const NAME: &'static str = "tlv";
+
+    fn encode(&self) -> Vec<u8> {
+        let size = self
+            .0
+            .iter()
+            .map(|r| tlv::record_len(r.value.len(), r.crc))
+            .sum();
+        let mut out = vec![0; size];
+        let mut writer = TlvWriter::new(&mut out);
+        for record in &self.0 {
+            let pushed = if record.crc {
+                writer.push_with_crc(record.tag, &record.value)
+            } else {
+                writer.push(record.tag, &record.value)
+            };
+            // Tags and lengths were checked by `push`, and `out` is sized
+            // for every record.
+            debug_assert!(pushed.is_ok());
+        }
+        out
+    }
+
+    fn decode(data: &[u8]) -> Result<Self, &'static str> {
+        tlv::validate(data).map_err(|e| match e {
+            TlvError::Empty => "no records",
+            TlvError::BadCrc { .. } => "record CRC mismatch",
+            _ => "truncated record",
+        })?;
+        Ok(Tlv(tlv::records(data)
+            .flatten()
+            .map(|r| TlvRecord {
+                tag: r.tag,
+                value: r.value.to_vec(),
+                crc: r.crc.is_some(),
+            })
+            .collect()))
+    }
+}
+
+impl fmt::Display for Tlv {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, record) in self.0.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "0x{:04X}", record.tag)?;
+            if record.crc {
+                write!(f, "+crc")?;
+            }
+            match Text::decode(&record.value) {
+                Ok(text) => write!(f, "={text}")?,
+                Err(_) => write!(f, "={:02X?}", record.value)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl CustomPayload for ImageDigest {
+    const NAME: &'static str = "digest";
+
+    fn encode(&self) -> Vec<u8> {
+        self.to_atom().to_vec()
+    }
+
+    fn decode(data: &[u8]) -> Result<Self, &'static str> {
+        ImageDigest::from_atom(data).ok_or("not a digest atom")
+    }
+}
+
+#[cfg(feature = "sign")]
+impl CustomPayload for crate::sign::SignatureAtom {
+    const NAME: &'static str = "signature";
+
+    fn encode(&self) -> Vec<u8> {
+        self.to_atom().to_vec()
+    }
+
+    fn decode(data: &[u8]) -> Result<Self, &'static str> {
+        Self::from_atom(data).ok_or("not a signature atom")
+    }
+}
+
+#[cfg(feature = "sign")]
+impl CustomPayload for crate::auth::AuthKeyAtom {
+    const NAME: &'static str = "auth-key";
+
+    fn encode(&self) -> Vec<u8> {
+        self.to_atom().to_vec()
+    }
+
+    fn decode(data: &[u8]) -> Result<Self, &'static str> {
+        Self::from_atom(data).ok_or("not an authentication key atom")
+    }
+}
+
+/// Describes `data` as `T` if it decodes.
+fn describe<T: CustomPayload + fmt::Display>(data: &[u8]) -> Option<String> {
+    T::decode(data).ok().map(|v| alloc::format!("{v}"))
+}
+
+/// Renders a payload if it decodes as the codec's type.
+type Describe = fn(&[u8]) -> Option<String>;
+
+/// An ordered list of payload types tried when displaying custom atoms.
+#[derive(Clone)]
+pub struct CodecRegistry {
+    codecs: Vec<(&'static str, Describe)>,
+}
+
+impl fmt::Debug for CodecRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list()
+            .entries(self.codecs.iter().map(|(name, _)| name))
+            .finish()
+    }
+}
+
+impl CodecRegistry {
+    /// A registry with no codecs.
+    pub fn new() -> Self {
+        CodecRegistry { codecs: Vec::new() }
+    }
+
+    /// The built-in codecs: `AuthKeyAtom` and `SignatureAtom` (with the
+    /// `sign` feature), [`ImageDigest`], [`Tlv`], [`KeyValues`], then [`Text`].
+    pub fn builtin() -> Self {
+        let mut registry = CodecRegistry::new();
+        registry.register::<Text>();
+        registry.register::<KeyValues>();
+        registry.register::<Tlv>();
+        registry.register::<ImageDigest>();
+        #[cfg(feature = "sign")]
+        registry.register::<crate::sign::SignatureAtom>();
+        #[cfg(feature = "sign")]
+        registry.register::<crate::auth::AuthKeyAtom>();
+        registry
+    }
+
+    /// Adds `T` ahead of the codecs already registered, so the most recently
+    /// registered (usually most specific) type is tried first.
+    pub fn register<T: CustomPayload + fmt::Display>(&mut self) {
+        self.codecs.insert(0, (T::NAME, describe::<T>));
+    }
+
+    /// The name of the first codec that decodes `data`, and the decoded value
+    /// rendered with its `Display` impl.
+    pub fn describe(&self, data: &[u8]) -> Option<(&'static str, String)> {
+        self.codecs
+            .iter()
+            .find_map(|(name, describe)| describe(data).map(|text| (*name, text)))
+    }
+}
+
+impl Default for CodecRegistry {
+    fn default() -> Self {
+        CodecRegistry::builtin()
+    }
+}
+
+/// `Display` adapter returned by [`Eeprom::display_with`].
+pub struct EepromDisplay<'a> {
+    eeprom: &'a Eeprom,
+    codecs: &'a CodecRegistry,
+    soc: Soc,
+    colour: bool,
+}
+
+impl EepromDisplay<'_> {
+    /// Names ALT pin functions from `soc`'s table instead of the BCM2835's.
+    pub fn soc(mut self, soc: Soc) -> Self {
+        self.soc = soc;
+        self
+    }
+
+    /// Colours the GPIO table (see [`PinTable::colour`](crate::PinTable::colour)).
+    pub fn colour(mut self, colour: bool) -> Self {
+        self.colour = colour;
+        self
+    }
+}
+
+impl fmt::Display for EepromDisplay<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.eeprom
+            .write_summary(f, self.soc, self.colour, self.codecs)
+    }
+}
+
+impl Eeprom {
+    /// Appends a custom atom holding `payload`.
+    pub fn add_custom_payload<T: CustomPayload>(&mut self, payload: &T) {
+        self.add_custom_atom(payload.encode());
+    }
+
+    /// The custom atoms that decode as `T`, with their index in
+    /// [`Eeprom::custom_atoms`].
+    pub fn custom_payloads<T: CustomPayload>(&self) -> impl Iterator<Item = (usize, T)> + '_ {
+        self.custom_atoms
+            .iter()
+            .enumerate()
+            .filter_map(|(i, data)| T::decode(data).ok().map(|v| (i, v)))
+    }
+
+    /// Like the `Display` impl, but describes custom atoms with `codecs`.
+    pub fn display_with<'a>(&'a self, codecs: &'a CodecRegistry) -> EepromDisplay<'a> {
+        EepromDisplay {
+            eeprom: self,
+            codecs,
+            soc: Soc::Bcm2835,
+            colour: false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_settings;
+    use alloc::string::ToString;
+
+    #[test]
+    fn builtin_codecs_round_trip_and_are_told_apart() {
+        let mut tlv = Tlv::new();
+        tlv.push(0x0001, vec![1, 2, 3])
+            .unwrap()
+            .push_with_crc(0x7FFF, b"v2")
+            .unwrap();
+        assert_eq!(tlv.push(0x8000, b"x"), Err(TlvError::TagOutOfRange(0x8000)));
+        assert_eq!(
+            tlv.push_with_crc(1, vec![0; 65536]),
+            Err(TlvError::ValueTooLong(65536))
+        );
+        assert_eq!(tlv.records().len(), 2);
+        let kv = KeyValues(vec![
+            ("serial".into(), "42".into()),
+            ("date".into(), "2026-10-19".into()),
+        ]);
+        let text = Text("Made in Tallinn".into());
+
+        let codecs = CodecRegistry::builtin();
+        let described = |data: &[u8]| codecs.describe(data).map(|(name, _)| name);
+        assert_eq!(described(&tlv.encode()), Some("tlv"));
+        assert_eq!(described(&kv.encode()), Some("key=value"));
+        assert_eq!(described(&text.encode()), Some("text"));
+        assert_eq!(described(&[0xFF, 0x00, 0x10]), None);
+
+        assert_eq!(Tlv::decode(&tlv.encode()).unwrap(), tlv);
+        assert_eq!(KeyValues::decode(&kv.encode()).unwrap(), kv);
+        assert_eq!(Text::decode(&text.encode()).unwrap(), text);
+        assert_eq!(kv.get("date"), Some("2026-10-19"));
+        assert_eq!(tlv.get(0x7FFF), Some(&b"v2"[..]));
+        assert_eq!(tlv.to_string(), "0x0001=[01, 02, 03], 0x7FFF+crc=\"v2\"");
+        assert!(Tlv::decode(&[1, 0, 5, 0, 1]).is_err());
+        let mut corrupt = tlv.encode();
+        corrupt[11] ^= 1;
+        assert_eq!(Tlv::decode(&corrupt), Err("record CRC mismatch"));
+        assert!(KeyValues::decode(b"no equals\n").is_err());
+    }
+
+    #[test]
+    fn custom_atoms_decode_through_eeprom() {
+        let mut eeprom = parse_settings("product_id 0x0001\n").unwrap();
+        eeprom.add_custom_payload(&KeyValues(vec![("serial".into(), "42".into())]));
+        eeprom.add_custom_atom(vec![0xFF, 0x00]);
+        eeprom.add_custom_payload(&Text("hello".into()));
+        let mut tlv = Tlv::new();
+        tlv.push(1, "SN-42")
+            .unwrap()
+            .push_with_crc(2, [7, 0xEA])
+            .unwrap();
+        eeprom.add_custom_payload(&tlv);
+
+        let parsed = Eeprom::from_bytes(&eeprom.serialize()).unwrap();
+        let texts: Vec<_> = parsed.custom_payloads::<Text>().collect();
+        // The key=value atom is valid text too; the registry order decides
+        // what `dump` calls it.
+        assert_eq!(texts.len(), 2);
+        assert_eq!(texts[1], (2, Text("hello".into())));
+
+        let dump = parsed.to_string();
+        assert!(dump.contains("  [0] 10 bytes, key=value: serial=42\n"));
+        assert!(dump.contains("  [1] 2 bytes: [FF, 00]\n"));
+        assert!(dump.contains("  [2] 5 bytes, text: \"hello\"\n"));
+        assert!(dump.contains("  [3] 17 bytes, tlv: 0x0001=\"SN-42\", 0x0002+crc=[07, EA]\n"));
+        let plain = parsed.display_with(&CodecRegistry::new()).to_string();
+        assert!(plain.contains("  [2] 5 bytes: [68, 65, 6C, 6C, 6F]\n"));
+    }
+}
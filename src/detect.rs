@@ -20,7 +20,7 @@ use alloc::{
 };
 
 #[cfg(feature = "std")]
-use std::{print, println};
+use std::{eprintln, print, println};
 
 #[cfg(all(feature = "linux", any(target_os = "linux", target_os = "android")))]
 fn print_header_diagnostics(buf: &[u8], buffer_len: usize) {
@@ -56,6 +56,11 @@ fn print_header_diagnostics(buf: &[u8], buffer_len: usize) {
     }
 }
 
+/// Largest EEPROM a HAT carries (a 24C512). A header announcing more is
+/// corrupt, so reads sized from `eeplen` stop here.
+#[cfg(all(feature = "linux", any(target_os = "linux", target_os = "android")))]
+const MAX_EEPROM_LEN: usize = 64 * 1024;
+
 #[cfg(all(feature = "linux", any(target_os = "linux", target_os = "android")))]
 fn read_eeprom_with_dynamic_buffer(
     dev_path: &str,
@@ -70,8 +75,9 @@ fn read_eeprom_with_dynamic_buffer(
 
     let eeplen = if &header_buf[0..4] == b"R-Pi" {
         Some(
-            u32::from_le_bytes([header_buf[8], header_buf[9], header_buf[10], header_buf[11]])
-                as usize,
+            (u32::from_le_bytes([header_buf[8], header_buf[9], header_buf[10], header_buf[11]])
+                as usize)
+                .min(MAX_EEPROM_LEN),
         )
     } else {
         None
@@ -90,6 +96,35 @@ fn read_eeprom_with_dynamic_buffer(
     Ok(buf)
 }
 
+/// Reads the HAT EEPROM image from `dev_path` (address 0x50).
+///
+/// Only the `eeplen` bytes announced by the header are read, at most 64 KiB;
+/// an image whose `eeplen` claims more comes back cut at that size for
+/// `repair` to fix. If the signature is missing, just the 12 header bytes are
+/// returned so callers can report what they found.
+#[cfg(all(feature = "linux", any(target_os = "linux", target_os = "android")))]
+pub fn read_eeprom_image(dev_path: &str) -> Result<Vec<u8>, crate::EhatromError> {
+    let mut buf = read_eeprom_with_dynamic_buffer(dev_path, 0x50, 12)?;
+    if &buf[0..4] == b"R-Pi" {
+        let eeplen = u32::from_le_bytes([buf[8], buf[9], buf[10], buf[11]]) as usize;
+        buf.truncate(eeplen.max(12));
+    }
+    Ok(buf)
+}
+
+/// Reads the HAT image like [`read_eeprom_image`] and runs `check` on it
+/// (e.g. [`auth::authenticate`](crate::auth::authenticate)) before returning
+/// it, so callers never see an image that failed the check.
+#[cfg(all(feature = "linux", any(target_os = "linux", target_os = "android")))]
+pub fn read_eeprom_image_checked<E: From<crate::EhatromError>>(
+    dev_path: &str,
+    check: impl FnOnce(&[u8]) -> Result<(), E>,
+) -> Result<Vec<u8>, E> {
+    let image = read_eeprom_image(dev_path)?;
+    check(&image)?;
+    Ok(image)
+}
+
 #[cfg(all(feature = "linux", any(target_os = "linux", target_os = "android")))]
 /// Scans the provided I2C device, printing parsed EEPROM details if found.
 ///
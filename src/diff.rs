@@ -0,0 +1,470 @@
+//  _  _       _             _  _
+// | || |  ___| |_ _ __ ___ | || |
+// | || |_/ __| __| '_ ` _ \| || |_
+// |__   _\__ | |_| | | | | |__   _|
+//   |_| |___/\__|_|_|_| |_|  |_|
+//! # ehatrom — EEPROM HAT library for Raspberry Pi HATs
+//! - [Documentation (docs.rs)](https://docs.rs/ehatrom)
+//! - [GitHub](https://github.com/4stm4/ehatrom)
+//!
+//! ## Semantic and raw diff of two EEPROM images
+//!
+//! [`diff_images`] walks [`atoms`](crate::atoms) on both sides and reports
+//! what changed in HAT terms: header fields, atoms added or removed, vendor
+//! fields, GPIO bank flags, per-pin configuration (decoded with
+//! [`decode_pin`](crate::decode_pin)), the power-supply current, and byte
+//! ranges for opaque payloads (device-tree blob, custom atoms). Atoms are
+//! paired by type and by their position among atoms of that type, so an
+//! inserted custom atom does not make every following atom look different.
+//!
+//! [`hexdump_diff`] is the raw fallback: a hexdump of the 16-byte rows that
+//! differ.
+
+use crate::settings::format_uuid;
+use crate::{AtomRef, AtomType, GPIO_COUNT, GPIO_COUNT_BANK1, GpioMapAtom, VendorInfoAtom};
+use crate::{HEADER_SIZE, atoms, decode_pin};
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::fmt::{self, Write as _};
+use core::ops::Range;
+
+/// One difference between two images, as reported by [`diff_images`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Change {
+    /// A 12-byte header field differs (`signature` is shown as a `u32`).
+    Header {
+        field: &'static str,
+        old: u32,
+        new: u32,
+    },
+    /// An atom exists only in the old image.
+    AtomRemoved {
+        atom_type: u16,
+        index: usize,
+        len: usize,
+    },
+    /// An atom exists only in the new image.
+    AtomAdded {
+        atom_type: u16,
+        index: usize,
+        len: usize,
+    },
+    /// A vendor-info field differs; values are rendered as text.
+    Vendor {
+        field: &'static str,
+        old: String,
+        new: String,
+    },
+    /// A GPIO bank flag (`drive`, `slew`, `hysteresis`, `back_power`) differs.
+    GpioFlag {
+        bank: u8,
+        field: &'static str,
+        old: u8,
+        new: u8,
+    },
+    /// A pin byte differs; `gpio` is the BCM number.
+    Pin {
+        bank: u8,
+        gpio: u8,
+        old: u8,
+        new: u8,
+    },
+    /// The power-supply atom value differs.
+    PowerSupply { old: u32, new: u32 },
+    /// An opaque payload (device-tree blob, custom or unknown atom) differs in
+    /// the listed byte ranges, relative to the start of the payload.
+    Data {
+        atom_type: u16,
+        index: usize,
+        old_len: usize,
+        new_len: usize,
+        ranges: Vec<Range<usize>>,
+    },
+}
+
+/// Short name of an atom type, as used in diff and dump output.
+pub(crate) fn atom_name(atom_type: u16) -> &'static str {
+    match AtomType::from(atom_type) {
+        AtomType::VendorInfo => "vendor_info",
+        AtomType::GpioMapBank0 => "gpio_bank0",
+        AtomType::DtBlob => "dt_blob",
+        AtomType::Custom => "custom",
+        AtomType::GpioMapBank1 => "gpio_bank1",
+        AtomType::PowerSupply => "power_supply",
+        AtomType::Unknown => "unknown",
+    }
+}
+
+/// Renders a pin byte as `FUNC,PULL`, `unused`, or `raw 0xNN` when the byte is
+/// not something `setgpio` could have produced.
+pub(crate) fn pin_text(byte: u8) -> String {
+    let cfg = decode_pin(byte);
+    if byte == 0 {
+        "unused".to_string()
+    } else if cfg.used && crate::encode_pin(cfg.func, cfg.pull) == byte {
+        format!("{:?},{:?}", cfg.func, cfg.pull).to_uppercase()
+    } else {
+        format!("raw 0x{byte:02X}")
+    }
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Change::Header { field, old, new } => write!(f, "header.{field}: {old} -> {new}"),
+            Change::AtomRemoved {
+                atom_type,
+                index,
+                len,
+            } => write!(
+                f,
+                "- atom {}#{index} (type 0x{atom_type:04X}) removed ({len} bytes)",
+                atom_name(*atom_type)
+            ),
+            Change::AtomAdded {
+                atom_type,
+                index,
+                len,
+            } => write!(
+                f,
+                "+ atom {}#{index} (type 0x{atom_type:04X}) added ({len} bytes)",
+                atom_name(*atom_type)
+            ),
+            Change::Vendor { field, old, new } => {
+                write!(f, "vendor_info.{field}: {old} -> {new}")
+            }
+            Change::GpioFlag {
+                bank,
+                field,
+                old,
+                new,
+            } => write!(f, "gpio.bank{bank}.{field}: {old} -> {new}"),
+            Change::Pin {
+                bank,
+                gpio,
+                old,
+                new,
+            } => write!(
+                f,
+                "gpio.bank{bank}.pin[{gpio}]: {} -> {}",
+                pin_text(*old),
+                pin_text(*new)
+            ),
+            Change::PowerSupply { old, new } => {
+                write!(f, "power_supply: {old} mA -> {new} mA")
+            }
+            Change::Data {
+                atom_type,
+                index,
+                old_len,
+                new_len,
+                ranges,
+            } => {
+                write!(f, "{}#{index}: ", atom_name(*atom_type))?;
+                if old_len != new_len {
+                    write!(f, "length {old_len} -> {new_len}, ")?;
+                }
+                write!(f, "{} byte range(s) differ:", ranges.len())?;
+                for r in ranges {
+                    write!(f, " 0x{:04X}..0x{:04X}", r.start, r.end)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Compares two serialized images and lists their semantic differences.
+///
+/// An empty result means the images are equivalent atom for atom. Atoms whose
+/// payload cannot be decoded (e.g. a truncated vendor atom) are compared as
+/// raw data instead.
+pub fn diff_images(old: &[u8], new: &[u8]) -> Vec<Change> {
+    let mut changes = Vec::new();
+    diff_header(old, new, &mut changes);
+
+    let old_atoms: Vec<AtomRef<'_>> = atoms(old).collect();
+    let new_atoms: Vec<AtomRef<'_>> = atoms(new).collect();
+
+    // Visit atom types in order of first appearance (old image first).
+    let mut types: Vec<u16> = Vec::new();
+    for a in old_atoms.iter().chain(new_atoms.iter()) {
+        if !types.contains(&a.atom_type) {
+            types.push(a.atom_type);
+        }
+    }
+    for atom_type in types {
+        let olds: Vec<&AtomRef<'_>> = old_atoms
+            .iter()
+            .filter(|a| a.atom_type == atom_type)
+            .collect();
+        let news: Vec<&AtomRef<'_>> = new_atoms
+            .iter()
+            .filter(|a| a.atom_type == atom_type)
+            .collect();
+        for index in 0..olds.len().max(news.len()) {
+            match (olds.get(index), news.get(index)) {
+                (Some(a), Some(b)) => diff_atom(atom_type, index, a.data, b.data, &mut changes),
+                (Some(a), None) => changes.push(Change::AtomRemoved {
+                    atom_type,
+                    index,
+                    len: a.data.len(),
+                }),
+                (None, Some(b)) => changes.push(Change::AtomAdded {
+                    atom_type,
+                    index,
+                    len: b.data.len(),
+                }),
+                (None, None) => {}
+            }
+        }
+    }
+    changes
+}
+
+fn diff_header(old: &[u8], new: &[u8], out: &mut Vec<Change>) {
+    let field = |d: &[u8], range: Range<usize>| -> u32 {
+        let mut v = [0u8; 4];
+        if let Some(src) = d.get(range.clone()) {
+            v[..src.len()].copy_from_slice(src);
+        }
+        u32::from_le_bytes(v)
+    };
+    let fields: [(&'static str, Range<usize>); 5] = [
+        ("signature", 0..4),
+        ("version", 4..5),
+        ("reserved", 5..6),
+        ("numatoms", 6..8),
+        ("eeplen", 8..HEADER_SIZE),
+    ];
+    for (name, range) in fields {
+        let (a, b) = (field(old, range.clone()), field(new, range));
+        if a != b {
+            out.push(Change::Header {
+                field: name,
+                old: a,
+                new: b,
+            });
+        }
+    }
+}
+
+fn diff_atom(atom_type: u16, index: usize, a: &[u8], b: &[u8], out: &mut Vec<Change>) {
+    if a == b {
+        return;
+    }
+    match AtomType::from(atom_type) {
+        AtomType::VendorInfo => {
+            if let (Some(va), Some(vb)) = (VendorInfoAtom::decode(a), VendorInfoAtom::decode(b)) {
+                diff_vendor(&va, &vb, out);
+                return;
+            }
+        }
+        AtomType::GpioMapBank0 | AtomType::GpioMapBank1 => {
+            if let (Some(ga), Some(gb)) = (GpioMapAtom::decode(a), GpioMapAtom::decode(b)) {
+                let bank1 = atom_type == AtomType::GpioMapBank1 as u16;
+                diff_gpio(bank1, &ga, &gb, out);
+                if a.len() == b.len() {
+                    return;
+                }
+            }
+        }
+        AtomType::PowerSupply if a.len() == 4 && b.len() == 4 => {
+            out.push(Change::PowerSupply {
+                old: u32::from_le_bytes([a[0], a[1], a[2], a[3]]),
+                new: u32::from_le_bytes([b[0], b[1], b[2], b[3]]),
+            });
+            return;
+        }
+        _ => {}
+    }
+    out.push(Change::Data {
+        atom_type,
+        index,
+        old_len: a.len(),
+        new_len: b.len(),
+        ranges: byte_ranges(a, b),
+    });
+}
+
+fn diff_vendor(a: &VendorInfoAtom, b: &VendorInfoAtom, out: &mut Vec<Change>) {
+    let text = |buf: &[u8; 16]| {
+        let len = buf.iter().position(|&c| c == 0).unwrap_or(16);
+        format!("{:?}", String::from_utf8_lossy(&buf[..len]))
+    };
+    let mut push = |field, old: String, new: String| {
+        if old != new {
+            out.push(Change::Vendor { field, old, new });
+        }
+    };
+    push("uuid", format_uuid(&a.uuid), format_uuid(&b.uuid));
+    let (pa, pb) = (a.product_id, b.product_id);
+    push("product_id", format!("0x{pa:04X}"), format!("0x{pb:04X}"));
+    let (va, vb) = (a.product_ver, b.product_ver);
+    push("product_ver", format!("0x{va:04X}"), format!("0x{vb:04X}"));
+    push("vendor", text(&a.vendor), text(&b.vendor));
+    push("product", text(&a.product), text(&b.product));
+}
+
+fn diff_gpio(bank1: bool, a: &GpioMapAtom, b: &GpioMapAtom, out: &mut Vec<Change>) {
+    let (bank, base, len) = if bank1 {
+        (1, GPIO_COUNT as u8, GPIO_COUNT_BANK1)
+    } else {
+        (0, 0, GPIO_COUNT)
+    };
+    let flags = |g: &GpioMapAtom| {
+        [
+            ("drive", g.flags & 0x0F),
+            ("slew", (g.flags >> 4) & 0x03),
+            ("hysteresis", (g.flags >> 6) & 0x03),
+            ("back_power", g.power),
+        ]
+    };
+    for ((field, old), (_, new)) in flags(a).into_iter().zip(flags(b)) {
+        if old != new {
+            out.push(Change::GpioFlag {
+                bank,
+                field,
+                old,
+                new,
+            });
+        }
+    }
+    for i in 0..len {
+        let (old, new) = (a.pins[i], b.pins[i]);
+        if old != new {
+            out.push(Change::Pin {
+                bank,
+                gpio: base + i as u8,
+                old,
+                new,
+            });
+        }
+    }
+}
+
+/// Returns the coalesced byte ranges where `a` and `b` differ; a length
+/// difference shows up as a final range covering the longer tail.
+pub fn byte_ranges(a: &[u8], b: &[u8]) -> Vec<Range<usize>> {
+    let mut ranges: Vec<Range<usize>> = Vec::new();
+    let common = a.len().min(b.len());
+    for i in (0..common).filter(|&i| a[i] != b[i]) {
+        match ranges.last_mut() {
+            Some(r) if r.end == i => r.end = i + 1,
+            _ => ranges.push(i..i + 1),
+        }
+    }
+    let longest = a.len().max(b.len());
+    if common < longest {
+        match ranges.last_mut() {
+            Some(r) if r.end == common => r.end = longest,
+            _ => ranges.push(common..longest),
+        }
+    }
+    ranges
+}
+
+/// Raw diff: every 16-byte row that differs, as a `-`/`+` hexdump pair.
+///
+/// Bytes past the end of the shorter image are shown as `--`.
+pub fn hexdump_diff(old: &[u8], new: &[u8]) -> String {
+    let mut out = String::new();
+    let len = old.len().max(new.len());
+    for row in (0..len).step_by(16) {
+        let end = (row + 16).min(len);
+        if old.get(row..end.min(old.len())) == new.get(row..end.min(new.len())) {
+            continue;
+        }
+        for (sign, data) in [('-', old), ('+', new)] {
+            let _ = write!(out, "{sign}{row:08x}:");
+            for i in row..end {
+                match data.get(i) {
+                    Some(b) => {
+                        let _ = write!(out, " {b:02x}");
+                    }
+                    None => out.push_str(" --"),
+                }
+            }
+            out.push('\n');
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{PinFunc, PinPull, encode_pin, parse_settings};
+
+    const SETTINGS: &str = "\
+product_uuid 12345678-9abc-def0-1234-56789abcdef0
+product_id 0x0001
+product_ver 0x0002
+vendor \"ACME\"
+product \"Test HAT\"
+setgpio 17 OUTPUT UP
+";
+
+    #[test]
+    fn identical_images_have_no_changes() {
+        let img = parse_settings(SETTINGS).unwrap().serialize();
+        assert!(diff_images(&img, &img).is_empty());
+        assert!(hexdump_diff(&img, &img).is_empty());
+    }
+
+    #[test]
+    fn reports_vendor_pin_and_blob_changes() {
+        let mut a = parse_settings(SETTINGS).unwrap();
+        a.add_dt_blob(alloc::vec![0u8; 32]);
+        let mut b = a.clone();
+        b.vendor_info.product_ver = 3;
+        b.gpio_map_bank0.pins[17] = 0;
+        b.gpio_map_bank0.pins[4] = encode_pin(PinFunc::Input, PinPull::Down);
+        let mut blob = alloc::vec![0u8; 32];
+        blob[4..6].copy_from_slice(&[1, 1]);
+        blob[20] = 9;
+        b.add_dt_blob(blob);
+        b.add_custom_atom(b"serial".to_vec());
+
+        let changes = diff_images(&a.serialize(), &b.serialize());
+        let text: Vec<String> = changes.iter().map(|c| c.to_string()).collect();
+        assert!(text.contains(&"header.numatoms: 3 -> 4".to_string()));
+        assert!(text.contains(&"vendor_info.product_ver: 0x0002 -> 0x0003".to_string()));
+        assert!(text.contains(&"gpio.bank0.pin[4]: unused -> INPUT,DOWN".to_string()));
+        assert!(text.contains(&"gpio.bank0.pin[17]: OUTPUT,UP -> unused".to_string()));
+        assert!(text.contains(
+            &"dt_blob#0: 2 byte range(s) differ: 0x0004..0x0006 0x0014..0x0015".to_string()
+        ));
+        assert!(matches!(
+            changes.last(),
+            Some(Change::AtomAdded {
+                atom_type: 0x0004,
+                index: 0,
+                len: 6
+            })
+        ));
+    }
+
+    #[test]
+    fn byte_ranges_cover_length_change() {
+        assert_eq!(byte_ranges(b"abcd", b"abXdEF"), [2..3, 4..6]);
+        assert_eq!(byte_ranges(b"abc", b"abX"), alloc::vec![2..3]);
+        assert_eq!(byte_ranges(b"abc", b"abXY"), alloc::vec![2..4]);
+    }
+
+    #[test]
+    fn hexdump_marks_only_differing_rows() {
+        let a = [0u8; 40];
+        let mut b = a;
+        b[18] = 0xAB;
+        let dump = hexdump_diff(&a, &b[..36]);
+        let lines: Vec<&str> = dump.lines().collect();
+        assert_eq!(lines.len(), 4);
+        assert!(lines[0].starts_with("-00000010: 00 00 00"));
+        assert!(lines[1].starts_with("+00000010: 00 00 ab"));
+        assert!(lines[3].ends_with("00 00 00 00 -- -- -- --"));
+    }
+}
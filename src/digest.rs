@@ -0,0 +1,238 @@
+//  _  _       _             _  _
+// | || |  ___| |_ _ __ ___ | || |
+// | || |_/ __| __| '_ ` _ \| || |_
+// |__   _\__ | |_| | | | | |__   _|
+//   |_| |___/\__|_|_|_| |_|  |_|
+//! # ehatrom — EEPROM HAT library for Raspberry Pi HATs
+//! - [Documentation (docs.rs)](https://docs.rs/ehatrom)
+//! - [GitHub](https://github.com/4stm4/ehatrom)
+//!
+//! ## Whole-image digests
+//!
+//! The HAT format only has per-atom CRC-16s. [`image_digest`] fingerprints a
+//! whole image with CRC-32 (IEEE) and SHA-256 over exactly the `eeplen` bytes
+//! the header declares, so trailing padding or EEPROM contents past the image
+//! do not change it. `ehatrom hash` prints it.
+//!
+//! An image can also carry its own digest in a trailing custom atom
+//! ([`embed_digest`], `ehatrom hash --embed`). Its payload is
+//! [`DIGEST_MAGIC`], the CRC-32 (little-endian) and the SHA-256, 40 bytes in
+//! all. The digest covers every byte before that atom, with the header
+//! already counting it in `numatoms` and `eeplen`. [`check_embedded`] (and
+//! `ehatrom verify`) recompute it. The atom has to be the last one; anything
+//! that rewrites the image, such as `edit`, leaves a stale digest that
+//! `verify` reports, until `hash --embed` is run again.
+
+use crate::utils::{crc32, sha256::Sha256};
+use crate::{AtomType, HEADER_SIZE, atoms};
+use core::fmt;
+
+/// First bytes of an embedded digest atom payload.
+pub const DIGEST_MAGIC: [u8; 4] = *b"EHD1";
+/// Payload length of an embedded digest atom.
+pub const DIGEST_ATOM_LEN: usize = DIGEST_MAGIC.len() + 4 + 32;
+
+/// CRC-32 and SHA-256 of an image.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ImageDigest {
+    pub crc32: u32,
+    pub sha256: [u8; 32],
+}
+
+impl ImageDigest {
+    /// Digests `bytes` as given.
+    pub fn of(bytes: &[u8]) -> Self {
+        let mut crc = crc32::Hasher::new();
+        crc.update(bytes);
+        let mut sha = Sha256::new();
+        sha.update(bytes);
+        ImageDigest {
+            crc32: crc.finalize(),
+            sha256: sha.finalize(),
+        }
+    }
+
+    /// Parses an embedded digest atom payload.
+    pub fn from_atom(data: &[u8]) -> Option<Self> {
+        let rest = data.strip_prefix(&DIGEST_MAGIC)?;
+        if rest.len() != DIGEST_ATOM_LEN - DIGEST_MAGIC.len() {
+            return None;
+        }
+        Some(ImageDigest {
+            crc32: u32::from_le_bytes([rest[0], rest[1], rest[2], rest[3]]),
+            sha256: rest[4..].try_into().ok()?,
+        })
+    }
+
+    /// The embedded digest atom payload.
+    pub fn to_atom(&self) -> [u8; DIGEST_ATOM_LEN] {
+        let mut out = [0u8; DIGEST_ATOM_LEN];
+        out[..4].copy_from_slice(&DIGEST_MAGIC);
+        out[4..8].copy_from_slice(&self.crc32.to_le_bytes());
+        out[8..].copy_from_slice(&self.sha256);
+        out
+    }
+}
+
+impl fmt::Display for ImageDigest {
+    /// `crc32:<8 hex digits> sha256:<64 hex digits>`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "crc32:{:08x} sha256:", self.crc32)?;
+        for byte in self.sha256 {
+            write!(f, "{byte:02x}")?;
+        }
+        Ok(())
+    }
+}
+
+/// The `eeplen` bytes of `data` that make up the image.
+pub fn image_bytes(data: &[u8]) -> Result<&[u8], &'static str> {
+    if data.len() < HEADER_SIZE {
+        return Err("Not enough data for EEPROM header");
+    }
+    if data[0..4] != crate::EEPROM_SIGNATURE {
+        return Err("Invalid EEPROM signature");
+    }
+    let eeplen = u32::from_le_bytes([data[8], data[9], data[10], data[11]]) as usize;
+    if eeplen < HEADER_SIZE || eeplen > data.len() {
+        return Err("eeplen does not fit the data");
+    }
+    Ok(&data[..eeplen])
+}
+
+/// Digests the `eeplen` bytes of an image.
+pub fn image_digest(data: &[u8]) -> Result<ImageDigest, &'static str> {
+    image_bytes(data).map(ImageDigest::of)
+}
+
+/// An embedded digest and its offset, if the last atom is a digest atom.
+fn find_embedded(data: &[u8]) -> Option<(usize, ImageDigest)> {
+    let (offset, atom) = atoms(data).with_offsets().last()?;
+    if atom.kind() != AtomType::Custom {
+        return None;
+    }
+    ImageDigest::from_atom(atom.data).map(|digest| (offset, digest))
+}
+
+/// The outcome of [`check_embedded`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DigestCheck {
+    /// The image has no digest atom.
+    Absent,
+    /// The embedded digest matches.
+    Valid(ImageDigest),
+    /// The image changed after the digest was embedded.
+    Mismatch {
+        stored: ImageDigest,
+        computed: ImageDigest,
+    },
+}
+
+/// Recomputes an embedded digest and compares it with the stored one.
+pub fn check_embedded(data: &[u8]) -> DigestCheck {
+    match find_embedded(data) {
+        None => DigestCheck::Absent,
+        Some((offset, stored)) => {
+            let computed = ImageDigest::of(&data[..offset]);
+            if computed == stored {
+                DigestCheck::Valid(stored)
+            } else {
+                DigestCheck::Mismatch { stored, computed }
+            }
+        }
+    }
+}
+
+/// Appends a digest atom to the `eeplen` bytes of `data`, replacing one that
+/// is already there, and updates `numatoms` and `eeplen`. Returns the new
+/// image and the embedded digest.
+#[cfg(feature = "alloc")]
+pub fn embed_digest(data: &[u8]) -> Result<(alloc::vec::Vec<u8>, ImageDigest), &'static str> {
+    let data = image_bytes(data)?;
+    let mut numatoms = u16::from_le_bytes([data[6], data[7]]);
+    let prefix = match find_embedded(data) {
+        Some((offset, _)) => {
+            numatoms -= 1;
+            &data[..offset]
+        }
+        None => data,
+    };
+    let next = numatoms.checked_add(1).ok_or("too many atoms")?;
+    let total = prefix.len() + crate::ATOM_HDR_SIZE + DIGEST_ATOM_LEN + crate::CRC_SIZE;
+    let mut out = alloc::vec![0u8; total];
+    out[..prefix.len()].copy_from_slice(prefix);
+    out[6..8].copy_from_slice(&next.to_le_bytes());
+    out[8..12].copy_from_slice(&(total as u32).to_le_bytes());
+    let digest = ImageDigest::of(&out[..prefix.len()]);
+    let mut offset = prefix.len();
+    let mut count = numatoms;
+    crate::write_atom(
+        &mut out,
+        &mut offset,
+        &mut count,
+        AtomType::Custom as u16,
+        &digest.to_atom(),
+    )
+    .map_err(|_| "digest atom does not fit")?;
+    Ok((out, digest))
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    use super::*;
+    use crate::{ATOM_HDR_SIZE, Eeprom, parse_settings};
+    use alloc::{string::ToString, vec::Vec};
+
+    fn image() -> Vec<u8> {
+        parse_settings("product_id 0x0001\nvendor \"ACME\"\nsetgpio 4 INPUT UP\n")
+            .unwrap()
+            .serialize()
+    }
+
+    #[test]
+    fn digest_covers_exactly_eeplen() {
+        let image = image();
+        let digest = image_digest(&image).unwrap();
+        let mut padded = image.clone();
+        padded.extend_from_slice(&[0xFF; 64]);
+        assert_eq!(image_digest(&padded).unwrap(), digest);
+        assert_eq!(digest, ImageDigest::of(&image));
+        assert!(digest.to_string().starts_with("crc32:"));
+        assert_eq!(
+            digest.to_string().len(),
+            "crc32:".len() + 8 + " sha256:".len() + 64
+        );
+        assert_eq!(
+            image_digest(&image[..20]),
+            Err("eeplen does not fit the data")
+        );
+    }
+
+    #[test]
+    fn embedded_digest_is_checked_and_replaced() {
+        let image = image();
+        assert_eq!(check_embedded(&image), DigestCheck::Absent);
+
+        let (signed, digest) = embed_digest(&image).unwrap();
+        assert!(Eeprom::verify(&signed));
+        assert_eq!(check_embedded(&signed), DigestCheck::Valid(digest));
+        let parsed = Eeprom::from_bytes(&signed).unwrap();
+        assert_eq!(parsed.custom_atoms.len(), 1);
+
+        // Re-embedding replaces the digest atom instead of stacking another.
+        let (again, _) = embed_digest(&signed).unwrap();
+        assert_eq!(again, signed);
+
+        let mut edited = signed.clone();
+        edited[HEADER_SIZE + ATOM_HDR_SIZE + 16] ^= 1; // product_id
+        assert!(matches!(
+            check_embedded(&edited),
+            DigestCheck::Mismatch { stored, .. } if stored == digest
+        ));
+
+        // A header claiming the maximum atom count has no room for one more.
+        let mut full = image.clone();
+        full[6..8].copy_from_slice(&u16::MAX.to_le_bytes());
+        assert_eq!(embed_digest(&full), Err("too many atoms"));
+    }
+}
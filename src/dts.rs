@@ -0,0 +1,1637 @@
+//  _  _       _             _  _
+// | || |  ___| |_ _ __ ___ | || |
+// | || |_/ __| __| '_ ` _ \| || |_
+// |__   _\__ | |_| | | | | |__   _|
+//   |_| |___/\__|_|_|_| |_|  |_|
+//! # ehatrom — EEPROM HAT library for Raspberry Pi HATs
+//! - [Documentation (docs.rs)](https://docs.rs/ehatrom)
+//! - [GitHub](https://github.com/4stm4/ehatrom)
+//!
+//! ## Device-tree source (DTS) overlay compiler and decompiler
+//!
+//! [`compile_dts`] turns overlay source into a flattened device tree whose
+//! output can go straight into [`Eeprom::add_dt_blob`](crate::Eeprom::add_dt_blob),
+//! so building an EEPROM does not need `dtc`. The accepted language is the
+//! subset HAT overlays use:
+//!
+//! - `/dts-v1/;`, `/plugin/;`, `//` and `/* */` comments
+//! - nodes with unit addresses and `label:` prefixes, and repeated node
+//!   definitions, which are merged
+//! - `&label { ... };` and `&{/path} { ... };` blocks. With `/plugin/` each
+//!   becomes a `fragment@N` with `target` / `target-path` and an
+//!   `__overlay__` node; without it the block is merged into the labelled node.
+//! - properties: strings and string lists, `<cells>` (decimal, hex, octal,
+//!   character literals, `(expressions)`, `&label` and `&{/path}` phandle
+//!   references), `/bits/ 8|16|64 <...>`, `[bytes]`, `&label` path references,
+//!   and any comma-separated mix of those
+//!
+//! Nodes that are the target of a phandle reference get a `phandle` property.
+//! With `/plugin/`, references to labels outside the overlay are left as
+//! `0xffffffff` and recorded in `__fixups__`, and local references in
+//! `__local_fixups__`. Every label is also exported in `__symbols__`, as
+//! `dtc -@` does, so the firmware can apply the overlay and later overlays can
+//! refer to it. The C preprocessor (`#include`, `#define`), `/include/`,
+//! `/memreserve/` and `/delete-node/` are not supported.
+//!
+//! [`decompile_dtb`] goes the other way, printing an embedded DT blob as
+//! readable source for `dump --dts` and `extract dt`.
+
+use crate::fdt::{FDT_MAGIC, Fdt, FdtError, Property, Token};
+use alloc::{
+    borrow::ToOwned,
+    collections::BTreeMap,
+    format,
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+
+/// Error returned by [`compile_dts`], carrying the 1-based source line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DtsError {
+    /// 1-based line number where compilation failed.
+    pub line: usize,
+    /// Human-readable reason.
+    pub reason: String,
+}
+
+impl core::fmt::Display for DtsError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "dts error on line {}: {}", self.line, self.reason)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for DtsError {}
+
+/// Compiles device-tree source into a flattened device tree (version 17).
+pub fn compile_dts(source: &str) -> Result<Vec<u8>, DtsError> {
+    let mut parser = Parser {
+        src: source.as_bytes(),
+        pos: 0,
+        line: 1,
+        depth: 0,
+    };
+    let unit = parser.unit()?;
+    let mut root = unit.root;
+    if unit.plugin {
+        add_fragments(&mut root, unit.refs);
+    } else {
+        for (line, target, node) in unit.refs {
+            let labels = collect_labels(&root)?;
+            let path = find(&root, &labels, &target).ok_or_else(|| DtsError {
+                line,
+                reason: format!("reference to undefined {target}"),
+            })?;
+            merge(node_mut(&mut root, &path), node);
+        }
+    }
+    resolve(&mut root, unit.plugin)?;
+    Ok(flatten(&root))
+}
+
+/// Where a reference points.
+#[derive(Debug, Clone)]
+enum Target {
+    Label(String),
+    Path(String),
+}
+
+impl core::fmt::Display for Target {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Target::Label(l) => write!(f, "label `{l}`"),
+            Target::Path(p) => write!(f, "path `{p}`"),
+        }
+    }
+}
+
+/// A piece of a property value; references are resolved after parsing.
+#[derive(Debug, Clone)]
+enum Piece {
+    Bytes(Vec<u8>),
+    /// A 32-bit phandle cell.
+    Phandle(Target),
+    /// A NUL-terminated path string.
+    Path(Target),
+}
+
+#[derive(Debug, Clone)]
+struct Prop {
+    name: String,
+    value: Vec<Piece>,
+    line: usize,
+}
+
+#[derive(Debug, Clone, Default)]
+struct Node {
+    name: String,
+    /// Line of the (first) definition, for error messages.
+    line: usize,
+    labels: Vec<String>,
+    props: Vec<Prop>,
+    children: Vec<Node>,
+}
+
+impl Node {
+    fn new(name: &str) -> Self {
+        Node {
+            name: name.to_string(),
+            ..Node::default()
+        }
+    }
+
+    fn set_prop(&mut self, prop: Prop) {
+        match self.props.iter_mut().find(|p| p.name == prop.name) {
+            Some(p) => *p = prop,
+            None => self.props.push(prop),
+        }
+    }
+
+    /// The child called `name`, created with `line` if there is none.
+    fn child_mut(&mut self, name: &str, line: usize) -> &mut Node {
+        let idx = match self.children.iter().position(|c| c.name == name) {
+            Some(i) => i,
+            None => {
+                self.children.push(Node {
+                    line,
+                    ..Node::new(name)
+                });
+                self.children.len() - 1
+            }
+        };
+        &mut self.children[idx]
+    }
+}
+
+/// Merges `from` into `into`: later properties win, same-named children merge.
+fn merge(into: &mut Node, from: Node) {
+    if into.line == 0 {
+        into.line = from.line;
+    }
+    for label in from.labels {
+        if !into.labels.contains(&label) {
+            into.labels.push(label);
+        }
+    }
+    for prop in from.props {
+        into.set_prop(prop);
+    }
+    for child in from.children {
+        match into.children.iter_mut().find(|c| c.name == child.name) {
+            Some(existing) => merge(existing, child),
+            None => into.children.push(child),
+        }
+    }
+}
+
+struct Unit {
+    plugin: bool,
+    root: Node,
+    refs: Vec<(usize, Target, Node)>,
+}
+
+struct Parser<'a> {
+    src: &'a [u8],
+    pos: usize,
+    line: usize,
+    /// Current nesting of nodes and expressions, bounded by [`MAX_DEPTH`].
+    depth: usize,
+}
+
+/// Deepest nesting of nodes or parenthesised/unary expressions accepted, so
+/// hostile input cannot overflow the stack.
+const MAX_DEPTH: usize = 256;
+
+fn is_name_char(c: u8) -> bool {
+    c.is_ascii_alphanumeric() || b",._+*#?@-".contains(&c)
+}
+
+fn is_label_char(c: u8) -> bool {
+    c.is_ascii_alphanumeric() || c == b'_'
+}
+
+impl Parser<'_> {
+    fn err<T>(&self, reason: impl Into<String>) -> Result<T, DtsError> {
+        Err(DtsError {
+            line: self.line,
+            reason: reason.into(),
+        })
+    }
+
+    /// Runs `f` one nesting level deeper, failing past [`MAX_DEPTH`].
+    fn nested<T>(
+        &mut self,
+        f: impl FnOnce(&mut Self) -> Result<T, DtsError>,
+    ) -> Result<T, DtsError> {
+        if self.depth == MAX_DEPTH {
+            return self.err("nesting too deep");
+        }
+        self.depth += 1;
+        let result = f(self);
+        self.depth -= 1;
+        result
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.src.get(self.pos).copied()
+    }
+
+    fn starts_with(&self, s: &str) -> bool {
+        self.src[self.pos..].starts_with(s.as_bytes())
+    }
+
+    fn bump(&mut self) -> Option<u8> {
+        let c = self.peek()?;
+        self.pos += 1;
+        if c == b'\n' {
+            self.line += 1;
+        }
+        Some(c)
+    }
+
+    /// Skips whitespace and comments.
+    fn skip(&mut self) -> Result<(), DtsError> {
+        loop {
+            match self.peek() {
+                Some(c) if c.is_ascii_whitespace() => {
+                    self.bump();
+                }
+                Some(b'/') if self.starts_with("//") => {
+                    while !matches!(self.peek(), None | Some(b'\n')) {
+                        self.bump();
+                    }
+                }
+                Some(b'/') if self.starts_with("/*") => {
+                    let line = self.line;
+                    self.pos += 2;
+                    while !self.starts_with("*/") {
+                        if self.bump().is_none() {
+                            return Err(DtsError {
+                                line,
+                                reason: "unterminated comment".into(),
+                            });
+                        }
+                    }
+                    self.pos += 2;
+                }
+                _ => return Ok(()),
+            }
+        }
+    }
+
+    fn eat(&mut self, c: u8) -> Result<bool, DtsError> {
+        self.skip()?;
+        if self.peek() == Some(c) {
+            self.bump();
+            Ok(true)
+        } else {
+            Ok(false)
+        }
+    }
+
+    fn expect(&mut self, c: u8) -> Result<(), DtsError> {
+        if self.eat(c)? {
+            Ok(())
+        } else {
+            self.err(format!("expected `{}`", c as char))
+        }
+    }
+
+    fn keyword(&mut self, kw: &str) -> Result<bool, DtsError> {
+        self.skip()?;
+        if self.starts_with(kw) {
+            self.pos += kw.len();
+            Ok(true)
+        } else {
+            Ok(false)
+        }
+    }
+
+    fn take_while(&mut self, pred: fn(u8) -> bool) -> String {
+        let start = self.pos;
+        while self.peek().is_some_and(pred) {
+            self.pos += 1;
+        }
+        String::from_utf8_lossy(&self.src[start..self.pos]).into_owned()
+    }
+
+    fn unit(&mut self) -> Result<Unit, DtsError> {
+        let mut unit = Unit {
+            plugin: false,
+            root: Node::new(""),
+            refs: Vec::new(),
+        };
+        loop {
+            self.skip()?;
+            match self.peek() {
+                None => return Ok(unit),
+                Some(b'#') => {
+                    return self.err(
+                        "preprocessor directives are not supported; run the source through cpp first",
+                    );
+                }
+                Some(b'/') if self.keyword("/dts-v1/")? => self.expect(b';')?,
+                Some(b'/') if self.keyword("/plugin/")? => {
+                    unit.plugin = true;
+                    self.expect(b';')?;
+                }
+                Some(b'/')
+                    if self
+                        .src
+                        .get(self.pos + 1)
+                        .is_some_and(|&c| c == b'{' || c.is_ascii_whitespace()) =>
+                {
+                    self.pos += 1;
+                    let mut node = Node::new("");
+                    node.line = self.line;
+                    self.body(&mut node)?;
+                    merge(&mut unit.root, node);
+                }
+                Some(b'&') => {
+                    let line = self.line;
+                    self.pos += 1;
+                    let target = self.target()?;
+                    let mut node = Node::new("");
+                    node.line = line;
+                    self.body(&mut node)?;
+                    unit.refs.push((line, target, node));
+                }
+                Some(b'/') => {
+                    let word = self.take_while(|c| c != b';' && !c.is_ascii_whitespace());
+                    return self.err(format!("unsupported directive `{word}`"));
+                }
+                _ => return self.err("expected `/ {`, `&label {` or a `/...` directive"),
+            }
+        }
+    }
+
+    /// Parses the part after `&`: `label` or `{/path}`.
+    fn target(&mut self) -> Result<Target, DtsError> {
+        if self.peek() == Some(b'{') {
+            self.pos += 1;
+            let path = self.take_while(|c| c != b'}' && !c.is_ascii_whitespace());
+            self.expect(b'}')?;
+            if !path.starts_with('/') {
+                return self.err("expected an absolute path in `&{...}`");
+            }
+            Ok(Target::Path(path))
+        } else {
+            let label = self.take_while(is_label_char);
+            if label.is_empty() {
+                return self.err("expected a label after `&`");
+            }
+            Ok(Target::Label(label))
+        }
+    }
+
+    /// Parses `{ ... };` into `node`.
+    fn body(&mut self, node: &mut Node) -> Result<(), DtsError> {
+        self.expect(b'{')?;
+        loop {
+            if self.eat(b'}')? {
+                return self.expect(b';');
+            }
+            if self.peek().is_none() {
+                return self.err("unexpected end of input inside a node");
+            }
+            if self.peek() == Some(b'/') {
+                let word = self.take_while(|c| c != b';' && !c.is_ascii_whitespace());
+                return self.err(format!("unsupported directive `{word}`"));
+            }
+            let mut labels = Vec::new();
+            let name = loop {
+                let word = self.take_while(is_name_char);
+                if word.is_empty() {
+                    return self.err("expected a node or property name");
+                }
+                if self.peek() == Some(b':') && word.bytes().all(is_label_char) {
+                    self.pos += 1;
+                    labels.push(word);
+                    self.skip()?;
+                } else {
+                    break word;
+                }
+            };
+            let line = self.line;
+            self.skip()?;
+            match self.peek() {
+                Some(b'{') => {
+                    let mut child = Node::new(&name);
+                    child.line = line;
+                    child.labels = labels;
+                    self.nested(|p| p.body(&mut child))?;
+                    let existing = node.child_mut(&name, line);
+                    merge(existing, child);
+                }
+                Some(b'=') => {
+                    self.pos += 1;
+                    let value = self.values()?;
+                    self.expect(b';')?;
+                    node.set_prop(Prop { name, value, line });
+                }
+                Some(b';') => {
+                    self.pos += 1;
+                    node.set_prop(Prop {
+                        name,
+                        value: Vec::new(),
+                        line,
+                    });
+                }
+                _ => return self.err(format!("expected `{{`, `=` or `;` after `{name}`")),
+            }
+        }
+    }
+
+    fn values(&mut self) -> Result<Vec<Piece>, DtsError> {
+        let mut out = Vec::new();
+        loop {
+            self.skip()?;
+            match self.peek() {
+                Some(b'"') => {
+                    let mut s = self.string()?;
+                    s.push(0);
+                    out.push(Piece::Bytes(s));
+                }
+                Some(b'<') => self.cells(32, &mut out)?,
+                Some(b'[') => out.push(Piece::Bytes(self.bytes()?)),
+                Some(b'&') => {
+                    self.pos += 1;
+                    out.push(Piece::Path(self.target()?));
+                }
+                Some(b'/') if self.keyword("/bits/")? => {
+                    self.skip()?;
+                    let bits = self.number()?;
+                    if !matches!(bits, 8 | 16 | 32 | 64) {
+                        return self.err("/bits/ must be 8, 16, 32 or 64");
+                    }
+                    self.skip()?;
+                    self.cells(bits as u32, &mut out)?;
+                }
+                _ => return self.err("expected a property value"),
+            }
+            if !self.eat(b',')? {
+                return Ok(out);
+            }
+        }
+    }
+
+    fn string(&mut self) -> Result<Vec<u8>, DtsError> {
+        self.pos += 1; // opening quote
+        let mut out = Vec::new();
+        loop {
+            if matches!(self.peek(), None | Some(b'\n')) {
+                return self.err("unterminated string");
+            }
+            match self.bump() {
+                Some(b'"') => return Ok(out),
+                Some(b'\\') => out.push(self.escape()?),
+                Some(c) => out.push(c),
+                None => unreachable!(),
+            }
+        }
+    }
+
+    fn escape(&mut self) -> Result<u8, DtsError> {
+        let c = self.bump().unwrap_or(0);
+        Ok(match c {
+            b'n' => b'\n',
+            b't' => b'\t',
+            b'r' => b'\r',
+            b'a' => 0x07,
+            b'b' => 0x08,
+            b'f' => 0x0C,
+            b'v' => 0x0B,
+            b'x' => {
+                let mut v = 0u32;
+                for _ in 0..2 {
+                    match self.peek().and_then(|d| (d as char).to_digit(16)) {
+                        Some(d) => {
+                            v = v * 16 + d;
+                            self.pos += 1;
+                        }
+                        None => break,
+                    }
+                }
+                v as u8
+            }
+            b'0'..=b'7' => {
+                let mut v = (c - b'0') as u32;
+                for _ in 0..2 {
+                    match self.peek().filter(|d| (b'0'..=b'7').contains(d)) {
+                        Some(d) => {
+                            v = v * 8 + (d - b'0') as u32;
+                            self.pos += 1;
+                        }
+                        None => break,
+                    }
+                }
+                v as u8
+            }
+            other => other,
+        })
+    }
+
+    fn bytes(&mut self) -> Result<Vec<u8>, DtsError> {
+        self.pos += 1; // `[`
+        let mut out = Vec::new();
+        loop {
+            self.skip()?;
+            match self.peek() {
+                Some(b']') => {
+                    self.pos += 1;
+                    return Ok(out);
+                }
+                Some(_) => {
+                    let hi = self.hex_digit()?;
+                    let lo = self.hex_digit()?;
+                    out.push((hi << 4) | lo);
+                }
+                None => return self.err("unterminated byte string"),
+            }
+        }
+    }
+
+    fn hex_digit(&mut self) -> Result<u8, DtsError> {
+        match self.peek().and_then(|c| (c as char).to_digit(16)) {
+            Some(d) => {
+                self.pos += 1;
+                Ok(d as u8)
+            }
+            None => self.err("expected two hex digits per byte in `[...]`"),
+        }
+    }
+
+    /// Parses `<...>` with `bits`-wide elements, appending to `out`.
+    fn cells(&mut self, bits: u32, out: &mut Vec<Piece>) -> Result<(), DtsError> {
+        self.expect(b'<')?;
+        loop {
+            self.skip()?;
+            let value = match self.peek() {
+                Some(b'>') => {
+                    self.pos += 1;
+                    return Ok(());
+                }
+                Some(b'&') => {
+                    if bits != 32 {
+                        return self.err("phandle references need 32-bit cells");
+                    }
+                    self.pos += 1;
+                    out.push(Piece::Phandle(self.target()?));
+                    continue;
+                }
+                Some(b'(') => {
+                    self.pos += 1;
+                    let v = self.expr(0)?;
+                    self.expect(b')')?;
+                    v
+                }
+                Some(b'\'') => self.char_literal()?,
+                Some(c) if c.is_ascii_digit() => self.number()?,
+                _ => return self.err("expected a cell value"),
+            };
+            let max = if bits == 64 {
+                u64::MAX
+            } else {
+                (1u64 << bits) - 1
+            };
+            // Negative expressions wrap to the cell width, as in dtc.
+            let value = if value > max && (value as i64) < 0 && (value as i64) >= -(max as i64) {
+                value & max
+            } else {
+                value
+            };
+            if value > max {
+                return self.err(format!("value 0x{value:x} does not fit in {bits} bits"));
+            }
+            let be = value.to_be_bytes();
+            out.push(Piece::Bytes(be[8 - (bits / 8) as usize..].to_vec()));
+        }
+    }
+
+    fn char_literal(&mut self) -> Result<u64, DtsError> {
+        self.pos += 1;
+        let c = match self.bump() {
+            Some(b'\\') => self.escape()?,
+            Some(c) => c,
+            None => return self.err("unterminated character literal"),
+        };
+        if self.bump() != Some(b'\'') {
+            return self.err("unterminated character literal");
+        }
+        Ok(c as u64)
+    }
+
+    fn number(&mut self) -> Result<u64, DtsError> {
+        let text = self.take_while(|c| c.is_ascii_alphanumeric());
+        let digits = text.trim_end_matches(['u', 'U', 'l', 'L']);
+        let parsed = if let Some(hex) = digits
+            .strip_prefix("0x")
+            .or_else(|| digits.strip_prefix("0X"))
+        {
+            u64::from_str_radix(hex, 16)
+        } else if digits.len() > 1 && digits.starts_with('0') {
+            u64::from_str_radix(&digits[1..], 8)
+        } else {
+            digits.parse()
+        };
+        match parsed {
+            Ok(v) => Ok(v),
+            Err(_) => self.err(format!("invalid number `{text}`")),
+        }
+    }
+
+    /// Precedence-climbing integer expression inside `( ... )`.
+    fn expr(&mut self, min_prec: u8) -> Result<u64, DtsError> {
+        let mut lhs = self.unary()?;
+        loop {
+            self.skip()?;
+            let Some((op, prec)) = BINARY_OPS
+                .iter()
+                .find(|(op, _)| self.starts_with(op) && !self.is_longer_op(op))
+                .copied()
+            else {
+                return Ok(lhs);
+            };
+            if prec < min_prec {
+                return Ok(lhs);
+            }
+            self.pos += op.len();
+            let rhs = self.expr(prec + 1)?;
+            lhs = match op {
+                "||" => ((lhs != 0) || (rhs != 0)) as u64,
+                "&&" => ((lhs != 0) && (rhs != 0)) as u64,
+                "|" => lhs | rhs,
+                "^" => lhs ^ rhs,
+                "&" => lhs & rhs,
+                "==" => (lhs == rhs) as u64,
+                "!=" => (lhs != rhs) as u64,
+                "<=" => (lhs <= rhs) as u64,
+                ">=" => (lhs >= rhs) as u64,
+                "<" => (lhs < rhs) as u64,
+                ">" => (lhs > rhs) as u64,
+                "<<" => lhs.wrapping_shl(rhs as u32),
+                ">>" => lhs.wrapping_shr(rhs as u32),
+                "+" => lhs.wrapping_add(rhs),
+                "-" => lhs.wrapping_sub(rhs),
+                "*" => lhs.wrapping_mul(rhs),
+                _ if rhs == 0 => return self.err("division by zero"),
+                "/" => lhs / rhs,
+                _ => lhs % rhs,
+            };
+        }
+    }
+
+    /// Whether a longer operator starting with `op` is at the cursor (e.g. `<<`
+    /// when looking for `<`).
+    fn is_longer_op(&self, op: &str) -> bool {
+        op.len() == 1
+            && BINARY_OPS
+                .iter()
+                .any(|(o, _)| o.len() == 2 && o.starts_with(op) && self.starts_with(o))
+    }
+
+    fn unary(&mut self) -> Result<u64, DtsError> {
+        self.skip()?;
+        match self.peek() {
+            Some(b'-') => {
+                self.pos += 1;
+                Ok(self.nested(Self::unary)?.wrapping_neg())
+            }
+            Some(b'~') => {
+                self.pos += 1;
+                Ok(!self.nested(Self::unary)?)
+            }
+            Some(b'!') => {
+                self.pos += 1;
+                Ok((self.nested(Self::unary)? == 0) as u64)
+            }
+            Some(b'(') => {
+                self.pos += 1;
+                let v = self.nested(|p| p.expr(0))?;
+                self.expect(b')')?;
+                Ok(v)
+            }
+            Some(b'\'') => self.char_literal(),
+            Some(c) if c.is_ascii_digit() => self.number(),
+            _ => self.err("expected an expression"),
+        }
+    }
+}
+
+/// Binary operators and their precedence (higher binds tighter). Two-character
+/// operators come first so they are matched before their prefixes.
+const BINARY_OPS: [(&str, u8); 18] = [
+    ("||", 1),
+    ("&&", 2),
+    ("==", 6),
+    ("!=", 6),
+    ("<=", 7),
+    (">=", 7),
+    ("<<", 8),
+    (">>", 8),
+    ("|", 3),
+    ("^", 4),
+    ("&", 5),
+    ("<", 7),
+    (">", 7),
+    ("+", 9),
+    ("-", 9),
+    ("*", 10),
+    ("/", 10),
+    ("%", 10),
+];
+
+/// Wraps each `&target { ... }` block in a `fragment@N` for `/plugin/` mode.
+fn add_fragments(root: &mut Node, refs: Vec<(usize, Target, Node)>) {
+    let mut n = 0;
+    for (line, target, node) in refs {
+        let mut name = format!("fragment@{n}");
+        while root.children.iter().any(|c| c.name == name) {
+            n += 1;
+            name = format!("fragment@{n}");
+        }
+        n += 1;
+        let mut frag = Node::new(&name);
+        frag.props.push(match target {
+            Target::Path(path) => {
+                let mut bytes = path.into_bytes();
+                bytes.push(0);
+                Prop {
+                    name: "target-path".into(),
+                    value: vec![Piece::Bytes(bytes)],
+                    line,
+                }
+            }
+            label => Prop {
+                name: "target".into(),
+                value: vec![Piece::Phandle(label)],
+                line,
+            },
+        });
+        let mut overlay = node;
+        overlay.name = "__overlay__".into();
+        frag.children.push(overlay);
+        root.children.push(frag);
+    }
+}
+
+/// Index path from the root to a node.
+type NodePath = Vec<usize>;
+
+fn node_at<'n>(root: &'n Node, path: &[usize]) -> &'n Node {
+    path.iter().fold(root, |n, &i| &n.children[i])
+}
+
+fn node_mut<'n>(root: &'n mut Node, path: &[usize]) -> &'n mut Node {
+    path.iter().fold(root, |n, &i| &mut n.children[i])
+}
+
+fn path_string(root: &Node, path: &[usize]) -> String {
+    if path.is_empty() {
+        return "/".into();
+    }
+    let mut out = String::new();
+    let mut node = root;
+    for &i in path {
+        node = &node.children[i];
+        out.push('/');
+        out.push_str(&node.name);
+    }
+    out
+}
+
+fn collect_labels(root: &Node) -> Result<BTreeMap<String, NodePath>, DtsError> {
+    fn walk(
+        node: &Node,
+        path: &mut NodePath,
+        out: &mut BTreeMap<String, NodePath>,
+    ) -> Result<(), DtsError> {
+        for label in &node.labels {
+            if out.insert(label.clone(), path.clone()).is_some() {
+                return Err(DtsError {
+                    line: node.line,
+                    reason: format!("duplicate label `{label}`"),
+                });
+            }
+        }
+        for (i, child) in node.children.iter().enumerate() {
+            path.push(i);
+            walk(child, path, out)?;
+            path.pop();
+        }
+        Ok(())
+    }
+    let mut out = BTreeMap::new();
+    walk(root, &mut Vec::new(), &mut out)?;
+    Ok(out)
+}
+
+fn find(root: &Node, labels: &BTreeMap<String, NodePath>, target: &Target) -> Option<NodePath> {
+    match target {
+        Target::Label(l) => labels.get(l).cloned(),
+        Target::Path(p) => {
+            let mut node = root;
+            let mut path = Vec::new();
+            for part in p.split('/').filter(|s| !s.is_empty()) {
+                // Allow omitting the unit address when it is unambiguous.
+                let exact = node.children.iter().position(|c| c.name == part);
+                let idx = exact.or_else(|| {
+                    let mut it = node
+                        .children
+                        .iter()
+                        .enumerate()
+                        .filter(|(_, c)| c.name.split('@').next() == Some(part));
+                    match (it.next(), it.next()) {
+                        (Some((i, _)), None) => Some(i),
+                        _ => None,
+                    }
+                })?;
+                path.push(idx);
+                node = &node.children[idx];
+            }
+            Some(path)
+        }
+    }
+}
+
+/// Resolves every reference, assigns phandles and (for plugins) adds the
+/// `__symbols__`, `__fixups__` and `__local_fixups__` nodes.
+fn resolve(root: &mut Node, plugin: bool) -> Result<(), DtsError> {
+    let labels = collect_labels(root)?;
+
+    // Phandles already given explicitly.
+    let mut phandles: BTreeMap<NodePath, u32> = BTreeMap::new();
+    let mut all = Vec::new();
+    walk_paths(root, &mut Vec::new(), &mut all);
+    for path in &all {
+        let node = node_at(root, path);
+        if let Some(p) = node.props.iter().find(|p| p.name == "phandle")
+            && let [Piece::Bytes(b)] = p.value.as_slice()
+            && b.len() == 4
+        {
+            phandles.insert(path.clone(), u32::from_be_bytes([b[0], b[1], b[2], b[3]]));
+        }
+    }
+    let mut next_phandle = phandles.values().copied().max().unwrap_or(0) + 1;
+
+    let mut resolved: Vec<(NodePath, usize, Vec<u8>)> = Vec::new();
+    let mut fixups: BTreeMap<String, Vec<u8>> = BTreeMap::new();
+    let mut fixup_order: Vec<String> = Vec::new();
+    let mut local_fixups: Vec<(NodePath, String, u32)> = Vec::new();
+    for path in &all {
+        let node = node_at(root, path);
+        for (pi, prop) in node.props.iter().enumerate() {
+            let mut bytes = Vec::new();
+            for piece in &prop.value {
+                match piece {
+                    Piece::Bytes(b) => bytes.extend_from_slice(b),
+                    Piece::Path(target) => {
+                        let found = find(root, &labels, target).ok_or_else(|| DtsError {
+                            line: prop.line,
+                            reason: format!("path reference to undefined {target}"),
+                        })?;
+                        bytes.extend_from_slice(path_string(root, &found).as_bytes());
+                        bytes.push(0);
+                    }
+                    Piece::Phandle(target) => {
+                        let offset = bytes.len() as u32;
+                        match find(root, &labels, target) {
+                            Some(found) => {
+                                let ph = *phandles.entry(found).or_insert_with(|| {
+                                    next_phandle += 1;
+                                    next_phandle - 1
+                                });
+                                bytes.extend_from_slice(&ph.to_be_bytes());
+                                if plugin {
+                                    local_fixups.push((path.clone(), prop.name.clone(), offset));
+                                }
+                            }
+                            None => match target {
+                                Target::Label(label) if plugin => {
+                                    bytes.extend_from_slice(&u32::MAX.to_be_bytes());
+                                    let entry = fixups.entry(label.clone()).or_insert_with(|| {
+                                        fixup_order.push(label.clone());
+                                        Vec::new()
+                                    });
+                                    entry.extend_from_slice(
+                                        format!(
+                                            "{}:{}:{offset}",
+                                            path_string(root, path),
+                                            prop.name
+                                        )
+                                        .as_bytes(),
+                                    );
+                                    entry.push(0);
+                                }
+                                _ => {
+                                    return Err(DtsError {
+                                        line: prop.line,
+                                        reason: format!("reference to undefined {target}"),
+                                    });
+                                }
+                            },
+                        }
+                    }
+                }
+            }
+            resolved.push((path.clone(), pi, bytes));
+        }
+    }
+
+    for (path, pi, bytes) in resolved {
+        node_mut(root, &path).props[pi].value = vec![Piece::Bytes(bytes)];
+    }
+    for (path, ph) in &phandles {
+        let node = node_mut(root, path);
+        if !node.props.iter().any(|p| p.name == "phandle") {
+            node.props.push(Prop {
+                name: "phandle".into(),
+                value: vec![Piece::Bytes(ph.to_be_bytes().to_vec())],
+                line: node.line,
+            });
+        }
+    }
+
+    if plugin {
+        let mut symbols = Node::new("__symbols__");
+        for (label, path) in &labels {
+            let mut value = path_string(root, path).into_bytes();
+            value.push(0);
+            symbols.props.push(bytes_prop(label, value));
+        }
+        let mut fixup_node = Node::new("__fixups__");
+        for label in fixup_order {
+            let value = fixups.remove(&label).unwrap_or_default();
+            fixup_node.props.push(bytes_prop(&label, value));
+        }
+        let mut local = Node::new("__local_fixups__");
+        for (path, prop, offset) in local_fixups {
+            let mut node = &mut local;
+            let mut src = &*root;
+            for &i in &path {
+                src = &src.children[i];
+                node = node.child_mut(&src.name, src.line);
+            }
+            match node.props.iter_mut().find(|p| p.name == prop) {
+                Some(Prop { value, .. }) => {
+                    if let Some(Piece::Bytes(b)) = value.first_mut() {
+                        b.extend_from_slice(&offset.to_be_bytes());
+                    }
+                }
+                None => node
+                    .props
+                    .push(bytes_prop(&prop, offset.to_be_bytes().to_vec())),
+            }
+        }
+        for special in [symbols, fixup_node, local] {
+            if !special.props.is_empty() || !special.children.is_empty() {
+                root.children.push(special);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// A generated `__symbols__`/`__fixups__` property. It has no source line;
+/// nothing reports errors against it.
+fn bytes_prop(name: &str, value: Vec<u8>) -> Prop {
+    Prop {
+        name: name.to_owned(),
+        value: vec![Piece::Bytes(value)],
+        line: 0,
+    }
+}
+
+fn walk_paths(node: &Node, path: &mut NodePath, out: &mut Vec<NodePath>) {
+    out.push(path.clone());
+    for (i, child) in node.children.iter().enumerate() {
+        path.push(i);
+        walk_paths(child, path, out);
+        path.pop();
+    }
+}
+
+/// Serializes a resolved tree as an FDT blob (version 17, empty reservation map).
+fn flatten(root: &Node) -> Vec<u8> {
+    fn emit(node: &Node, st: &mut Vec<u8>, strings: &mut Vec<u8>) {
+        st.extend_from_slice(&1u32.to_be_bytes());
+        st.extend_from_slice(node.name.as_bytes());
+        st.push(0);
+        st.resize(st.len().next_multiple_of(4), 0);
+        for prop in &node.props {
+            let value: &[u8] = match prop.value.as_slice() {
+                [Piece::Bytes(b)] => b,
+                _ => &[],
+            };
+            let nameoff = string_offset(strings, &prop.name);
+            st.extend_from_slice(&3u32.to_be_bytes());
+            st.extend_from_slice(&(value.len() as u32).to_be_bytes());
+            st.extend_from_slice(&nameoff.to_be_bytes());
+            st.extend_from_slice(value);
+            st.resize(st.len().next_multiple_of(4), 0);
+        }
+        for child in &node.children {
+            emit(child, st, strings);
+        }
+        st.extend_from_slice(&2u32.to_be_bytes());
+    }
+
+    let mut st = Vec::new();
+    let mut strings = Vec::new();
+    emit(root, &mut st, &mut strings);
+    st.extend_from_slice(&9u32.to_be_bytes());
+
+    const HEADER: usize = 40;
+    const RSVMAP: usize = 16;
+    let off_struct = HEADER + RSVMAP;
+    let off_strings = off_struct + st.len();
+    let total = off_strings + strings.len();
+    let mut out = Vec::with_capacity(total);
+    for word in [
+        FDT_MAGIC,
+        total as u32,
+        off_struct as u32,
+        off_strings as u32,
+        HEADER as u32,
+        17,
+        16,
+        0,
+        strings.len() as u32,
+        st.len() as u32,
+    ] {
+        out.extend_from_slice(&word.to_be_bytes());
+    }
+    out.extend_from_slice(&[0u8; RSVMAP]);
+    out.extend_from_slice(&st);
+    out.extend_from_slice(&strings);
+    out
+}
+
+/// Offset of `name` in the strings block, appending it if new.
+fn string_offset(strings: &mut Vec<u8>, name: &str) -> u32 {
+    let mut at = 0;
+    while at < strings.len() {
+        let end = at + strings[at..].iter().position(|&b| b == 0).unwrap_or(0);
+        if &strings[at..end] == name.as_bytes() {
+            return at as u32;
+        }
+        at = end + 1;
+    }
+    let off = strings.len() as u32;
+    strings.extend_from_slice(name.as_bytes());
+    strings.push(0);
+    off
+}
+
+/// Prints a flattened device tree as DTS source.
+///
+/// Property values are rendered heuristically, like `dtc -O dts`: a list of
+/// printable NUL-terminated strings becomes `"a", "b"`, a multiple of four
+/// bytes becomes `<cells>`, and anything else `[bytes]`. In overlays the
+/// `__symbols__`, `__fixups__` and `__local_fixups__` nodes are folded back
+/// into `label:` prefixes and `&label` / `&{/path}` cell references (and the
+/// `phandle` properties those references imply are left out), so the output
+/// recompiles with [`compile_dts`] to the same blob.
+///
+/// Trees nested deeper than the compiler accepts (256 levels) are rejected
+/// with [`FdtError::TooDeep`].
+pub fn decompile_dtb(blob: &[u8]) -> Result<String, FdtError> {
+    let fdt = Fdt::parse(blob)?;
+    let mut stack: Vec<Tree<'_>> = Vec::new();
+    let mut root = None;
+    for token in fdt.tokens() {
+        match token {
+            Token::BeginNode(_) if stack.len() == MAX_DEPTH => return Err(FdtError::TooDeep),
+            Token::BeginNode(name) => stack.push(Tree {
+                name,
+                props: Vec::new(),
+                children: Vec::new(),
+            }),
+            Token::Prop(p) => {
+                if let Some(node) = stack.last_mut() {
+                    node.props.push(p);
+                }
+            }
+            Token::EndNode => {
+                let node = stack.pop().ok_or(FdtError::Unbalanced)?;
+                match stack.last_mut() {
+                    Some(parent) => parent.children.push(node),
+                    None => root = Some(node),
+                }
+            }
+        }
+    }
+    let root = root.ok_or(FdtError::MissingEnd)?;
+
+    let special = |name| root.children.iter().find(|c| c.name == name);
+    let mut refs = Refs::default();
+    if let Some(symbols) = special("__symbols__") {
+        for p in &symbols.props {
+            if let Some(path) = p.strings().next() {
+                refs.labels
+                    .entry(path.to_string())
+                    .or_default()
+                    .push(p.name);
+            }
+        }
+    }
+    if let Some(fixups) = special("__fixups__") {
+        for p in &fixups.props {
+            for entry in p.strings() {
+                let mut parts = entry.rsplitn(3, ':');
+                if let (Some(Ok(offset)), Some(prop), Some(path)) =
+                    (parts.next().map(str::parse), parts.next(), parts.next())
+                {
+                    refs.add((path, prop), offset, format!("&{}", p.name));
+                }
+            }
+        }
+    }
+    let mut phandles = BTreeMap::new();
+    root.walk("", &mut |path, node| {
+        for p in &node.props {
+            if matches!(p.name, "phandle" | "linux,phandle")
+                && let Some(ph) = p.as_u32()
+            {
+                phandles.insert(ph, path.to_string());
+            }
+        }
+    });
+    if let Some(local) = special("__local_fixups__") {
+        let mut local_refs = Vec::new();
+        local.walk("", &mut |path, node| {
+            for p in &node.props {
+                for offset in p.value.chunks_exact(4) {
+                    let offset = u32::from_be_bytes([offset[0], offset[1], offset[2], offset[3]]);
+                    local_refs.push((path.to_string(), p.name, offset));
+                }
+            }
+        });
+        for (path, prop, offset) in local_refs {
+            let path = if path.is_empty() { "/" } else { path.as_str() };
+            let Some(ph) = root
+                .find(path)
+                .and_then(|n| n.props.iter().find(|p| p.name == prop))
+                .and_then(|p| p.value.get(offset as usize..offset as usize + 4))
+                .map(|b| u32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+            else {
+                continue;
+            };
+            let Some(target) = phandles.get(&ph) else {
+                continue;
+            };
+            let text = match refs.labels.get(target).and_then(|l| l.first()) {
+                Some(label) => format!("&{label}"),
+                None => format!("&{{{target}}}"),
+            };
+            refs.implied.push(ph);
+            refs.add((path, prop), offset, text);
+        }
+    }
+    let plugin = special("__fixups__").is_some()
+        || special("__local_fixups__").is_some()
+        || root
+            .children
+            .iter()
+            .any(|c| c.children.iter().any(|c| c.name == "__overlay__"));
+
+    let mut out = String::from("/dts-v1/;\n");
+    if plugin {
+        out.push_str("/plugin/;\n");
+    }
+    out.push('\n');
+    print_node(&mut out, &root, "", 0, plugin, &refs);
+    Ok(out)
+}
+
+/// An unflattened node borrowing from the blob.
+struct Tree<'a> {
+    name: &'a str,
+    props: Vec<Property<'a>>,
+    children: Vec<Tree<'a>>,
+}
+
+impl<'a> Tree<'a> {
+    /// Calls `f` with every node below this one and its path relative to it
+    /// (`""` for this node itself).
+    fn walk(&self, path: &str, f: &mut impl FnMut(&str, &Tree<'a>)) {
+        f(path, self);
+        for child in &self.children {
+            child.walk(&format!("{path}/{}", child.name), f);
+        }
+    }
+
+    fn find(&self, path: &str) -> Option<&Tree<'a>> {
+        path.split('/')
+            .filter(|s| !s.is_empty())
+            .try_fold(self, |node, part| {
+                node.children.iter().find(|c| c.name == part)
+            })
+    }
+}
+
+/// Labels and cell references recovered from the overlay bookkeeping nodes.
+#[derive(Default)]
+struct Refs<'a> {
+    /// Node path to its labels.
+    labels: BTreeMap<String, Vec<&'a str>>,
+    /// `(node path, property)` to `(offset, reference text)` pairs.
+    cells: BTreeMap<(String, String), Vec<(u32, String)>>,
+    /// Phandles that local references point at.
+    implied: Vec<u32>,
+}
+
+impl Refs<'_> {
+    fn add(&mut self, (path, prop): (&str, &str), offset: u32, text: String) {
+        let at = self.cells.entry((path.into(), prop.into())).or_default();
+        at.push((offset, text));
+        at.sort_by_key(|&(offset, _)| offset);
+    }
+}
+
+fn print_node(
+    out: &mut String,
+    node: &Tree<'_>,
+    path: &str,
+    depth: usize,
+    plugin: bool,
+    refs: &Refs<'_>,
+) {
+    let indent = "\t".repeat(depth);
+    let path_key = if path.is_empty() { "/" } else { path };
+    out.push_str(&indent);
+    if depth == 0 {
+        out.push_str("/ {\n");
+    } else {
+        for label in refs.labels.get(path_key).into_iter().flatten() {
+            out.push_str(label);
+            out.push_str(": ");
+        }
+        out.push_str(node.name);
+        out.push_str(" {\n");
+    }
+
+    let mut first = true;
+    for p in &node.props {
+        let implied = matches!(p.name, "phandle" | "linux,phandle")
+            && p.as_u32().is_some_and(|ph| refs.implied.contains(&ph));
+        if implied {
+            continue;
+        }
+        let cells = refs.cells.get(&(path_key.to_string(), p.name.to_string()));
+        out.push_str(&indent);
+        out.push('\t');
+        out.push_str(p.name);
+        if !p.value.is_empty() {
+            out.push_str(" = ");
+            out.push_str(&format_value(p.value, cells.map_or(&[][..], Vec::as_slice)));
+        }
+        out.push_str(";\n");
+        first = false;
+    }
+    for child in &node.children {
+        let bookkeeping = matches!(
+            child.name,
+            "__symbols__" | "__fixups__" | "__local_fixups__"
+        );
+        if depth == 0 && plugin && bookkeeping {
+            continue;
+        }
+        if !first {
+            out.push('\n');
+        }
+        first = false;
+        let child_path = format!("{path}/{}", child.name);
+        print_node(out, child, &child_path, depth + 1, plugin, refs);
+    }
+    out.push_str(&indent);
+    out.push_str("};\n");
+}
+
+/// Renders a property value, substituting `refs` (sorted by offset) for the
+/// 32-bit cells they patch.
+fn format_value(value: &[u8], refs: &[(u32, String)]) -> String {
+    enum Part {
+        Cells(Vec<String>),
+        Other(String),
+    }
+    let mut parts: Vec<Part> = Vec::new();
+    let push_cells = |parts: &mut Vec<Part>, cells: Vec<String>| match parts.last_mut() {
+        Some(Part::Cells(prev)) => prev.extend(cells),
+        _ => parts.push(Part::Cells(cells)),
+    };
+    let mut pos = 0;
+    let mut refs = refs.iter().peekable();
+    while pos < value.len() {
+        // Skip references that overlap a cell already printed or run past the end.
+        while refs
+            .next_if(|(offset, _)| (*offset as usize) < pos || *offset as usize + 4 > value.len())
+            .is_some()
+        {}
+        if let Some((_, text)) = refs.next_if(|(offset, _)| *offset as usize == pos) {
+            push_cells(&mut parts, vec![text.clone()]);
+            pos += 4;
+            continue;
+        }
+        let end = refs.peek().map_or(value.len(), |(offset, _)| {
+            (*offset as usize).min(value.len())
+        });
+        let chunk = &value[pos..end.max(pos + 1)];
+        pos += chunk.len();
+        if let Some(strings) = as_strings(chunk) {
+            parts.push(Part::Other(strings));
+        } else if chunk.len().is_multiple_of(4) {
+            let cells = chunk
+                .chunks_exact(4)
+                .map(|c| format!("0x{:02x}", u32::from_be_bytes([c[0], c[1], c[2], c[3]])))
+                .collect();
+            push_cells(&mut parts, cells);
+        } else {
+            let bytes: Vec<String> = chunk.iter().map(|b| format!("{b:02x}")).collect();
+            parts.push(Part::Other(format!("[{}]", bytes.join(" "))));
+        }
+    }
+    let parts: Vec<String> = parts
+        .into_iter()
+        .map(|part| match part {
+            Part::Cells(cells) => format!("<{}>", cells.join(" ")),
+            Part::Other(text) => text,
+        })
+        .collect();
+    parts.join(", ")
+}
+
+/// Formats `chunk` as a DTS string list if it is one or more non-empty,
+/// printable, NUL-terminated strings (or a single empty string).
+fn as_strings(chunk: &[u8]) -> Option<String> {
+    let body = chunk.strip_suffix(&[0])?;
+    if body.is_empty() {
+        return Some("\"\"".into());
+    }
+    let printable = |&b: &u8| b == 0 || b == b'\t' || b == b'\n' || (0x20..0x7F).contains(&b);
+    if !body.iter().all(printable)
+        || body.starts_with(&[0])
+        || body.windows(2).any(|w| w == [0, 0])
+        || body.ends_with(&[0])
+    {
+        return None;
+    }
+    let strings: Vec<String> = body
+        .split(|&b| b == 0)
+        .map(|s| {
+            let mut text = String::from("\"");
+            for &b in s {
+                match b {
+                    b'"' => text.push_str("\\\""),
+                    b'\\' => text.push_str("\\\\"),
+                    b'\t' => text.push_str("\\t"),
+                    b'\n' => text.push_str("\\n"),
+                    _ => text.push(b as char),
+                }
+            }
+            text.push('"');
+            text
+        })
+        .collect();
+    Some(strings.join(", "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fdt::tests::{Item, build};
+    use crate::fdt::{Fdt, FragmentTarget, Token};
+
+    const OVERLAY: &str = r#"
+/dts-v1/;
+/plugin/;
+
+/ {
+    compatible = "brcm,bcm2835";
+
+    fragment@0 {
+        target = <&i2c1>;
+        __overlay__ {
+            #address-cells = <1>;
+            #size-cells = <0>;
+            status = "okay";
+
+            sensor: bme280@76 {
+                compatible = "bosch,bme280", "bosch,bmp280";
+                reg = <0x76>;
+                interrupt-parent = <&gpio>;
+                interrupts = <4 (1 << 1)>;
+            };
+        };
+    };
+
+    __overrides__ {
+        addr = <&sensor>, "reg:0";
+    };
+};
+
+/* pin setup for the interrupt line */
+&gpio {
+    int_pins: int_pins {
+        brcm,pins = <4>;
+        brcm,function = <0>; // input
+        brcm,pull = /bits/ 8 <2>;
+        mac = [00 11 22 33 44 55];
+    };
+};
+"#;
+
+    fn prop<'a>(fdt: &Fdt<'a>, node_path: &str, name: &str) -> Option<&'a [u8]> {
+        let mut stack: Vec<&str> = Vec::new();
+        for token in fdt.tokens() {
+            match token {
+                Token::BeginNode(n) => stack.push(n),
+                Token::EndNode => {
+                    stack.pop();
+                }
+                Token::Prop(p) if p.name == name && stack.join("/") == node_path => {
+                    return Some(p.value);
+                }
+                Token::Prop(_) => {}
+            }
+        }
+        None
+    }
+
+    #[test]
+    fn compiles_a_plugin_overlay() {
+        let blob = compile_dts(OVERLAY).unwrap();
+        let fdt = Fdt::parse(&blob).unwrap();
+        assert_eq!(fdt.compatible(), Some("brcm,bcm2835"));
+        let targets: Vec<_> = fdt.fragments().map(|f| (f.name, f.target)).collect();
+        assert_eq!(
+            targets,
+            [
+                ("fragment@0", FragmentTarget::Label("i2c1")),
+                ("fragment@1", FragmentTarget::Label("gpio")),
+            ]
+        );
+
+        let sensor = "/fragment@0/__overlay__/bme280@76";
+        assert_eq!(prop(&fdt, sensor, "reg"), Some(&[0, 0, 0, 0x76][..]));
+        assert_eq!(
+            prop(&fdt, sensor, "interrupts"),
+            Some(&[0, 0, 0, 4, 0, 0, 0, 2][..])
+        );
+        assert_eq!(prop(&fdt, sensor, "interrupt-parent"), Some(&[0xFF; 4][..]));
+        assert_eq!(prop(&fdt, sensor, "phandle"), Some(&[0, 0, 0, 1][..]));
+        assert_eq!(
+            prop(&fdt, sensor, "compatible"),
+            Some(&b"bosch,bme280\0bosch,bmp280\0"[..])
+        );
+        assert_eq!(
+            prop(&fdt, "/__overrides__", "addr"),
+            Some(&b"\0\0\0\x01reg:0\0"[..])
+        );
+        let pins = "/fragment@1/__overlay__/int_pins";
+        assert_eq!(prop(&fdt, pins, "brcm,pull"), Some(&[2][..]));
+        assert_eq!(
+            prop(&fdt, pins, "mac"),
+            Some(&[0x00, 0x11, 0x22, 0x33, 0x44, 0x55][..])
+        );
+
+        assert_eq!(
+            prop(&fdt, "/__fixups__", "gpio"),
+            Some(
+                &b"/fragment@0/__overlay__/bme280@76:interrupt-parent:0\0/fragment@1:target:0\0"[..]
+            )
+        );
+        assert_eq!(
+            prop(&fdt, "/__local_fixups__/__overrides__", "addr"),
+            Some(&[0, 0, 0, 0][..])
+        );
+        assert_eq!(
+            prop(&fdt, "/__symbols__", "sensor"),
+            Some(&b"/fragment@0/__overlay__/bme280@76\0"[..])
+        );
+        assert_eq!(
+            prop(&fdt, "/__symbols__", "int_pins"),
+            Some(&b"/fragment@1/__overlay__/int_pins\0"[..])
+        );
+    }
+
+    #[test]
+    fn merges_references_without_plugin() {
+        let src = r#"/dts-v1/;
+/ {
+    model = "test";
+    soc { uart: serial@7e201000 { status = "disabled"; }; };
+    aliases { serial0 = &uart; };
+};
+&uart { status = "okay"; clocks = <&uart 0x10 (-1)>; };
+&{/soc/serial} { extra; };
+"#;
+        let blob = compile_dts(src).unwrap();
+        let fdt = Fdt::parse(&blob).unwrap();
+        let uart = "/soc/serial@7e201000";
+        assert_eq!(prop(&fdt, uart, "status"), Some(&b"okay\0"[..]));
+        assert_eq!(prop(&fdt, uart, "extra"), Some(&[][..]));
+        assert_eq!(
+            prop(&fdt, uart, "clocks"),
+            Some(&[0, 0, 0, 1, 0, 0, 0, 0x10, 0xFF, 0xFF, 0xFF, 0xFF][..])
+        );
+        assert_eq!(
+            prop(&fdt, "/aliases", "serial0"),
+            Some(&b"/soc/serial@7e201000\0"[..])
+        );
+        assert!(prop(&fdt, "/__symbols__", "uart").is_none());
+    }
+
+    #[test]
+    fn decompiles_overlays_back_to_equivalent_source() {
+        let blob = compile_dts(OVERLAY).unwrap();
+        let text = decompile_dtb(&blob).unwrap();
+        assert!(text.starts_with("/dts-v1/;\n/plugin/;\n\n/ {\n"));
+        for line in [
+            "\tcompatible = \"brcm,bcm2835\";",
+            "\t\ttarget = <&i2c1>;",
+            "\t\t\tsensor: bme280@76 {",
+            "\t\t\t\tcompatible = \"bosch,bme280\", \"bosch,bmp280\";",
+            "\t\t\t\tinterrupts = <0x04 0x02>;",
+            "\t\taddr = <&sensor>, \"reg:0\";",
+            "\t\t\t\tbrcm,pull = [02];",
+            "\t\t\t\tmac = [00 11 22 33 44 55];",
+        ] {
+            assert!(
+                text.lines().any(|l| l == line),
+                "missing {line:?} in\n{text}"
+            );
+        }
+        assert!(!text.contains("phandle") && !text.contains("__fixups__"));
+        assert_eq!(compile_dts(&text).unwrap(), blob);
+    }
+
+    #[test]
+    fn decompiles_plain_trees_with_raw_phandles() {
+        let blob = compile_dts(
+            "/dts-v1/;\n/ { a: node { phandle = <7>; empty; }; user { ref = <&a>; }; };",
+        )
+        .unwrap();
+        let text = decompile_dtb(&blob).unwrap();
+        assert!(!text.contains("/plugin/"));
+        assert!(text.contains("\tnode {\n\t\tphandle = <0x07>;\n\t\tempty;\n\t};"));
+        assert!(text.contains("ref = <0x07>;"));
+        assert_eq!(compile_dts(&text).unwrap(), blob);
+        assert_eq!(decompile_dtb(b"junk"), Err(FdtError::TooShort));
+    }
+
+    #[test]
+    fn reports_errors_with_lines() {
+        let err = |src| compile_dts(src).unwrap_err();
+        assert_eq!(err("/ {\n  a = <&nowhere>;\n};").line, 2);
+        assert_eq!(err("/ {\n\n  a = \"open;\n};").line, 3);
+        assert!(
+            err("#include <dt-bindings/gpio/gpio.h>\n")
+                .reason
+                .contains("cpp")
+        );
+        assert!(err("/ { a = /bits/ 8 <256>; };").reason.contains("8 bits"));
+        assert!(err("/ { a = <(1 / 0)>; };").reason.contains("division"));
+        assert!(
+            err("/ { /delete-node/ x; };")
+                .reason
+                .contains("unsupported")
+        );
+        // Labels of nodes that are created, or merged from a reference,
+        // report where they were defined.
+        let dup = err(
+            "/ {\n  a: x { };\n  y {\n    z { };\n  };\n};\n&{/y} {\n  z {\n    a: w { };\n  };\n};",
+        );
+        assert_eq!((dup.line, dup.reason.as_str()), (9, "duplicate label `a`"));
+    }
+
+    #[test]
+    fn rejects_deep_nesting() {
+        let parens = format!(
+            "/ {{ a = <{}1{}>; }};",
+            "(".repeat(100_000),
+            ")".repeat(100_000)
+        );
+        assert_eq!(compile_dts(&parens).unwrap_err().reason, "nesting too deep");
+        let negations = format!("/ {{ a = <({}1)>; }};", "-".repeat(100_000));
+        assert_eq!(
+            compile_dts(&negations).unwrap_err().reason,
+            "nesting too deep"
+        );
+        let nodes = format!("/ {{ {} }};", "n { ".repeat(100_000));
+        assert_eq!(compile_dts(&nodes).unwrap_err().reason, "nesting too deep");
+        let ok = format!("/ {{ a = <{}1{}>; }};", "(".repeat(200), ")".repeat(200));
+        assert!(compile_dts(&ok).is_ok());
+
+        // The decompiler stops at the same depth instead of overflowing the
+        // stack on a hostile blob.
+        let nested = |depth: usize| {
+            let mut items: Vec<Item<'_>> = (0..depth).map(|_| Item::Begin("n")).collect();
+            items.extend((0..depth).map(|_| Item::End));
+            build(&items)
+        };
+        assert_eq!(decompile_dtb(&nested(200_000)), Err(FdtError::TooDeep));
+        assert!(decompile_dtb(&nested(MAX_DEPTH)).is_ok());
+    }
+}
@@ -0,0 +1,297 @@
+//  _  _       _             _  _
+// | || |  ___| |_ _ __ ___ | || |
+// | || |_/ __| __| '_ ` _ \| || |_
+// |__   _\__ | |_| | | | | |__   _|
+//   |_| |___/\__|_|_|_| |_|  |_|
+//! # ehatrom — EEPROM HAT library for Raspberry Pi HATs
+//! - [Documentation (docs.rs)](https://docs.rs/ehatrom)
+//! - [GitHub](https://github.com/4stm4/ehatrom)
+//!
+//! ## Field-path edits
+//!
+//! Applies `path=value` assignments to a parsed [`Eeprom`] so a single field
+//! can be changed without decompiling the image. Values use the same syntax
+//! as the [`settings`](crate::settings) directives. Supported paths:
+//!
+//! - `version` — header format version (decimal or `0x` hex)
+//! - `vendor_info.uuid`, `vendor_info.product_id`, `vendor_info.product_ver`
+//! - `vendor_info.vendor`, `vendor_info.product` (at most 16 bytes, quotes optional)
+//! - `gpio.bank0.pin[N]` (N = 0..27) and `gpio.bank1.pin[N]` (N = 28..45, BCM
+//!   numbering), set to `FUNC[,PULL]` (e.g. `OUTPUT,UP`) or `UNUSED`
+//! - `gpio.bankN.drive`, `gpio.bankN.slew`, `gpio.bankN.hysteresis`, and
+//!   `gpio.bank0.back_power`
+//! - `power_supply` — current in mA, or `none` to drop the atom
+//!
+//! Editing a bank1 field creates the bank1 atom if the image had none. The
+//! header (`numatoms`, `eeplen`) is recomputed after every edit, and
+//! [`edit_image`] re-serializes with fresh CRC-16s. Atoms are written back in
+//! the canonical order, and atoms of unknown type come back as custom atoms,
+//! exactly as [`Eeprom::from_bytes`] parses them.
+
+use crate::settings::{
+    copy_str, parse_func, parse_hex_u16, parse_nibble, parse_pull, parse_uuid, unquote,
+};
+use crate::{Eeprom, GPIO_COUNT, GPIO_COUNT_BANK1, GpioMapAtom, PinPull, encode_pin};
+use alloc::{
+    string::{String, ToString},
+    vec::Vec,
+};
+
+/// Error returned by [`apply_edit`] and [`edit_image`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EditError {
+    /// The input image could not be parsed (reason from [`Eeprom::from_bytes`]).
+    Image(&'static str),
+    /// An assignment was rejected.
+    Field {
+        /// The assignment as given, e.g. `gpio.bank0.pin[17]=OUTPUT,UP`.
+        edit: String,
+        /// Human-readable reason.
+        reason: &'static str,
+    },
+}
+
+impl core::fmt::Display for EditError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            EditError::Image(reason) => write!(f, "cannot parse image: {reason}"),
+            EditError::Field { edit, reason } => write!(f, "edit `{edit}`: {reason}"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for EditError {}
+
+/// Applies one `path=value` assignment to `eeprom` and refreshes its header.
+pub fn apply_edit(eeprom: &mut Eeprom, edit: &str) -> Result<(), EditError> {
+    let err = |reason| EditError::Field {
+        edit: edit.to_string(),
+        reason,
+    };
+    let (path, value) = edit.split_once('=').ok_or(err("expected path=value"))?;
+    let (path, value) = (path.trim(), value.trim());
+
+    match path {
+        "version" => {
+            let v = match value.strip_prefix("0x") {
+                Some(hex) => u8::from_str_radix(hex, 16).ok(),
+                None => value.parse().ok(),
+            };
+            eeprom.header.version = v
+                .filter(|&v| v != 0)
+                .ok_or(err("invalid version (expected 1..255)"))?;
+        }
+        "vendor_info.uuid" => {
+            eeprom.vendor_info.uuid = parse_uuid(value).ok_or(err("invalid uuid"))?;
+        }
+        "vendor_info.product_id" => {
+            eeprom.vendor_info.product_id =
+                parse_hex_u16(value).ok_or(err("invalid product_id (expected hex u16)"))?;
+        }
+        "vendor_info.product_ver" => {
+            eeprom.vendor_info.product_ver =
+                parse_hex_u16(value).ok_or(err("invalid product_ver (expected hex u16)"))?;
+        }
+        "vendor_info.vendor" | "vendor_info.product" => {
+            let text = unquote(value);
+            if text.len() > 16 {
+                return Err(err("string longer than 16 bytes"));
+            }
+            let buf = if path == "vendor_info.vendor" {
+                &mut eeprom.vendor_info.vendor
+            } else {
+                &mut eeprom.vendor_info.product
+            };
+            copy_str(buf, text);
+        }
+        "power_supply" => {
+            eeprom.power_supply = if value.eq_ignore_ascii_case("none") {
+                None
+            } else {
+                Some(
+                    value
+                        .parse()
+                        .map_err(|_| err("invalid power_supply (expected mA or none)"))?,
+                )
+            };
+        }
+        _ => {
+            let rest = path
+                .strip_prefix("gpio.")
+                .ok_or(err("unknown field path"))?;
+            let (bank, field) = rest.split_once('.').ok_or(err("unknown field path"))?;
+            // Edit a copy so a rejected value never leaves a fresh bank1 behind.
+            let bank1 = match bank {
+                "bank0" => false,
+                "bank1" => true,
+                _ => return Err(err("unknown GPIO bank (expected bank0 or bank1)")),
+            };
+            let mut atom = match eeprom.gpio_map_bank1 {
+                Some(atom) if bank1 => atom,
+                None if bank1 => GpioMapAtom {
+                    flags: 0,
+                    power: 0,
+                    pins: [0u8; GPIO_COUNT],
+                },
+                _ => eeprom.gpio_map_bank0,
+            };
+            edit_gpio(&mut atom, bank1, field, value).map_err(err)?;
+            if bank1 {
+                eeprom.gpio_map_bank1 = Some(atom);
+            } else {
+                eeprom.gpio_map_bank0 = atom;
+            }
+        }
+    }
+    eeprom.update_header();
+    Ok(())
+}
+
+fn edit_gpio(
+    bank: &mut GpioMapAtom,
+    bank1: bool,
+    field: &str,
+    value: &str,
+) -> Result<(), &'static str> {
+    let (mut drive, mut slew, mut hysteresis, mut back_power) = bank.bank_flags();
+    match field {
+        "drive" => drive = parse_nibble(value, u8::MAX).ok_or("invalid drive")?,
+        "slew" => slew = parse_nibble(value, u8::MAX).ok_or("invalid slew")?,
+        "hysteresis" => hysteresis = parse_nibble(value, u8::MAX).ok_or("invalid hysteresis")?,
+        "back_power" if !bank1 => {
+            back_power = parse_nibble(value, u8::MAX).ok_or("invalid back_power")?
+        }
+        _ => {
+            let gpio: usize = field
+                .strip_prefix("pin[")
+                .and_then(|s| s.strip_suffix(']'))
+                .and_then(|n| n.parse().ok())
+                .ok_or("unknown GPIO field")?;
+            let (base, len) = if bank1 {
+                (GPIO_COUNT, GPIO_COUNT_BANK1)
+            } else {
+                (0, GPIO_COUNT)
+            };
+            if !(base..base + len).contains(&gpio) {
+                return Err("GPIO number outside this bank (bank0 0..27, bank1 28..45)");
+            }
+            bank.pins[gpio - base] = parse_pin_value(value)?;
+            return Ok(());
+        }
+    }
+    bank.set_bank_flags(drive, slew, hysteresis, back_power)
+}
+
+/// Parses `UNUSED` or `FUNC[,PULL]` (case-insensitive) into a pin byte.
+fn parse_pin_value(value: &str) -> Result<u8, &'static str> {
+    let value = value.to_ascii_uppercase();
+    if value == "UNUSED" {
+        return Ok(0);
+    }
+    let (func, pull) = match value.split_once(',') {
+        Some((f, p)) => (f.trim(), Some(p.trim())),
+        None => (value.trim(), None),
+    };
+    let func = parse_func(func).ok_or("invalid pin function")?;
+    let pull = match pull {
+        Some(p) => parse_pull(p).ok_or("invalid pin pull")?,
+        None => PinPull::Default,
+    };
+    Ok(encode_pin(func, pull))
+}
+
+/// Parses `data`, applies every edit in order and returns the re-serialized
+/// image with fresh CRC-16s and header.
+pub fn edit_image<'a, I>(data: &[u8], edits: I) -> Result<Vec<u8>, EditError>
+where
+    I: IntoIterator<Item = &'a str>,
+{
+    let mut eeprom = Eeprom::from_bytes(data).map_err(EditError::Image)?;
+    for edit in edits {
+        apply_edit(&mut eeprom, edit)?;
+    }
+    Ok(eeprom.serialize())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{PinFunc, parse_settings};
+
+    const SETTINGS: &str = "\
+product_id 0x0001
+product_ver 0x0002
+vendor \"ACME\"
+product \"Test HAT\"
+gpio_drive 3
+setgpio 17 OUTPUT UP
+";
+
+    #[test]
+    fn edits_fields_and_recomputes_crcs() {
+        let image = parse_settings(SETTINGS).unwrap().serialize();
+        let out = edit_image(
+            &image,
+            [
+                "vendor_info.product_ver=0x0003",
+                "gpio.bank0.pin[17]=INPUT,DOWN",
+                "gpio.bank0.slew=1",
+                "gpio.bank1.pin[40]=alt0",
+                "power_supply=2000",
+            ],
+        )
+        .unwrap();
+        assert!(Eeprom::verify(&out));
+
+        let e = Eeprom::from_bytes(&out).unwrap();
+        let product_ver = e.vendor_info.product_ver;
+        assert_eq!(product_ver, 0x0003);
+        assert_eq!(
+            e.gpio_map_bank0.pins[17],
+            encode_pin(PinFunc::Input, PinPull::Down)
+        );
+        // Drive is kept while slew changes.
+        assert_eq!(e.gpio_map_bank0.flags, 0x13);
+        assert_eq!(
+            e.gpio_map_bank1.unwrap().pins[12],
+            encode_pin(PinFunc::Alt0, PinPull::Default)
+        );
+        assert_eq!(e.power_supply, Some(2000));
+        let numatoms = e.header.numatoms;
+        assert_eq!(numatoms, 4);
+    }
+
+    #[test]
+    fn unused_and_none_clear_values() {
+        let mut e = parse_settings("current_supply 1500\nsetgpio 17 OUTPUT UP\n").unwrap();
+        apply_edit(&mut e, "gpio.bank0.pin[17]=UNUSED").unwrap();
+        apply_edit(&mut e, "power_supply=none").unwrap();
+        assert_eq!(e.gpio_map_bank0.pins[17], 0);
+        assert_eq!(e.power_supply, None);
+        let numatoms = e.header.numatoms;
+        assert_eq!(numatoms, 2);
+    }
+
+    #[test]
+    fn rejects_bad_edits() {
+        let mut e = parse_settings(SETTINGS).unwrap();
+        for bad in [
+            "vendor_info.product_ver",
+            "vendor_info.color=red",
+            "gpio.bank0.pin[28]=OUTPUT",
+            "gpio.bank1.pin[3]=OUTPUT",
+            "gpio.bank0.pin[4]=OUTPUT,SIDEWAYS",
+            "gpio.bank1.back_power=1",
+            "gpio.bank0.drive=9",
+            "vendor_info.vendor=a vendor name that is too long",
+        ] {
+            assert!(apply_edit(&mut e, bad).is_err(), "{bad} accepted");
+        }
+        assert!(e.gpio_map_bank1.is_none());
+        assert_eq!(
+            edit_image(b"junk", []),
+            Err(EditError::Image("Not enough data for EEPROM header"))
+        );
+    }
+}
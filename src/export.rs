@@ -0,0 +1,185 @@
+//  _  _       _             _  _
+// | || |  ___| |_ _ __ ___ | || |
+// | || |_/ __| __| '_ ` _ \| || |_
+// |__   _\__ | |_| | | | | |__   _|
+//   |_| |___/\__|_|_|_| |_|  |_|
+//! # ehatrom — EEPROM HAT library for Raspberry Pi HATs
+//! - [Documentation (docs.rs)](https://docs.rs/ehatrom)
+//! - [GitHub](https://github.com/4stm4/ehatrom)
+//!
+//! ## `config.txt` and pinctrl overlay export
+//!
+//! Prototypes often carry no DT blob, so the firmware never applies the GPIO
+//! map. These generators turn the used pins of both banks into something that
+//! does:
+//!
+//! - [`config_txt`] writes `config.txt` `gpio=` lines, e.g. `gpio=17=op,dh,pu`:
+//!   `ip`, `op` or `a0`…`a5`, then `dl`/`dh` for outputs if an
+//!   [`OutputLevel`] is given, then `pu`/`pd`/`pn` (nothing for `DEFAULT`).
+//! - [`pinctrl_dts`] writes an equivalent overlay source using the BCM2835
+//!   pinctrl binding (Pi 1–4): one node per pin under `&gpio`, claimed as a
+//!   pin hog through the controller's own `pinctrl-0`. It compiles with
+//!   [`compile_dts`](crate::dts::compile_dts).
+//!
+//! The pin bytes say nothing about the level of an output pin, and the bank
+//! drive, slew and hysteresis flags have no `gpio=` equivalent; neither is
+//! exported.
+
+use crate::view::EepromView;
+use crate::{PinConfig, PinFunc, PinPull};
+use alloc::{format, string::String, vec::Vec};
+use core::fmt::Write as _;
+
+/// Level `config_txt` gives output pins.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OutputLevel {
+    /// Plain `op`; the firmware leaves the level alone.
+    #[default]
+    Keep,
+    /// `op,dl`.
+    Low,
+    /// `op,dh`.
+    High,
+}
+
+fn config_func(func: PinFunc) -> &'static str {
+    match func {
+        PinFunc::Input => "ip",
+        PinFunc::Output => "op",
+        PinFunc::Alt0 => "a0",
+        PinFunc::Alt1 => "a1",
+        PinFunc::Alt2 => "a2",
+        PinFunc::Alt3 => "a3",
+        PinFunc::Alt4 => "a4",
+        PinFunc::Alt5 => "a5",
+    }
+}
+
+/// `ACME Sensor HAT (product 0x1234 v2)`, for the generated comments.
+fn title(view: &EepromView) -> String {
+    match view.vendor_info() {
+        Some(vendor) => format!(
+            "{} {} (product 0x{:04x} v{})",
+            vendor.vendor().unwrap_or("?"),
+            vendor.product().unwrap_or("?"),
+            vendor.product_id(),
+            vendor.product_ver()
+        ),
+        None => "HAT without vendor info".into(),
+    }
+}
+
+/// The `config.txt` `gpio=` lines for the used pins of `data`.
+pub fn config_txt(data: &[u8], level: OutputLevel) -> Result<String, &'static str> {
+    let view = EepromView::new(data)?;
+    let mut out = format!("# GPIO setup of {}, from its HAT EEPROM\n", title(&view));
+    let pins: Vec<(usize, PinConfig)> = view.used_pins().collect();
+    if pins.is_empty() {
+        out.push_str("# (no pins used)\n");
+    }
+    for (gpio, pin) in pins {
+        let _ = write!(out, "gpio={gpio}={}", config_func(pin.func));
+        if pin.func == PinFunc::Output {
+            match level {
+                OutputLevel::Keep => {}
+                OutputLevel::Low => out.push_str(",dl"),
+                OutputLevel::High => out.push_str(",dh"),
+            }
+        }
+        match pin.pull {
+            PinPull::Default => {}
+            PinPull::Up => out.push_str(",pu"),
+            PinPull::Down => out.push_str(",pd"),
+            PinPull::None => out.push_str(",pn"),
+        }
+        out.push('\n');
+    }
+    Ok(out)
+}
+
+/// `brcm,pull` value, or `None` to leave the pull as it is.
+fn brcm_pull(pull: PinPull) -> Option<u8> {
+    match pull {
+        PinPull::Default => None,
+        PinPull::None => Some(0),
+        PinPull::Down => Some(1),
+        PinPull::Up => Some(2),
+    }
+}
+
+/// Overlay source that applies the used pins of `data` through pinctrl.
+pub fn pinctrl_dts(data: &[u8]) -> Result<String, &'static str> {
+    let view = EepromView::new(data)?;
+    let pins: Vec<(usize, PinConfig)> = view.used_pins().collect();
+    let mut out = format!(
+        "// GPIO setup of {}, from its HAT EEPROM\n\
+         // brcm,function takes BCM2835 FSEL values; brcm,pull is 0 none, 1 down, 2 up\n\
+         /dts-v1/;\n/plugin/;\n\n&gpio {{\n",
+        title(&view)
+    );
+    if !pins.is_empty() {
+        let nodes: Vec<String> = pins
+            .iter()
+            .map(|(gpio, _)| format!("&hat_gpio{gpio}"))
+            .collect();
+        let _ = write!(
+            out,
+            "\tpinctrl-names = \"default\";\n\tpinctrl-0 = <{}>;\n",
+            nodes.join(" ")
+        );
+    }
+    for (gpio, pin) in pins {
+        let _ = write!(
+            out,
+            "\n\that_gpio{gpio}: hat_gpio{gpio} {{\n\
+             \t\tbrcm,pins = <{gpio}>;\n\
+             \t\tbrcm,function = <{}>; // {}\n",
+            pin.func as u8,
+            pin.func.keyword()
+        );
+        if let Some(pull) = brcm_pull(pin.pull) {
+            let _ = writeln!(out, "\t\tbrcm,pull = <{pull}>; // {}", pin.pull.keyword());
+        }
+        out.push_str("\t};\n");
+    }
+    out.push_str("};\n");
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fdt::{Fdt, FragmentTarget};
+    use crate::{dts, parse_settings};
+
+    const SETTINGS: &str = "vendor \"ACME\"\nproduct \"Relay HAT\"\nproduct_id 0x1234\n\
+                            setgpio 4 ALT0\nsetgpio 17 OUTPUT UP\nsetgpio 22 INPUT NONE\n\
+                            setgpio 30 ALT3 DOWN\n";
+
+    #[test]
+    fn writes_gpio_lines_for_config_txt() {
+        let image = parse_settings(SETTINGS).unwrap().serialize();
+        assert_eq!(
+            config_txt(&image, OutputLevel::High).unwrap(),
+            "# GPIO setup of ACME Relay HAT (product 0x1234 v0), from its HAT EEPROM\n\
+             gpio=4=a0\ngpio=17=op,dh,pu\ngpio=22=ip,pn\ngpio=30=a3,pd\n"
+        );
+        let keep = config_txt(&image, OutputLevel::Keep).unwrap();
+        assert!(keep.contains("gpio=17=op,pu\n"));
+    }
+
+    #[test]
+    fn pinctrl_overlay_compiles() {
+        let image = parse_settings(SETTINGS).unwrap().serialize();
+        let source = pinctrl_dts(&image).unwrap();
+        assert!(source.contains("pinctrl-0 = <&hat_gpio4 &hat_gpio17 &hat_gpio22 &hat_gpio30>;"));
+        assert!(source.contains("brcm,function = <1>; // OUTPUT\n\t\tbrcm,pull = <2>; // UP"));
+        // GPIO 4 keeps its default pull.
+        assert!(source.contains("brcm,function = <4>; // ALT0\n\t};"));
+
+        let blob = dts::compile_dts(&source).unwrap();
+        let tree = Fdt::parse(&blob).unwrap();
+        let targets: Vec<_> = tree.fragments().map(|f| f.target).collect();
+        assert_eq!(targets, [FragmentTarget::Label("gpio")]);
+    }
+}
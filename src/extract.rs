@@ -0,0 +1,259 @@
+//  _  _       _             _  _
+// | || |  ___| |_ _ __ ___ | || |
+// | || |_/ __| __| '_ ` _ \| || |_
+// |__   _\__ | |_| | | | | |__   _|
+//   |_| |___/\__|_|_|_| |_|  |_|
+//! # ehatrom — EEPROM HAT library for Raspberry Pi HATs
+//! - [Documentation (docs.rs)](https://docs.rs/ehatrom)
+//! - [GitHub](https://github.com/4stm4/ehatrom)
+//!
+//! ## Extracting atoms to files
+//!
+//! [`extract`] splits an image into one file per atom, named after the atom
+//! type and kind (`01-vendor_info.bin`, `03-dt_blob.dtb`, `04-custom-0.bin`,
+//! …), plus an [`SETTINGS_FILE`] that rebuilds the image with
+//! [`parse_settings_with_loader`]. The settings file spells the vendor, GPIO
+//! and power atoms out as directives and pulls the DT and custom atoms back in
+//! with `dt_file` and `custom_file`. Nothing here touches the filesystem; the
+//! CLI writes the files.
+//!
+//! The rebuild is byte-identical for any image that [`Eeprom::from_bytes`]
+//! and [`Eeprom::serialize`] round-trip. That excludes images with atoms out
+//! of the canonical order, unknown atom types (rebuilt as custom atoms), a
+//! header version other than [`FORMAT_VERSION`], or pin bytes and flags that
+//! no `setgpio`/`gpio_*` directive produces. The settings file notes the
+//! last two; use [`Extraction::rebuild`] to check.
+
+use crate::diff::atom_name;
+use crate::settings::format_uuid;
+use crate::{
+    AtomType, DtAtom, Eeprom, FORMAT_VERSION, GPIO_COUNT, GPIO_COUNT_BANK1, GpioMapAtom,
+    SettingsError, decode_pin, encode_pin, parse_settings_with_loader,
+};
+use alloc::{format, string::String, vec::Vec};
+use core::fmt::Write;
+
+/// Name of the generated settings file.
+pub const SETTINGS_FILE: &str = "eeprom_settings.txt";
+
+/// One atom payload, without its header and CRC.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExtractedFile {
+    /// File name, e.g. `04-custom-0.bin`.
+    pub name: String,
+    pub atom_type: u16,
+    pub data: Vec<u8>,
+}
+
+/// The result of [`extract`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Extraction {
+    /// Atom payloads in image order.
+    pub files: Vec<ExtractedFile>,
+    /// Settings text that refers to `files` by name.
+    pub settings: String,
+}
+
+impl Extraction {
+    /// Rebuilds the image from [`Extraction::settings`] and the extracted files.
+    pub fn rebuild(&self) -> Result<Vec<u8>, SettingsError> {
+        let eeprom = parse_settings_with_loader(&self.settings, |name| {
+            self.files
+                .iter()
+                .find(|f| f.name == name)
+                .map(|f| f.data.clone())
+                .ok_or("no such extracted file")
+        })?;
+        Ok(eeprom.serialize())
+    }
+}
+
+/// Splits `data` into per-atom files and a settings file that rebuilds it.
+pub fn extract(data: &[u8]) -> Result<Extraction, &'static str> {
+    let eeprom = Eeprom::from_bytes(data)?;
+
+    let mut files = Vec::new();
+    let mut dt_file = None;
+    let mut custom_files = Vec::new();
+    for atom in crate::atoms(data) {
+        let name = match atom.kind() {
+            AtomType::DtBlob => match DtAtom::parse(atom.data) {
+                DtAtom::Blob(_) => String::from("03-dt_blob.dtb"),
+                DtAtom::Overlay { .. } => String::from("03-dt_overlay.txt"),
+            },
+            AtomType::Custom | AtomType::Unknown => format!(
+                "{:02x}-{}-{}.bin",
+                atom.atom_type,
+                atom_name(atom.atom_type),
+                custom_files.len()
+            ),
+            _ => format!("{:02x}-{}.bin", atom.atom_type, atom_name(atom.atom_type)),
+        };
+        match atom.kind() {
+            AtomType::DtBlob => dt_file = Some(name.clone()),
+            AtomType::Custom | AtomType::Unknown => custom_files.push(name.clone()),
+            _ => {}
+        }
+        files.push(ExtractedFile {
+            name,
+            atom_type: atom.atom_type,
+            data: atom.data.to_vec(),
+        });
+    }
+
+    let settings = format_settings(&eeprom, dt_file.as_deref(), &custom_files);
+    Ok(Extraction { files, settings })
+}
+
+/// Renders `eeprom` as settings directives.
+fn format_settings(eeprom: &Eeprom, dt_file: Option<&str>, custom_files: &[String]) -> String {
+    let mut out = String::new();
+    // Writing to a String cannot fail.
+    let _ = write_settings(&mut out, eeprom, dt_file, custom_files);
+    out
+}
+
+fn write_settings(
+    out: &mut String,
+    eeprom: &Eeprom,
+    dt_file: Option<&str>,
+    custom_files: &[String],
+) -> core::fmt::Result {
+    let v = &eeprom.vendor_info;
+    let (product_id, product_ver) = (v.product_id, v.product_ver);
+    let text = |buf: &[u8; 16]| {
+        let len = buf.iter().position(|&b| b == 0).unwrap_or(buf.len());
+        String::from_utf8_lossy(&buf[..len]).into_owned()
+    };
+    writeln!(
+        out,
+        "# Generated by `ehatrom extract`; rebuild with `ehatrom make`."
+    )?;
+    let version = eeprom.header.version;
+    if version != FORMAT_VERSION {
+        writeln!(out, "# header version {version} has no settings directive")?;
+    }
+    writeln!(out, "product_uuid {}", format_uuid(&v.uuid))?;
+    writeln!(out, "product_id 0x{product_id:04x}")?;
+    writeln!(out, "product_ver 0x{product_ver:04x}")?;
+    writeln!(out, "vendor \"{}\"", text(&v.vendor))?;
+    writeln!(out, "product \"{}\"", text(&v.product))?;
+
+    let bank0 = &eeprom.gpio_map_bank0;
+    writeln!(out)?;
+    write_flags(out, "", bank0)?;
+    writeln!(out, "back_power {}", bank0.power)?;
+    write_pins(out, 0, &bank0.pins[..GPIO_COUNT])?;
+
+    if let Some(bank1) = &eeprom.gpio_map_bank1 {
+        writeln!(out)?;
+        write_flags(out, "bank1_", bank1)?;
+        if bank1.power != 0 {
+            writeln!(
+                out,
+                "# bank1 power byte 0x{:02x} has no directive",
+                bank1.power
+            )?;
+        }
+        write_pins(out, GPIO_COUNT, &bank1.pins[..GPIO_COUNT_BANK1])?;
+    }
+
+    if eeprom.power_supply.is_some() || dt_file.is_some() || !custom_files.is_empty() {
+        writeln!(out)?;
+    }
+    if let Some(current) = eeprom.power_supply {
+        writeln!(out, "current_supply {current}")?;
+    }
+    if let Some(name) = dt_file {
+        writeln!(out, "dt_file {name}")?;
+    }
+    for name in custom_files {
+        writeln!(out, "custom_file {name}")?;
+    }
+    Ok(())
+}
+
+fn write_flags(out: &mut String, prefix: &str, bank: &GpioMapAtom) -> core::fmt::Result {
+    let flags = bank.flags;
+    writeln!(out, "{prefix}gpio_drive {}", flags & 0x0F)?;
+    writeln!(out, "{prefix}gpio_slew {}", (flags >> 4) & 0x03)?;
+    writeln!(out, "{prefix}gpio_hysteresis {}", flags >> 6)
+}
+
+fn write_pins(out: &mut String, base: usize, pins: &[u8]) -> core::fmt::Result {
+    for (i, &byte) in pins.iter().enumerate() {
+        let gpio = base + i;
+        let cfg = decode_pin(byte);
+        if byte == 0 {
+            continue;
+        } else if cfg.used && encode_pin(cfg.func, cfg.pull) == byte {
+            let config = format!("{:?} {:?}", cfg.func, cfg.pull).to_uppercase();
+            writeln!(out, "setgpio {gpio} {config}")?;
+        } else {
+            writeln!(
+                out,
+                "# gpio {gpio}: raw pin byte 0x{byte:02x} has no setgpio form"
+            )?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_settings;
+
+    #[test]
+    fn extracted_files_rebuild_the_same_image() {
+        let mut eeprom = parse_settings(
+            "product_uuid 12345678-9abc-def0-1234-56789abcdef0\n\
+             product_id 0x0001\nvendor \"ACME\"\nproduct \"Test HAT\"\n\
+             gpio_drive 3\ngpio_hysteresis 1\nsetgpio 4 INPUT UP\nsetgpio 40 ALT0 DEFAULT\n\
+             current_supply 1500\n",
+        )
+        .unwrap();
+        eeprom.add_dt_blob(crate::dts::compile_dts("/dts-v1/;\n/ { a = <1>; };").unwrap());
+        eeprom.add_custom_atom(b"serial=42".to_vec());
+        eeprom.add_custom_atom(alloc::vec![1, 2, 3]);
+        let image = eeprom.serialize();
+
+        let extraction = extract(&image).unwrap();
+        let names: Vec<_> = extraction.files.iter().map(|f| f.name.as_str()).collect();
+        assert_eq!(
+            names,
+            [
+                "01-vendor_info.bin",
+                "02-gpio_bank0.bin",
+                "03-dt_blob.dtb",
+                "05-gpio_bank1.bin",
+                "06-power_supply.bin",
+                "04-custom-0.bin",
+                "04-custom-1.bin",
+            ]
+        );
+        assert_eq!(extraction.files[5].data, b"serial=42");
+        assert!(extraction.settings.contains("setgpio 40 ALT0 DEFAULT\n"));
+        assert!(
+            extraction
+                .settings
+                .contains("custom_file 04-custom-1.bin\n")
+        );
+        assert_eq!(extraction.rebuild().unwrap(), image);
+    }
+
+    #[test]
+    fn notes_what_settings_cannot_express() {
+        let mut eeprom = parse_settings("setgpio 5 OUTPUT UP\n").unwrap();
+        eeprom.gpio_map_bank0.pins[6] = 0x01;
+        let image = eeprom.serialize();
+        let extraction = extract(&image).unwrap();
+        assert!(
+            extraction
+                .settings
+                .contains("# gpio 6: raw pin byte 0x01 has no setgpio form\n")
+        );
+        assert_ne!(extraction.rebuild().unwrap(), image);
+        assert_eq!(extract(b"junk"), Err("Not enough data for EEPROM header"));
+    }
+}
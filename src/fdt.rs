@@ -0,0 +1,688 @@
+//  _  _       _             _  _
+// | || |  ___| |_ _ __ ___ | || |
+// | || |_/ __| __| '_ ` _ \| || |_
+// |__   _\__ | |_| | | | | |__   _|
+//   |_| |___/\__|_|_|_| |_|  |_|
+//! # ehatrom — EEPROM HAT library for Raspberry Pi HATs
+//! - [Documentation (docs.rs)](https://docs.rs/ehatrom)
+//! - [GitHub](https://github.com/4stm4/ehatrom)
+//!
+//! ## Flattened Device Tree (DTB) validation and inspection
+//!
+//! The DT blob atom (`0x0003`) should hold a compiled overlay (`.dtbo`).
+//! [`Fdt::parse`] checks that it does. It verifies the `0xd00dfeed` magic,
+//! `totalsize`, the format version, and the bounds of the memory-reservation,
+//! structure and strings blocks. It then walks the whole structure block, so a
+//! `.dts` source file or a truncated blob placed in that slot is rejected.
+//!
+//! Once parsed, [`Fdt::tokens`] walks the structure block. [`Fdt::compatible`]
+//! and [`Fdt::fragments`] summarise an overlay: its `compatible` string and
+//! each `fragment@N` with its target. A phandle target is resolved to its label
+//! through `/__fixups__` when the overlay was compiled with `-@`. Nothing here
+//! allocates, so it works in `no_std` builds.
+
+use core::fmt;
+
+/// FDT header magic (`0xd00dfeed`, big-endian on the wire).
+pub const FDT_MAGIC: u32 = 0xd00d_feed;
+/// Size of the v17 FDT header in bytes.
+const FDT_HEADER_SIZE: usize = 40;
+/// Oldest structure-block version this parser understands.
+const FDT_MIN_VERSION: u32 = 16;
+/// Newest `last_comp_version` this parser understands.
+const FDT_MAX_COMP_VERSION: u32 = 17;
+
+const FDT_BEGIN_NODE: u32 = 0x1;
+const FDT_END_NODE: u32 = 0x2;
+const FDT_PROP: u32 = 0x3;
+const FDT_NOP: u32 = 0x4;
+const FDT_END: u32 = 0x9;
+
+/// Why a blob is not a usable flattened device tree.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FdtError {
+    /// Fewer bytes than the 40-byte header.
+    TooShort,
+    /// The first word is not [`FDT_MAGIC`].
+    BadMagic(u32),
+    /// `totalsize` is smaller than the header or larger than the blob.
+    BadTotalSize { totalsize: u32, available: usize },
+    /// The format version is outside what this parser supports.
+    UnsupportedVersion {
+        version: u32,
+        last_comp_version: u32,
+    },
+    /// A block (`"memory reservation"`, `"structure"` or `"strings"`) lies
+    /// outside `totalsize` or is misaligned.
+    BlockOutOfBounds(&'static str),
+    /// An unknown or misplaced token at the given structure-block offset.
+    BadToken { offset: usize, token: u32 },
+    /// A node or property name that is unterminated, out of bounds or not UTF-8.
+    BadName { offset: usize },
+    /// A property value runs past the structure block.
+    TruncatedProperty { offset: usize },
+    /// Nodes are not properly nested (or there is more than one root).
+    Unbalanced,
+    /// The structure block ends without `FDT_END`.
+    MissingEnd,
+    /// Nodes nest deeper than the decompiler follows (256 levels).
+    TooDeep,
+}
+
+impl fmt::Display for FdtError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FdtError::TooShort => write!(f, "shorter than the 40-byte FDT header"),
+            FdtError::BadMagic(m) => write!(f, "bad magic 0x{m:08x} (expected 0xd00dfeed)"),
+            FdtError::BadTotalSize {
+                totalsize,
+                available,
+            } => write!(f, "totalsize {totalsize} does not fit {available} bytes"),
+            FdtError::UnsupportedVersion {
+                version,
+                last_comp_version,
+            } => write!(
+                f,
+                "unsupported version {version} (last compatible {last_comp_version})"
+            ),
+            FdtError::BlockOutOfBounds(block) => write!(f, "{block} block out of bounds"),
+            FdtError::BadToken { offset, token } => {
+                write!(f, "bad token 0x{token:x} at structure offset 0x{offset:x}")
+            }
+            FdtError::BadName { offset } => {
+                write!(f, "bad name at structure offset 0x{offset:x}")
+            }
+            FdtError::TruncatedProperty { offset } => {
+                write!(f, "truncated property at structure offset 0x{offset:x}")
+            }
+            FdtError::Unbalanced => write!(f, "unbalanced nodes"),
+            FdtError::MissingEnd => write!(f, "structure block has no FDT_END"),
+            FdtError::TooDeep => write!(f, "nodes nested too deep"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for FdtError {}
+
+/// The decoded FDT header (all fields are big-endian on the wire).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FdtHeader {
+    pub totalsize: u32,
+    pub off_dt_struct: u32,
+    pub off_dt_strings: u32,
+    pub off_mem_rsvmap: u32,
+    pub version: u32,
+    pub last_comp_version: u32,
+    pub boot_cpuid_phys: u32,
+    pub size_dt_strings: u32,
+    pub size_dt_struct: u32,
+}
+
+/// A validated flattened device tree borrowed from a byte slice.
+#[derive(Debug, Clone, Copy)]
+pub struct Fdt<'a> {
+    header: FdtHeader,
+    structure: &'a [u8],
+    strings: &'a [u8],
+}
+
+/// One structure-block token, as yielded by [`Fdt::tokens`] (`FDT_NOP` is
+/// skipped and `FDT_END` ends the iteration).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Token<'a> {
+    /// Start of a node; the root node's name is empty.
+    BeginNode(&'a str),
+    /// End of the most recently opened node.
+    EndNode,
+    /// A property of the current node.
+    Prop(Property<'a>),
+}
+
+/// A property: name and raw big-endian value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Property<'a> {
+    pub name: &'a str,
+    pub value: &'a [u8],
+}
+
+/// Where a fragment applies, from its `target` or `target-path` property.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FragmentTarget<'a> {
+    /// `target-path = "/soc/gpio"`.
+    Path(&'a str),
+    /// `target = <&label>`, resolved through `/__fixups__`.
+    Label(&'a str),
+    /// `target = <phandle>` that no fixup names.
+    Phandle(u32),
+    /// Neither property is present.
+    Missing,
+}
+
+impl fmt::Display for FragmentTarget<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FragmentTarget::Path(p) => write!(f, "{p}"),
+            FragmentTarget::Label(l) => write!(f, "&{l}"),
+            FragmentTarget::Phandle(p) => write!(f, "phandle 0x{p:x}"),
+            FragmentTarget::Missing => write!(f, "(no target)"),
+        }
+    }
+}
+
+/// An overlay fragment: a direct child of the root other than the `__*__`
+/// bookkeeping nodes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Fragment<'a> {
+    /// Node name, e.g. `fragment@0`.
+    pub name: &'a str,
+    pub target: FragmentTarget<'a>,
+}
+
+fn be32(data: &[u8], at: usize) -> Option<u32> {
+    let b = data.get(at..at.checked_add(4)?)?;
+    Some(u32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+}
+
+fn align4(n: usize) -> usize {
+    (n + 3) & !3
+}
+
+/// Reads a NUL-terminated UTF-8 string starting at `at`.
+fn cstr(data: &[u8], at: usize) -> Option<&str> {
+    let rest = data.get(at..)?;
+    let len = rest.iter().position(|&b| b == 0)?;
+    core::str::from_utf8(&rest[..len]).ok()
+}
+
+impl<'a> Fdt<'a> {
+    /// Validates `data` as a flattened device tree. Bytes after `totalsize`
+    /// are ignored.
+    pub fn parse(data: &'a [u8]) -> Result<Self, FdtError> {
+        if data.len() < FDT_HEADER_SIZE {
+            return Err(FdtError::TooShort);
+        }
+        let word = |i: usize| be32(data, i * 4).unwrap_or(0);
+        if word(0) != FDT_MAGIC {
+            return Err(FdtError::BadMagic(word(0)));
+        }
+        let header = FdtHeader {
+            totalsize: word(1),
+            off_dt_struct: word(2),
+            off_dt_strings: word(3),
+            off_mem_rsvmap: word(4),
+            version: word(5),
+            last_comp_version: word(6),
+            boot_cpuid_phys: word(7),
+            size_dt_strings: word(8),
+            size_dt_struct: word(9),
+        };
+        let total = header.totalsize as usize;
+        if total < FDT_HEADER_SIZE || total > data.len() {
+            return Err(FdtError::BadTotalSize {
+                totalsize: header.totalsize,
+                available: data.len(),
+            });
+        }
+        if header.version < FDT_MIN_VERSION || header.last_comp_version > FDT_MAX_COMP_VERSION {
+            return Err(FdtError::UnsupportedVersion {
+                version: header.version,
+                last_comp_version: header.last_comp_version,
+            });
+        }
+        let data = &data[..total];
+        let block = |off: u32, size: u32, align: usize, name| {
+            let (off, size) = (off as usize, size as usize);
+            match off.checked_add(size) {
+                Some(end) if off >= FDT_HEADER_SIZE && end <= total && off % align == 0 => {
+                    Ok(&data[off..end])
+                }
+                _ => Err(FdtError::BlockOutOfBounds(name)),
+            }
+        };
+        // Version 16 has no size_dt_struct; the block then runs to totalsize.
+        let struct_size = if header.version >= 17 {
+            header.size_dt_struct
+        } else {
+            header.totalsize.saturating_sub(header.off_dt_struct)
+        };
+        let structure = block(header.off_dt_struct, struct_size, 4, "structure")?;
+        let strings = block(header.off_dt_strings, header.size_dt_strings, 1, "strings")?;
+
+        // The reservation map is a list of (address, size) u64 pairs ending in
+        // an all-zero entry.
+        block(header.off_mem_rsvmap, 0, 8, "memory reservation")?;
+        let mut at = header.off_mem_rsvmap as usize;
+        loop {
+            let entry = data
+                .get(at..at + 16)
+                .ok_or(FdtError::BlockOutOfBounds("memory reservation"))?;
+            at += 16;
+            if entry.iter().all(|&b| b == 0) {
+                break;
+            }
+        }
+
+        let fdt = Fdt {
+            header,
+            structure,
+            strings,
+        };
+        fdt.check_structure()?;
+        Ok(fdt)
+    }
+
+    /// Walks the structure block once, checking every token, name and value.
+    fn check_structure(&self) -> Result<(), FdtError> {
+        let s = self.structure;
+        let mut pos = 0;
+        let mut depth = 0usize;
+        let mut roots = 0;
+        loop {
+            let token = be32(s, pos).ok_or(FdtError::MissingEnd)?;
+            match token {
+                FDT_BEGIN_NODE => {
+                    let name = cstr(s, pos + 4).ok_or(FdtError::BadName { offset: pos })?;
+                    if depth == 0 {
+                        roots += 1;
+                        if roots > 1 {
+                            return Err(FdtError::Unbalanced);
+                        }
+                    }
+                    depth += 1;
+                    pos = align4(pos + 4 + name.len() + 1);
+                }
+                FDT_END_NODE => {
+                    depth = depth.checked_sub(1).ok_or(FdtError::Unbalanced)?;
+                    pos += 4;
+                }
+                FDT_PROP => {
+                    if depth == 0 {
+                        return Err(FdtError::BadToken { offset: pos, token });
+                    }
+                    let (len, nameoff) = match (be32(s, pos + 4), be32(s, pos + 8)) {
+                        (Some(len), Some(off)) => (len as usize, off as usize),
+                        _ => return Err(FdtError::TruncatedProperty { offset: pos }),
+                    };
+                    // `len` comes from the blob: on 32-bit targets the sum can wrap.
+                    let end = pos.checked_add(12).and_then(|p| p.checked_add(len));
+                    let end = match end {
+                        Some(end) if end <= s.len() => end,
+                        _ => return Err(FdtError::TruncatedProperty { offset: pos }),
+                    };
+                    cstr(self.strings, nameoff).ok_or(FdtError::BadName { offset: pos })?;
+                    pos = align4(end);
+                }
+                FDT_NOP => pos += 4,
+                FDT_END if depth == 0 && roots == 1 => return Ok(()),
+                FDT_END => return Err(FdtError::Unbalanced),
+                _ => return Err(FdtError::BadToken { offset: pos, token }),
+            }
+        }
+    }
+
+    /// The decoded header.
+    pub fn header(&self) -> &FdtHeader {
+        &self.header
+    }
+
+    /// Iterates over the structure-block tokens.
+    pub fn tokens(&self) -> Tokens<'a> {
+        Tokens {
+            structure: self.structure,
+            strings: self.strings,
+            pos: 0,
+        }
+    }
+
+    /// A property of the root node.
+    pub fn root_property(&self, name: &str) -> Option<Property<'a>> {
+        let mut depth = 0;
+        for token in self.tokens() {
+            match token {
+                Token::BeginNode(_) => depth += 1,
+                Token::EndNode => depth -= 1,
+                Token::Prop(p) if depth == 1 && p.name == name => return Some(p),
+                Token::Prop(_) => {}
+            }
+            // Root properties precede its children; stop at the first child.
+            if depth > 1 {
+                return None;
+            }
+        }
+        None
+    }
+
+    /// The first entry of the root `compatible` property.
+    pub fn compatible(&self) -> Option<&'a str> {
+        self.root_property("compatible")?.strings().next()
+    }
+
+    /// Iterates over the overlay fragments (direct children of the root,
+    /// except `__symbols__`, `__fixups__` and `__local_fixups__`).
+    pub fn fragments(&self) -> Fragments<'a> {
+        Fragments {
+            fdt: *self,
+            tokens: self.tokens(),
+            depth: 0,
+        }
+    }
+
+    /// Finds the label whose `/__fixups__` entry patches `node:prop:offset`,
+    /// where `node` is a path such as `/fragment@0`.
+    pub fn fixup_label(&self, node: &str, prop: &str, offset: u32) -> Option<&'a str> {
+        let mut depth = 0;
+        let mut in_fixups = false;
+        for token in self.tokens() {
+            match token {
+                Token::BeginNode(name) => {
+                    depth += 1;
+                    in_fixups = depth == 2 && name == "__fixups__";
+                }
+                Token::EndNode => {
+                    depth -= 1;
+                    in_fixups = false;
+                }
+                Token::Prop(p) if in_fixups && depth == 2 => {
+                    let hit = p.strings().any(|entry| {
+                        let mut parts = entry.rsplitn(3, ':');
+                        let off = parts.next().and_then(|o| o.parse::<u32>().ok());
+                        let name = parts.next();
+                        let path = parts.next();
+                        off == Some(offset) && name == Some(prop) && path == Some(node)
+                    });
+                    if hit {
+                        return Some(p.name);
+                    }
+                }
+                Token::Prop(_) => {}
+            }
+        }
+        None
+    }
+}
+
+impl<'a> Property<'a> {
+    /// The value as a `u32` cell, if it is exactly four bytes.
+    pub fn as_u32(&self) -> Option<u32> {
+        if self.value.len() == 4 {
+            be32(self.value, 0)
+        } else {
+            None
+        }
+    }
+
+    /// The value as a list of NUL-terminated strings (empty if it is not one).
+    pub fn strings(&self) -> impl Iterator<Item = &'a str> + 'a {
+        let value = self.value;
+        let valid = value.last() == Some(&0) && core::str::from_utf8(value).is_ok();
+        let body = if valid {
+            &value[..value.len() - 1]
+        } else {
+            &[]
+        };
+        body.split(|&b| b == 0)
+            .filter(move |_| valid)
+            .filter_map(|s| core::str::from_utf8(s).ok())
+    }
+}
+
+/// Iterator returned by [`Fdt::tokens`].
+#[derive(Debug, Clone)]
+pub struct Tokens<'a> {
+    structure: &'a [u8],
+    strings: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Iterator for Tokens<'a> {
+    type Item = Token<'a>;
+
+    fn next(&mut self) -> Option<Token<'a>> {
+        let s = self.structure;
+        loop {
+            let pos = self.pos;
+            match be32(s, pos)? {
+                FDT_BEGIN_NODE => {
+                    let name = cstr(s, pos + 4)?;
+                    self.pos = align4(pos + 4 + name.len() + 1);
+                    return Some(Token::BeginNode(name));
+                }
+                FDT_END_NODE => {
+                    self.pos = pos + 4;
+                    return Some(Token::EndNode);
+                }
+                FDT_PROP => {
+                    let len = be32(s, pos + 4)? as usize;
+                    let name = cstr(self.strings, be32(s, pos + 8)? as usize)?;
+                    let end = pos.checked_add(12)?.checked_add(len)?;
+                    let value = s.get(pos + 12..end)?;
+                    self.pos = align4(end);
+                    return Some(Token::Prop(Property { name, value }));
+                }
+                FDT_NOP => self.pos = pos + 4,
+                _ => {
+                    self.pos = s.len();
+                    return None;
+                }
+            }
+        }
+    }
+}
+
+/// Iterator returned by [`Fdt::fragments`].
+#[derive(Debug, Clone)]
+pub struct Fragments<'a> {
+    fdt: Fdt<'a>,
+    tokens: Tokens<'a>,
+    depth: usize,
+}
+
+impl<'a> Iterator for Fragments<'a> {
+    type Item = Fragment<'a>;
+
+    fn next(&mut self) -> Option<Fragment<'a>> {
+        let mut current: Option<Fragment<'a>> = None;
+        while let Some(token) = self.tokens.next() {
+            match token {
+                Token::BeginNode(name) => {
+                    self.depth += 1;
+                    if self.depth == 2 && !(name.starts_with("__") && name.ends_with("__")) {
+                        current = Some(Fragment {
+                            name,
+                            target: FragmentTarget::Missing,
+                        });
+                    }
+                }
+                Token::EndNode => {
+                    self.depth -= 1;
+                    if self.depth == 1 && current.is_some() {
+                        return current;
+                    }
+                }
+                Token::Prop(p) if self.depth == 2 => {
+                    if let Some(frag) = current.as_mut() {
+                        match p.name {
+                            "target-path" => {
+                                if let Some(path) = p.strings().next() {
+                                    frag.target = FragmentTarget::Path(path);
+                                }
+                            }
+                            "target" => {
+                                if let Some(phandle) = p.as_u32() {
+                                    frag.target = self
+                                        .fixup_target(frag.name)
+                                        .map_or(FragmentTarget::Phandle(phandle), |l| {
+                                            FragmentTarget::Label(l)
+                                        });
+                                }
+                            }
+                            _ => {}
+                        }
+                    }
+                }
+                Token::Prop(_) => {}
+            }
+        }
+        None
+    }
+}
+
+impl<'a> Fragments<'a> {
+    fn fixup_target(&self, fragment: &str) -> Option<&'a str> {
+        // `/__fixups__` entries name the node by absolute path.
+        let mut path = [0u8; 64];
+        let len = fragment.len() + 1;
+        let buf = path.get_mut(..len)?;
+        buf[0] = b'/';
+        buf[1..].copy_from_slice(fragment.as_bytes());
+        let path = core::str::from_utf8(buf).ok()?;
+        self.fdt.fixup_label(path, "target", 0)
+    }
+}
+
+#[cfg(all(test, feature = "alloc"))]
+pub(crate) mod tests {
+    use super::*;
+    use alloc::vec::Vec;
+
+    /// One entry for the minimal test FDT writer [`build`].
+    pub(crate) enum Item<'a> {
+        Begin(&'a str),
+        End,
+        Prop(&'a str, &'a [u8]),
+    }
+
+    pub(crate) fn build(items: &[Item<'_>]) -> Vec<u8> {
+        let mut st = Vec::new();
+        let mut strings: Vec<u8> = Vec::new();
+        for item in items {
+            match item {
+                Item::Begin(name) => {
+                    st.extend_from_slice(&FDT_BEGIN_NODE.to_be_bytes());
+                    st.extend_from_slice(name.as_bytes());
+                    st.push(0);
+                }
+                Item::End => st.extend_from_slice(&FDT_END_NODE.to_be_bytes()),
+                Item::Prop(name, value) => {
+                    let off = strings.len() as u32;
+                    strings.extend_from_slice(name.as_bytes());
+                    strings.push(0);
+                    st.extend_from_slice(&FDT_PROP.to_be_bytes());
+                    st.extend_from_slice(&(value.len() as u32).to_be_bytes());
+                    st.extend_from_slice(&off.to_be_bytes());
+                    st.extend_from_slice(value);
+                }
+            }
+            st.resize(align4(st.len()), 0);
+        }
+        st.extend_from_slice(&FDT_END.to_be_bytes());
+        let off_rsv = FDT_HEADER_SIZE;
+        let off_struct = off_rsv + 16;
+        let off_strings = off_struct + st.len();
+        let total = off_strings + strings.len();
+        let mut out = Vec::new();
+        for w in [
+            FDT_MAGIC,
+            total as u32,
+            off_struct as u32,
+            off_strings as u32,
+            off_rsv as u32,
+            17,
+            16,
+            0,
+            strings.len() as u32,
+            st.len() as u32,
+        ] {
+            out.extend_from_slice(&w.to_be_bytes());
+        }
+        out.extend_from_slice(&[0; 16]);
+        out.extend_from_slice(&st);
+        out.extend_from_slice(&strings);
+        out
+    }
+
+    pub(crate) fn sample_overlay() -> Vec<u8> {
+        use Item::*;
+        build(&[
+            Begin(""),
+            Prop("compatible", b"brcm,bcm2835\0"),
+            Begin("fragment@0"),
+            Prop("target", &[0xFF, 0xFF, 0xFF, 0xFF]),
+            Begin("__overlay__"),
+            Prop("status", b"okay\0"),
+            End,
+            End,
+            Begin("fragment@1"),
+            Prop("target-path", b"/soc/gpio\0"),
+            Begin("__overlay__"),
+            End,
+            End,
+            Begin("__fixups__"),
+            Prop("i2c1", b"/fragment@0:target:0\0"),
+            End,
+            End,
+        ])
+    }
+
+    #[test]
+    fn inspects_an_overlay() {
+        let blob = sample_overlay();
+        let fdt = Fdt::parse(&blob).unwrap();
+        assert_eq!(fdt.header().version, 17);
+        assert_eq!(fdt.compatible(), Some("brcm,bcm2835"));
+        let frags: Vec<_> = fdt.fragments().collect();
+        assert_eq!(
+            frags,
+            [
+                Fragment {
+                    name: "fragment@0",
+                    target: FragmentTarget::Label("i2c1"),
+                },
+                Fragment {
+                    name: "fragment@1",
+                    target: FragmentTarget::Path("/soc/gpio"),
+                },
+            ]
+        );
+        assert_eq!(fdt.tokens().count(), 17);
+
+        let mut eeprom = crate::parse_settings("").unwrap();
+        eeprom.add_dt_blob(blob.clone());
+        let text = alloc::format!("{eeprom}");
+        assert!(text.contains("(FDT v17, compatible \"brcm,bcm2835\")"));
+        assert!(text.contains("  fragment@0 -> &i2c1\n  fragment@1 -> /soc/gpio\n"));
+    }
+
+    #[test]
+    fn rejects_sources_and_truncation() {
+        assert_eq!(
+            Fdt::parse(b"/dts-v1/;\n/plugin/;\n/ { compatible = \"x\"; };\n").unwrap_err(),
+            FdtError::BadMagic(0x2f647473)
+        );
+        let blob = sample_overlay();
+        assert!(matches!(
+            Fdt::parse(&blob[..blob.len() - 8]),
+            Err(FdtError::BadTotalSize { .. })
+        ));
+        // Shrink size_dt_struct so FDT_END falls outside the structure block.
+        let mut short = blob.clone();
+        let size = u32::from_be_bytes([short[36], short[37], short[38], short[39]]) - 4;
+        short[36..40].copy_from_slice(&size.to_be_bytes());
+        assert_eq!(Fdt::parse(&short).unwrap_err(), FdtError::MissingEnd);
+        // A property length near u32::MAX must not wrap the offset.
+        let mut huge = blob.clone();
+        let len_at = FDT_HEADER_SIZE + 16 + 8 + 4;
+        huge[len_at..len_at + 4].copy_from_slice(&u32::MAX.to_be_bytes());
+        assert_eq!(
+            Fdt::parse(&huge).unwrap_err(),
+            FdtError::TruncatedProperty { offset: 8 }
+        );
+        let mut old = blob;
+        old[20..24].copy_from_slice(&1u32.to_be_bytes());
+        assert!(matches!(
+            Fdt::parse(&old),
+            Err(FdtError::UnsupportedVersion { version: 1, .. })
+        ));
+    }
+}
@@ -29,8 +29,16 @@ const PULL_SHIFT: u8 = 5;
 const PULL_MASK: u8 = 0b0110_0000;
 
 /// GPIO pin function (`func_sel`), using the BCM2835 FSEL field values.
+///
+/// With the `serde` feature this serializes as the `setgpio` keyword
+/// (`"INPUT"`, `"OUTPUT"`, `"ALT0"` … `"ALT5"`).
 #[repr(u8)]
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "UPPERCASE")
+)]
 pub enum PinFunc {
     Input = 0b000,
     Output = 0b001,
@@ -44,7 +52,7 @@ pub enum PinFunc {
 
 impl PinFunc {
     /// Decodes the `func_sel` from a pin byte.
-    pub fn from_bits(byte: u8) -> Self {
+    pub const fn from_bits(byte: u8) -> Self {
         match byte & FUNC_MASK {
             0b000 => PinFunc::Input,
             0b001 => PinFunc::Output,
@@ -56,11 +64,30 @@ impl PinFunc {
             _ => PinFunc::Alt5, // 0b010
         }
     }
+
+    /// The `setgpio` keyword, e.g. `"ALT0"`.
+    pub const fn keyword(self) -> &'static str {
+        match self {
+            PinFunc::Input => "INPUT",
+            PinFunc::Output => "OUTPUT",
+            PinFunc::Alt0 => "ALT0",
+            PinFunc::Alt1 => "ALT1",
+            PinFunc::Alt2 => "ALT2",
+            PinFunc::Alt3 => "ALT3",
+            PinFunc::Alt4 => "ALT4",
+            PinFunc::Alt5 => "ALT5",
+        }
+    }
 }
 
-/// GPIO pin pull setting.
+/// GPIO pin pull setting (`"DEFAULT"`, `"UP"`, `"DOWN"`, `"NONE"` with `serde`).
 #[repr(u8)]
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "UPPERCASE")
+)]
 pub enum PinPull {
     Default = 0,
     Up = 1,
@@ -70,7 +97,7 @@ pub enum PinPull {
 
 impl PinPull {
     /// Decodes the pull setting from a pin byte.
-    pub fn from_bits(byte: u8) -> Self {
+    pub const fn from_bits(byte: u8) -> Self {
         match (byte & PULL_MASK) >> PULL_SHIFT {
             0 => PinPull::Default,
             1 => PinPull::Up,
@@ -78,10 +105,21 @@ impl PinPull {
             _ => PinPull::None,
         }
     }
+
+    /// The `setgpio` keyword, e.g. `"DEFAULT"`.
+    pub const fn keyword(self) -> &'static str {
+        match self {
+            PinPull::Default => "DEFAULT",
+            PinPull::Up => "UP",
+            PinPull::Down => "DOWN",
+            PinPull::None => "NONE",
+        }
+    }
 }
 
 /// A decoded pin configuration.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct PinConfig {
     /// Whether the board uses this pin (bit 7).
     pub used: bool,
@@ -98,12 +136,12 @@ pub const UNUSED_PIN: u8 = 0;
 ///
 /// The "board uses this pin" flag (bit 7) is always set, so the result is never
 /// `0x00`; use [`UNUSED_PIN`] for pins the board leaves alone.
-pub fn encode_pin(func: PinFunc, pull: PinPull) -> u8 {
+pub const fn encode_pin(func: PinFunc, pull: PinPull) -> u8 {
     PIN_USED | (func as u8) | ((pull as u8) << PULL_SHIFT)
 }
 
 /// Decodes a pin byte into its [`PinConfig`].
-pub fn decode_pin(byte: u8) -> PinConfig {
+pub const fn decode_pin(byte: u8) -> PinConfig {
     PinConfig {
         used: byte & PIN_USED != 0,
         func: PinFunc::from_bits(byte),
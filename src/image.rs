@@ -0,0 +1,232 @@
+//  _  _       _             _  _
+// | || |  ___| |_ _ __ ___ | || |
+// | || |_/ __| __| '_ ` _ \| || |_
+// |__   _\__ | |_| | | | | |__   _|
+//   |_| |___/\__|_|_|_| |_|  |_|
+//! # ehatrom — EEPROM HAT library for Raspberry Pi HATs
+//! - [Documentation (docs.rs)](https://docs.rs/ehatrom)
+//! - [GitHub](https://github.com/4stm4/ehatrom)
+//!
+//! ## Compile-time image generation
+//!
+//! [`ImageBuilder`] assembles a complete, CRC-correct image entirely in
+//! `const fn`s, so firmware can embed a default HAT image in flash without a
+//! build-time generator and `include_bytes!`. Atoms are written in call
+//! order; call the methods in the order below to get the same bytes as
+//! [`Eeprom::serialize`](crate::Eeprom::serialize):
+//! `vendor_info`, `gpio_bank0`, `dt_blob`, `gpio_bank1`, `power_supply`,
+//! then `custom` for each custom atom.
+//!
+//! The array length `N` must equal the image length, which [`build`] checks
+//! (a mismatch fails compilation when evaluated in a `const`). The
+//! [`hat_image!`](crate::hat_image) macro measures the image with an
+//! `ImageBuilder::<0>` first, so you never spell `N` out:
+//!
+//! ```
+//! use ehatrom::{GpioMapAtom, PinFunc, PinPull, VendorInfoAtom, encode_pin, hat_image};
+//!
+//! const VENDOR: VendorInfoAtom = VendorInfoAtom::new(0x0001, 1, "ACME", "Sensor HAT", [0; 16]);
+//! const GPIO: GpioMapAtom = {
+//!     let mut pins = [0u8; 28];
+//!     pins[4] = encode_pin(PinFunc::Output, PinPull::Up);
+//!     GpioMapAtom { flags: 0, power: 0, pins }
+//! };
+//!
+//! static HAT: &[u8] = &hat_image!(vendor_info(&VENDOR).gpio_bank0(&GPIO).power_supply(500));
+//!
+//! assert!(ehatrom::Eeprom::verify(HAT));
+//! let view = ehatrom::EepromView::new(HAT).unwrap();
+//! assert_eq!(view.vendor_info().unwrap().product(), Ok("Sensor HAT"));
+//! ```
+//!
+//! [`build`]: ImageBuilder::build
+
+use crate::{
+    ATOM_HDR_SIZE, AtomType, CRC_SIZE, EEPROM_SIGNATURE, FORMAT_VERSION, GPIO_COUNT,
+    GPIO_COUNT_BANK1, GpioMapAtom, HEADER_SIZE, VENDOR_FIXED_SIZE, VendorInfoAtom, copy_into,
+    write_atom,
+};
+
+/// Builds an image of exactly `N` bytes in `const` context.
+#[derive(Debug, Clone, Copy)]
+pub struct ImageBuilder<const N: usize> {
+    buf: [u8; N],
+    len: usize,
+    count: u16,
+    version: u8,
+}
+
+impl<const N: usize> Default for ImageBuilder<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize> ImageBuilder<N> {
+    /// An image with only the header, at [`FORMAT_VERSION`].
+    pub const fn new() -> Self {
+        ImageBuilder {
+            buf: [0; N],
+            len: HEADER_SIZE,
+            count: 0,
+            version: FORMAT_VERSION,
+        }
+    }
+
+    /// Sets the header format version.
+    pub const fn version(mut self, version: u8) -> Self {
+        self.version = version;
+        self
+    }
+
+    /// Appends an atom of any type. Atoms that do not fit in `N` bytes are
+    /// only counted, which is how `ImageBuilder::<0>` measures an image.
+    pub const fn atom(mut self, atom_type: u16, data: &[u8]) -> Self {
+        let mut offset = self.len;
+        let mut count = self.count;
+        if write_atom(&mut self.buf, &mut offset, &mut count, atom_type, data).is_err() {
+            offset += ATOM_HDR_SIZE + data.len() + CRC_SIZE;
+            count += 1;
+        }
+        self.len = offset;
+        self.count = count;
+        self
+    }
+
+    /// Appends the vendor-info atom.
+    pub const fn vendor_info(self, atom: &VendorInfoAtom) -> Self {
+        let mut body = [0u8; VENDOR_FIXED_SIZE + 32];
+        let len = atom.encode(&mut body);
+        self.atom(AtomType::VendorInfo as u16, body.split_at(len).0)
+    }
+
+    /// Appends the bank0 GPIO map atom.
+    pub const fn gpio_bank0(self, atom: &GpioMapAtom) -> Self {
+        let mut body = [0u8; 2 + GPIO_COUNT];
+        atom.encode_bank0(&mut body);
+        self.atom(AtomType::GpioMapBank0 as u16, &body)
+    }
+
+    /// Appends the DT blob atom (a compiled overlay or an overlay name).
+    pub const fn dt_blob(self, data: &[u8]) -> Self {
+        self.atom(AtomType::DtBlob as u16, data)
+    }
+
+    /// Appends the bank1 GPIO map atom.
+    pub const fn gpio_bank1(self, atom: &GpioMapAtom) -> Self {
+        let mut body = [0u8; 2 + GPIO_COUNT_BANK1];
+        atom.encode_bank1(&mut body);
+        self.atom(AtomType::GpioMapBank1 as u16, &body)
+    }
+
+    /// Appends the power-supply atom: required back-power current in mA.
+    pub const fn power_supply(self, current_ma: u32) -> Self {
+        self.atom(AtomType::PowerSupply as u16, &current_ma.to_le_bytes())
+    }
+
+    /// Appends a custom atom.
+    pub const fn custom(self, data: &[u8]) -> Self {
+        self.atom(AtomType::Custom as u16, data)
+    }
+
+    /// Length of the image built so far, including the header.
+    pub const fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Always `false`: the header alone is 12 bytes.
+    pub const fn is_empty(&self) -> bool {
+        false
+    }
+
+    /// Writes the header and returns the image.
+    ///
+    /// # Panics
+    ///
+    /// If `N` differs from [`ImageBuilder::len`].
+    pub const fn build(mut self) -> [u8; N] {
+        assert!(
+            self.len == N,
+            "ImageBuilder: N must equal the image length (see `hat_image!`)"
+        );
+        copy_into(&mut self.buf, 0, &EEPROM_SIGNATURE);
+        self.buf[4] = self.version;
+        copy_into(&mut self.buf, 6, &self.count.to_le_bytes());
+        copy_into(&mut self.buf, 8, &(self.len as u32).to_le_bytes());
+        self.buf
+    }
+}
+
+/// Builds a HAT image as a `[u8; N]` constant, with `N` worked out for you.
+///
+/// Takes a chain of [`ImageBuilder`] method calls. Every argument must be a
+/// constant expression.
+///
+/// ```
+/// # use ehatrom::{GpioMapAtom, VendorInfoAtom, hat_image};
+/// const VENDOR: VendorInfoAtom = VendorInfoAtom::new(0x0001, 1, "ACME", "HAT", [0; 16]);
+/// const GPIO: GpioMapAtom = GpioMapAtom { flags: 0, power: 0, pins: [0; 28] };
+/// const IMAGE: &[u8] = &hat_image!(vendor_info(&VENDOR).gpio_bank0(&GPIO).custom(b"serial=42"));
+/// assert!(ehatrom::Eeprom::verify(IMAGE));
+/// ```
+#[macro_export]
+macro_rules! hat_image {
+    ($($method:ident ( $($arg:expr),* $(,)? )).+ $(,)?) => {{
+        const LEN: usize = $crate::image::ImageBuilder::<0>::new()
+            $(.$method($($arg),*))+
+            .len();
+        const IMAGE: [u8; LEN] = $crate::image::ImageBuilder::<LEN>::new()
+            $(.$method($($arg),*))+
+            .build();
+        IMAGE
+    }};
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    use super::*;
+    use crate::{PinFunc, PinPull, encode_pin, parse_settings};
+
+    const VENDOR: VendorInfoAtom = VendorInfoAtom::new(0x1234, 2, "ACME", "Sensor HAT", [0x11; 16]);
+    const GPIO: GpioMapAtom = {
+        let mut pins = [0u8; GPIO_COUNT];
+        pins[4] = encode_pin(PinFunc::Output, PinPull::Up);
+        GpioMapAtom {
+            flags: 0x13,
+            power: 1,
+            pins,
+        }
+    };
+    const IMAGE: &[u8] = &crate::hat_image!(
+        vendor_info(&VENDOR)
+            .gpio_bank0(&GPIO)
+            .dt_blob(b"acme-sensor")
+            .gpio_bank1(&GPIO)
+            .power_supply(1500)
+            .custom(b"first")
+            .custom(&[1, 2, 3])
+    );
+
+    #[test]
+    fn const_image_matches_serialize() {
+        let mut eeprom = parse_settings("").unwrap();
+        eeprom.vendor_info = VENDOR;
+        eeprom.gpio_map_bank0 = GPIO;
+        eeprom.set_dt_overlay("acme-sensor").unwrap();
+        eeprom.add_gpio_map_bank1(GPIO);
+        eeprom.add_power_supply(1500);
+        eeprom.add_custom_atom(b"first".to_vec());
+        eeprom.add_custom_atom(alloc::vec![1, 2, 3]);
+        assert_eq!(IMAGE, eeprom.serialize());
+        assert_eq!(
+            ImageBuilder::<0>::new().vendor_info(&VENDOR).len(),
+            12 + 8 + 36 + 2
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "N must equal the image length")]
+    fn build_checks_the_length() {
+        ImageBuilder::<64>::new().vendor_info(&VENDOR).build();
+    }
+}
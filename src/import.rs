@@ -0,0 +1,278 @@
+//  _  _       _             _  _
+// | || |  ___| |_ _ __ ___ | || |
+// | || |_/ __| __| '_ ` _ \| || |_
+// |__   _\__ | |_| | | | | |__   _|
+//   |_| |___/\__|_|_|_| |_|  |_|
+//! # ehatrom — EEPROM HAT library for Raspberry Pi HATs
+//! - [Documentation (docs.rs)](https://docs.rs/ehatrom)
+//! - [GitHub](https://github.com/4stm4/ehatrom)
+//!
+//! ## GPIO import from `pinctrl get` / `raspi-gpio get`
+//!
+//! [`parse_pin_dump`] reads the text either tool prints on a running Pi and
+//! builds the GPIO maps that reproduce it, so a configuration tried out
+//! interactively can go straight into an EEPROM. Both formats may be mixed;
+//! each line is recognised on its own:
+//!
+//! - `pinctrl get`: `17: op dh pd | lo // GPIO17 = output`. The function is
+//!   `ip`, `op` or `a0`…`a5`; `no` (no function, the Pi 5 reset state) leaves
+//!   the pin unused. `dh`/`dl` are ignored. The pull is `pu`, `pd` or `pn`;
+//!   `--` or nothing keeps `DEFAULT`.
+//! - `raspi-gpio get`: `GPIO 17: level=0 fsel=1 func=OUTPUT pull=DOWN`. `fsel`
+//!   is the BCM2835 function-select value; `pull=` is only printed on the
+//!   Pi 4 and later.
+//!
+//! Other lines (bank headers, blank lines) are skipped. Both tools list every
+//! GPIO, most of them in their reset state, so pass a filter (see
+//! [`parse_pin_list`]) naming the pins the HAT uses. Without one, every
+//! listed GPIO from 2 to 45 is taken; the ID EEPROM pins 0/1 never are, and
+//! GPIO 46 and up do not fit a HAT GPIO map. On a Pi 5, GPIO 28 and up are
+//! RP1-internal, not header pins.
+//!
+//! Only the lines of taken GPIOs are checked, so an untrimmed capture works
+//! even where other pins use functions a HAT cannot set, such as the RP1's
+//! `a6`…`a8`.
+
+use crate::{Eeprom, GPIO_COUNT, GPIO_COUNT_BANK1, GpioMapAtom, PinFunc, PinPull, encode_pin};
+use alloc::vec::Vec;
+use core::fmt;
+
+/// Error returned by [`parse_pin_dump`], carrying the 1-based line number.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ImportError {
+    /// 1-based line number where parsing failed.
+    pub line: usize,
+    /// Human-readable reason.
+    pub reason: &'static str,
+}
+
+impl fmt::Display for ImportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "pin dump error on line {}: {}", self.line, self.reason)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ImportError {}
+
+/// GPIO maps built by [`parse_pin_dump`], with zero flags and back-power.
+#[derive(Debug, Clone, Copy)]
+pub struct ImportedPins {
+    pub bank0: GpioMapAtom,
+    /// Present when a GPIO from 28 to 45 is used.
+    pub bank1: Option<GpioMapAtom>,
+}
+
+impl ImportedPins {
+    /// Replaces the pins of `eeprom`'s GPIO maps, keeping the bank0 flags
+    /// and back-power. A bank1 map is dropped when no bank1 pin was imported.
+    pub fn apply(&self, eeprom: &mut Eeprom) {
+        eeprom.gpio_map_bank0.pins = self.bank0.pins;
+        eeprom.gpio_map_bank1 = match (self.bank1, eeprom.gpio_map_bank1) {
+            (Some(imported), Some(mut existing)) => {
+                existing.pins = imported.pins;
+                Some(existing)
+            }
+            (imported, _) => imported,
+        };
+    }
+}
+
+/// Parses a pin list such as `4,17,22-27`.
+pub fn parse_pin_list(list: &str) -> Option<Vec<usize>> {
+    let mut pins = Vec::new();
+    for item in list.split(',') {
+        let item = item.trim();
+        match item.split_once('-') {
+            Some((first, last)) => {
+                let (first, last): (usize, usize) = (first.parse().ok()?, last.parse().ok()?);
+                if first > last {
+                    return None;
+                }
+                pins.extend(first..=last);
+            }
+            None => pins.push(item.parse().ok()?),
+        }
+    }
+    Some(pins)
+}
+
+fn pinctrl_func(tok: &str) -> Option<Option<PinFunc>> {
+    Some(Some(match tok {
+        "ip" => PinFunc::Input,
+        "op" => PinFunc::Output,
+        "a0" => PinFunc::Alt0,
+        "a1" => PinFunc::Alt1,
+        "a2" => PinFunc::Alt2,
+        "a3" => PinFunc::Alt3,
+        "a4" => PinFunc::Alt4,
+        "a5" => PinFunc::Alt5,
+        "no" | "none" => return Some(None),
+        _ => return None,
+    }))
+}
+
+/// The function and pull of a line after the `<gpio>:`, or `None` if unused.
+type PinState = Result<Option<(PinFunc, PinPull)>, &'static str>;
+
+/// Splits `17: op dh pd` into the GPIO number and the rest of the line.
+fn split_gpio<'a>(line: &'a str, expected: &'static str) -> Result<(usize, &'a str), &'static str> {
+    let (gpio, rest) = line.split_once(':').ok_or(expected)?;
+    let gpio = gpio.trim().parse().map_err(|_| "invalid GPIO number")?;
+    Ok((gpio, rest))
+}
+
+/// `op dh pd | lo // GPIO17 = output`.
+fn parse_pinctrl(rest: &str) -> PinState {
+    let state = rest.split('|').next().unwrap_or("");
+    let mut tokens = state.split_whitespace();
+    let func = tokens.next().ok_or("missing function")?;
+    let func = match pinctrl_func(func) {
+        Some(func) => func,
+        None if func.starts_with('a') => return Err("function cannot be set from a HAT EEPROM"),
+        None => return Err("unknown function"),
+    };
+    let mut pull = PinPull::Default;
+    for tok in tokens {
+        pull = match tok {
+            "dh" | "dl" | "--" => continue,
+            "pu" => PinPull::Up,
+            "pd" => PinPull::Down,
+            "pn" => PinPull::None,
+            _ => return Err("unknown pull"),
+        };
+    }
+    Ok(func.map(|func| (func, pull)))
+}
+
+/// `level=0 fsel=1 func=OUTPUT pull=DOWN`.
+fn parse_raspi_gpio(rest: &str) -> PinState {
+    let mut fsel = None;
+    let mut pull = PinPull::Default;
+    for (key, value) in rest.split_whitespace().filter_map(|kv| kv.split_once('=')) {
+        match key {
+            "fsel" => fsel = value.parse::<u8>().ok().filter(|&v| v < 8),
+            "pull" => {
+                pull = match value {
+                    "UP" => PinPull::Up,
+                    "DOWN" => PinPull::Down,
+                    "NONE" => PinPull::None,
+                    _ => return Err("unknown pull"),
+                }
+            }
+            _ => {}
+        }
+    }
+    let fsel = fsel.ok_or("missing or invalid fsel")?;
+    Ok(Some((PinFunc::from_bits(fsel), pull)))
+}
+
+/// Builds GPIO maps from `pinctrl get` or `raspi-gpio get` output, taking
+/// only the GPIOs in `filter` if one is given. Lines of other GPIOs are not
+/// checked, so functions a HAT cannot set elsewhere on the SoC do not matter.
+pub fn parse_pin_dump(text: &str, filter: Option<&[usize]>) -> Result<ImportedPins, ImportError> {
+    let mut pins = [0u8; GPIO_COUNT + GPIO_COUNT_BANK1];
+    for (idx, raw) in text.lines().enumerate() {
+        let line = raw.trim();
+        let err = |reason| ImportError {
+            line: idx + 1,
+            reason,
+        };
+        let (parse, split): (fn(&str) -> PinState, _) = if line.starts_with("GPIO ") {
+            let split = split_gpio(&line["GPIO".len()..], "expected `GPIO <n>: ...`");
+            (parse_raspi_gpio, split)
+        } else if line.starts_with(|c: char| c.is_ascii_digit()) {
+            (
+                parse_pinctrl,
+                split_gpio(line, "expected `<gpio>: <function>`"),
+            )
+        } else {
+            continue;
+        };
+        let (gpio, rest) = split.map_err(err)?;
+        let wanted = match filter {
+            Some(filter) => filter.contains(&gpio),
+            None => (2..pins.len()).contains(&gpio),
+        };
+        if !wanted {
+            continue;
+        }
+        if let (Some(slot), Some((func, pull))) = (pins.get_mut(gpio), parse(rest).map_err(err)?) {
+            *slot = encode_pin(func, pull);
+        }
+    }
+
+    let bank = |pins: &[u8]| {
+        let mut atom = GpioMapAtom {
+            flags: 0,
+            power: 0,
+            pins: [0u8; GPIO_COUNT],
+        };
+        atom.pins[..pins.len()].copy_from_slice(pins);
+        atom
+    };
+    let (bank0, bank1) = pins.split_at(GPIO_COUNT);
+    Ok(ImportedPins {
+        bank0: bank(bank0),
+        bank1: bank1.iter().any(|&b| b != 0).then(|| bank(bank1)),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PINCTRL_PI5: &str = include_str!("../tests/fixtures/pinctrl-get-pi5.txt");
+    const RASPI_GPIO_PI4: &str = include_str!("../tests/fixtures/raspi-gpio-get-pi4.txt");
+
+    #[test]
+    fn imports_pinctrl_output() {
+        let pins = parse_pin_dump(
+            PINCTRL_PI5,
+            Some(&parse_pin_list("2-4,14,15,17,18").unwrap()),
+        )
+        .unwrap();
+        let bank0 = pins.bank0.pins;
+        assert_eq!(bank0[2], encode_pin(PinFunc::Alt3, PinPull::Up));
+        assert_eq!(bank0[14], encode_pin(PinFunc::Alt4, PinPull::None));
+        assert_eq!(bank0[18], encode_pin(PinFunc::Output, PinPull::Down));
+        // GPIO 17 has no function selected, GPIO 5 is filtered out.
+        assert_eq!((bank0[17], bank0[5]), (0, 0));
+        assert!(pins.bank1.is_none());
+
+        // Without a filter, unconfigured (`no`) pins and the ID pins stay
+        // unused; the RP1 lines from GPIO 46 on, with functions a6..a8, are
+        // skipped rather than rejected.
+        let all = parse_pin_dump(PINCTRL_PI5, None).unwrap();
+        assert_eq!((all.bank0.pins[0], all.bank0.pins[17]), (0, 0));
+        assert_eq!(all.bank0.pins[4], encode_pin(PinFunc::Input, PinPull::Up));
+        let bank1 = all.bank1.unwrap().pins;
+        assert_eq!(bank1[45 - 28], encode_pin(PinFunc::Alt0, PinPull::Down));
+
+        // A wanted pin with an unsupported function is still an error.
+        let err = parse_pin_dump(PINCTRL_PI5, Some(&[4, 48])).unwrap_err();
+        assert_eq!(err.line, 49);
+        assert_eq!(err.reason, "function cannot be set from a HAT EEPROM");
+        let err = parse_pin_dump("3: a3 pq | hi\n4: ip xx | hi\n", Some(&[4])).unwrap_err();
+        assert_eq!((err.line, err.reason), (2, "unknown pull"));
+    }
+
+    #[test]
+    fn imports_raspi_gpio_output_with_bank1() {
+        let pins = parse_pin_dump(RASPI_GPIO_PI4, Some(&[2, 3, 14, 17, 40, 41, 50])).unwrap();
+        assert_eq!(pins.bank0.pins[2], encode_pin(PinFunc::Alt0, PinPull::Up));
+        assert_eq!(
+            pins.bank0.pins[17],
+            encode_pin(PinFunc::Output, PinPull::Down)
+        );
+        let bank1 = pins.bank1.unwrap().pins;
+        assert_eq!(bank1[40 - 28], encode_pin(PinFunc::Alt0, PinPull::None));
+
+        let mut eeprom = crate::parse_settings("gpio_drive 3\nsetgpio 30 INPUT\n").unwrap();
+        pins.apply(&mut eeprom);
+        assert_eq!(eeprom.gpio_map_bank0.flags, 3);
+        assert_eq!(eeprom.gpio_map_bank1.unwrap().pins[30 - 28], 0);
+        assert_eq!(eeprom.gpio_map_bank1.unwrap().pins[41 - 28], bank1[41 - 28]);
+        assert_eq!(parse_pin_list("3,1-x"), None);
+    }
+}
@@ -123,15 +123,58 @@ impl core::fmt::Display for ValidationError {
 #[cfg(feature = "std")]
 impl std::error::Error for ValidationError {}
 
+pub mod digest;
+pub mod fdt;
 pub mod gpio;
+pub mod image;
+pub mod pinout;
+pub mod soc;
+pub mod tlv;
 pub mod utils;
+pub mod view;
 pub use gpio::{PinConfig, PinFunc, PinPull, UNUSED_PIN, decode_pin, encode_pin};
+pub use image::ImageBuilder;
+pub use pinout::PinTable;
+pub use soc::Soc;
 use utils::crc16::crc16;
+pub use view::EepromView;
 
 #[cfg(feature = "alloc")]
 pub mod settings;
 #[cfg(feature = "alloc")]
-pub use settings::{SettingsError, parse_settings};
+pub use settings::{SettingsError, parse_settings, parse_settings_with_loader};
+
+#[cfg(feature = "alloc")]
+pub mod conflicts;
+#[cfg(feature = "alloc")]
+pub mod custom;
+#[cfg(feature = "alloc")]
+pub mod diff;
+#[cfg(feature = "alloc")]
+pub mod dts;
+#[cfg(feature = "alloc")]
+pub mod edit;
+#[cfg(feature = "alloc")]
+pub mod export;
+#[cfg(feature = "alloc")]
+pub mod extract;
+#[cfg(feature = "alloc")]
+pub mod import;
+#[cfg(feature = "alloc")]
+pub mod lint;
+#[cfg(feature = "alloc")]
+pub mod repair;
+
+#[cfg(feature = "serde")]
+pub mod schema;
+
+#[cfg(feature = "toml")]
+pub mod board;
+
+#[cfg(feature = "sign")]
+pub mod auth;
+#[cfg(feature = "sign")]
+pub mod sign;
 
 #[cfg(all(feature = "linux", any(target_os = "linux", target_os = "android")))]
 use i2cdev::{core::I2CDevice, linux::LinuxI2CDevice};
@@ -379,6 +422,63 @@ impl core::fmt::Display for DtBlobAtom {
     }
 }
 
+/// Decoded contents of the device-tree atom (`0x0003`), see [`Eeprom::dt_atom`].
+///
+/// Besides a compiled overlay, HAT+ firmware accepts the *name* of an overlay
+/// to load from `/boot/overlays`, optionally followed by `dtoverlay`-style
+/// parameters (`my-hat,addr=0x51,irq`). That is much smaller than the blob.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DtAtom<'a> {
+    /// A compiled device tree (anything that is not an overlay name).
+    Blob(&'a [u8]),
+    /// An overlay name with its comma-separated parameters (possibly empty).
+    Overlay { name: &'a str, params: &'a str },
+}
+
+impl<'a> DtAtom<'a> {
+    /// Classifies DT atom data. Data starting with the FDT magic is a blob;
+    /// otherwise a valid overlay spec (see [`DtAtom::overlay`]) is an overlay
+    /// name. A trailing NUL after the name is accepted.
+    pub fn parse(data: &'a [u8]) -> Self {
+        if data.starts_with(&fdt::FDT_MAGIC.to_be_bytes()) {
+            return DtAtom::Blob(data);
+        }
+        let text = data.strip_suffix(&[0]).unwrap_or(data);
+        match core::str::from_utf8(text).ok().and_then(DtAtom::overlay) {
+            Some(atom) => atom,
+            None => DtAtom::Blob(data),
+        }
+    }
+
+    /// Parses a `name[,param[=value]]...` overlay spec, as written after
+    /// `dtoverlay=` in `config.txt`. The name may use ASCII letters, digits,
+    /// `-`, `_` and `.`; parameters may use any printable ASCII except spaces.
+    pub fn overlay(spec: &'a str) -> Option<Self> {
+        let (name, params) = spec.split_once(',').unwrap_or((spec, ""));
+        let name_ok = !name.is_empty()
+            && name
+                .bytes()
+                .all(|b| b.is_ascii_alphanumeric() || b"-_.".contains(&b));
+        let params_ok = params.bytes().all(|b| b.is_ascii_graphic());
+        (name_ok && params_ok).then_some(DtAtom::Overlay { name, params })
+    }
+
+    /// The overlay parameters as `(name, value)` pairs; empty for a blob.
+    pub fn params(&self) -> impl Iterator<Item = (&'a str, Option<&'a str>)> + 'a {
+        let params = match self {
+            DtAtom::Overlay { params, .. } => *params,
+            DtAtom::Blob(_) => "",
+        };
+        params
+            .split(',')
+            .filter(|p| !p.is_empty())
+            .map(|p| match p.split_once('=') {
+                Some((k, v)) => (k, Some(v)),
+                None => (p, None),
+            })
+    }
+}
+
 #[repr(C, packed)]
 #[derive(Clone, Copy)]
 pub struct CustomAtom<const N: usize> {
@@ -440,7 +540,8 @@ pub struct Eeprom {
     pub power_supply: Option<u32>,
     /// Manufacturer custom atoms, each a raw data blob. The HAT format has no
     /// per-atom sub-type, so every entry is emitted with the spec custom type
-    /// `0x0004`; distinguish multiple custom atoms by their content.
+    /// `0x0004`; distinguish multiple custom atoms by their content, or keep
+    /// tagged records in one atom with the [`tlv`] container format.
     #[cfg(feature = "alloc")]
     pub custom_atoms: Vec<Vec<u8>>,
     #[cfg(not(feature = "alloc"))]
@@ -448,29 +549,44 @@ pub struct Eeprom {
 }
 
 /// Returns the used length of a fixed string buffer (up to the first NUL).
-fn string_len(buf: &[u8]) -> usize {
-    buf.iter().position(|&b| b == 0).unwrap_or(buf.len())
+const fn string_len(buf: &[u8]) -> usize {
+    let mut n = 0;
+    while n < buf.len() && buf[n] != 0 {
+        n += 1;
+    }
+    n
+}
+
+/// Copies `src` into `buf` at `at`; a `const` stand-in for
+/// `buf[at..at + src.len()].copy_from_slice(src)`.
+const fn copy_into(buf: &mut [u8], at: usize, src: &[u8]) {
+    let mut i = 0;
+    while i < src.len() {
+        buf[at + i] = src[i];
+        i += 1;
+    }
 }
 
 impl VendorInfoAtom {
     /// Encodes the vendor-info atom body into `out`, returning its length.
     ///
     /// `out` must be at least [`VENDOR_FIXED_SIZE`] + 32 bytes.
-    fn encode(&self, out: &mut [u8]) -> usize {
-        let vslen = string_len(&self.vendor);
-        let pslen = string_len(&self.product);
-        out[0..16].copy_from_slice(&self.uuid);
-        out[16..18].copy_from_slice(&self.product_id.to_le_bytes());
-        out[18..20].copy_from_slice(&self.product_ver.to_le_bytes());
+    const fn encode(&self, out: &mut [u8]) -> usize {
+        let (vendor, product) = (self.vendor, self.product);
+        let vslen = string_len(&vendor);
+        let pslen = string_len(&product);
+        copy_into(out, 0, &self.uuid);
+        copy_into(out, 16, &self.product_id.to_le_bytes());
+        copy_into(out, 18, &self.product_ver.to_le_bytes());
         out[20] = vslen as u8;
         out[21] = pslen as u8;
-        out[22..22 + vslen].copy_from_slice(&self.vendor[..vslen]);
-        out[22 + vslen..22 + vslen + pslen].copy_from_slice(&self.product[..pslen]);
+        copy_into(out, 22, vendor.split_at(vslen).0);
+        copy_into(out, 22 + vslen, product.split_at(pslen).0);
         VENDOR_FIXED_SIZE + vslen + pslen
     }
 
     /// On-the-wire size of this atom's data.
-    fn data_len(&self) -> usize {
+    const fn data_len(&self) -> usize {
         VENDOR_FIXED_SIZE + string_len(&self.vendor) + string_len(&self.product)
     }
 
@@ -508,18 +624,52 @@ impl VendorInfoAtom {
 }
 
 impl GpioMapAtom {
+    /// Sets the bank flags and back-power, checking them against the spec
+    /// ranges: `drive` 0..8, `slew`, `hysteresis` and `back_power` 0..2. The
+    /// JSON, TOML and `edit` inputs all validate through this.
+    pub fn set_bank_flags(
+        &mut self,
+        drive: u8,
+        slew: u8,
+        hysteresis: u8,
+        back_power: u8,
+    ) -> Result<(), &'static str> {
+        if drive > 8 {
+            return Err("GPIO drive out of range (0..8)");
+        }
+        if slew > 2 {
+            return Err("GPIO slew out of range (0..2)");
+        }
+        if hysteresis > 2 {
+            return Err("GPIO hysteresis out of range (0..2)");
+        }
+        if back_power > 2 {
+            return Err("GPIO back_power out of range (0..2)");
+        }
+        self.flags = drive | (slew << 4) | (hysteresis << 6);
+        self.power = back_power;
+        Ok(())
+    }
+
+    /// The `(drive, slew, hysteresis, back_power)` fields of the bank.
+    #[cfg(feature = "alloc")]
+    pub(crate) fn bank_flags(&self) -> (u8, u8, u8, u8) {
+        let flags = self.flags;
+        (flags & 0x0F, (flags >> 4) & 0x03, flags >> 6, self.power)
+    }
+
     /// Encodes a bank0 GPIO map body (30 bytes) into `out`.
-    fn encode_bank0(&self, out: &mut [u8; 2 + GPIO_COUNT]) {
+    const fn encode_bank0(&self, out: &mut [u8; 2 + GPIO_COUNT]) {
         out[0] = self.flags;
         out[1] = self.power;
-        out[2..].copy_from_slice(&self.pins);
+        copy_into(out, 2, &self.pins);
     }
 
     /// Encodes a bank1 GPIO map body (20 bytes) into `out`.
-    fn encode_bank1(&self, out: &mut [u8; 2 + GPIO_COUNT_BANK1]) {
+    const fn encode_bank1(&self, out: &mut [u8; 2 + GPIO_COUNT_BANK1]) {
         out[0] = self.flags;
         out[1] = self.power;
-        out[2..].copy_from_slice(&self.pins[..GPIO_COUNT_BANK1]);
+        copy_into(out, 2, self.pins.split_at(GPIO_COUNT_BANK1).0);
     }
 
     /// Parses a GPIO map body; `pins` beyond the supplied data are left zero.
@@ -629,7 +779,8 @@ impl Eeprom {
     }
 
     /// Parses an EEPROM image from a `'static` byte slice without heap
-    /// allocations (`no_std`). Custom atoms are skipped in this mode.
+    /// allocations (`no_std`). Custom atoms are skipped in this mode; to read
+    /// them, or to read from a non-`'static` buffer, use [`EepromView`].
     #[cfg(not(feature = "alloc"))]
     pub fn from_bytes_no_alloc(data: &'static [u8]) -> Result<Self, &'static str> {
         if data.len() < HEADER_SIZE {
@@ -793,6 +944,21 @@ impl Eeprom {
         self.update_header();
     }
 
+    /// Makes the DT atom name an overlay instead of carrying a blob (HAT+).
+    /// `spec` is `name[,param[=value]]...`, as after `dtoverlay=` in
+    /// `config.txt`; it is stored as-is, without a trailing NUL.
+    #[cfg(feature = "alloc")]
+    pub fn set_dt_overlay(&mut self, spec: &str) -> Result<(), &'static str> {
+        DtAtom::overlay(spec).ok_or("invalid overlay name or parameters")?;
+        self.add_dt_blob(spec.as_bytes().to_vec());
+        Ok(())
+    }
+
+    /// The DT atom, classified as a compiled blob or an overlay name.
+    pub fn dt_atom(&self) -> Option<DtAtom<'_>> {
+        self.dt_blob_bytes().map(DtAtom::parse)
+    }
+
     pub fn add_gpio_map_bank1(&mut self, atom: GpioMapAtom) {
         self.gpio_map_bank1 = Some(atom);
         self.update_header();
@@ -1073,7 +1239,7 @@ impl Eeprom {
 ///
 /// `dlen` is set to `data.len() + 2` and the CRC-16 is computed over the 8-byte
 /// atom header together with `data`, exactly as the reference `eepmake` does.
-fn write_atom(
+const fn write_atom(
     buf: &mut [u8],
     offset: &mut usize,
     count: &mut u16,
@@ -1086,13 +1252,13 @@ fn write_atom(
         return Err(EhatromError::BufferTooSmall);
     }
     let dlen = (data.len() + CRC_SIZE) as u32;
-    buf[start..start + 2].copy_from_slice(&atom_type.to_le_bytes());
-    buf[start + 2..start + 4].copy_from_slice(&count.to_le_bytes());
-    buf[start + 4..start + 8].copy_from_slice(&dlen.to_le_bytes());
+    copy_into(buf, start, &atom_type.to_le_bytes());
+    copy_into(buf, start + 2, &count.to_le_bytes());
+    copy_into(buf, start + 4, &dlen.to_le_bytes());
     let body_end = start + ATOM_HDR_SIZE + data.len();
-    buf[start + ATOM_HDR_SIZE..body_end].copy_from_slice(data);
-    let crc = crc16(&buf[start..body_end]);
-    buf[body_end..body_end + CRC_SIZE].copy_from_slice(&crc.to_le_bytes());
+    copy_into(buf, start + ATOM_HDR_SIZE, data);
+    let crc = crc16(buf.split_at(body_end).0.split_at(start).1);
+    copy_into(buf, body_end, &crc.to_le_bytes());
     *offset = body_end + CRC_SIZE;
     *count += 1;
     Ok(())
@@ -1171,6 +1337,18 @@ pub fn atoms(data: &[u8]) -> AtomIter<'_> {
     }
 }
 
+impl<'a> AtomIter<'a> {
+    /// Pairs each atom with the offset of its 8-byte header in the image.
+    pub(crate) fn with_offsets(self) -> impl Iterator<Item = (usize, AtomRef<'a>)> {
+        let mut offset = self.offset;
+        self.map(move |atom| {
+            let at = offset;
+            offset += ATOM_HDR_SIZE + atom.data.len() + CRC_SIZE;
+            (at, atom)
+        })
+    }
+}
+
 impl<'a> Iterator for AtomIter<'a> {
     type Item = AtomRef<'a>;
 
@@ -1328,7 +1506,10 @@ pub fn read_from_eeprom_i2c(
 #[cfg(all(feature = "linux", any(target_os = "linux", target_os = "android")))]
 pub mod detect;
 #[cfg(all(feature = "linux", any(target_os = "linux", target_os = "android")))]
-pub use detect::{detect_all_i2c_devices, detect_and_show_eeprom_info, find_i2c_devices};
+pub use detect::{
+    detect_all_i2c_devices, detect_and_show_eeprom_info, find_i2c_devices, read_eeprom_image,
+    read_eeprom_image_checked,
+};
 
 impl VendorInfoAtom {
     /// Creates a `VendorInfoAtom` from strings.
@@ -1336,7 +1517,7 @@ impl VendorInfoAtom {
     /// The vendor and product strings are stored in 16-byte buffers (truncated
     /// if longer); on serialization only the used prefix is written, with the
     /// spec `vslen`/`pslen` length fields set accordingly.
-    pub fn new(
+    pub const fn new(
         product_id: u16,
         product_ver: u16,
         vendor: &str,
@@ -1347,10 +1528,18 @@ impl VendorInfoAtom {
         let mut product_arr = [0u8; 16];
         let vendor_bytes = vendor.as_bytes();
         let product_bytes = product.as_bytes();
-        let vendor_len = vendor_bytes.len().min(16);
-        let product_len = product_bytes.len().min(16);
-        vendor_arr[..vendor_len].copy_from_slice(&vendor_bytes[..vendor_len]);
-        product_arr[..product_len].copy_from_slice(&product_bytes[..product_len]);
+        let vendor_len = if vendor_bytes.len() < 16 {
+            vendor_bytes.len()
+        } else {
+            16
+        };
+        let product_len = if product_bytes.len() < 16 {
+            product_bytes.len()
+        } else {
+            16
+        };
+        copy_into(&mut vendor_arr, 0, vendor_bytes.split_at(vendor_len).0);
+        copy_into(&mut product_arr, 0, product_bytes.split_at(product_len).0);
         VendorInfoAtom {
             uuid,
             product_id,
@@ -1363,15 +1552,64 @@ impl VendorInfoAtom {
 
 impl core::fmt::Display for Eeprom {
     fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        #[cfg(feature = "alloc")]
+        return self.write_summary(f, Soc::Bcm2835, false, &custom::CodecRegistry::builtin());
+        #[cfg(not(feature = "alloc"))]
+        self.write_summary(f, Soc::Bcm2835, false)
+    }
+}
+
+impl Eeprom {
+    /// The human-readable summary behind `Display` (and `dump`).
+    fn write_summary(
+        &self,
+        f: &mut core::fmt::Formatter<'_>,
+        soc: Soc,
+        colour: bool,
+        #[cfg(feature = "alloc")] codecs: &custom::CodecRegistry,
+    ) -> core::fmt::Result {
         writeln!(f, "EEPROM Header:\n{}", self.header)?;
         writeln!(f, "\nVendor Info:\n{}", self.vendor_info)?;
-        writeln!(f, "\nGPIO Map Bank0:\n{}", self.gpio_map_bank0)?;
-        if let Some(dt_blob) = self.dt_blob_bytes() {
-            writeln!(f, "\nDT Blob: {} bytes", dt_blob.len())?;
+        let (flags, power) = (self.gpio_map_bank0.flags, self.gpio_map_bank0.power);
+        writeln!(
+            f,
+            "\nGPIO Map Bank0:\nflags: 0x{flags:02X}\npower: 0x{power:02X}"
+        )?;
+        match self.dt_atom() {
+            Some(DtAtom::Overlay { name, params }) => {
+                write!(f, "\nDT Overlay: {name}")?;
+                if !params.is_empty() {
+                    write!(f, " (params: {params})")?;
+                }
+                writeln!(f)?;
+            }
+            Some(DtAtom::Blob(dt_blob)) => {
+                write!(f, "\nDT Blob: {} bytes", dt_blob.len())?;
+                match fdt::Fdt::parse(dt_blob) {
+                    Ok(tree) => {
+                        write!(f, " (FDT v{}", tree.header().version)?;
+                        if let Some(compatible) = tree.compatible() {
+                            write!(f, ", compatible \"{compatible}\"")?;
+                        }
+                        writeln!(f, ")")?;
+                        for fragment in tree.fragments() {
+                            writeln!(f, "  {} -> {}", fragment.name, fragment.target)?;
+                        }
+                    }
+                    Err(e) => writeln!(f, " (invalid FDT: {e})")?,
+                }
+            }
+            None => {}
         }
         if let Some(ref bank1) = self.gpio_map_bank1 {
-            writeln!(f, "\nGPIO Map Bank1:\n{bank1}")?
+            let (flags, power) = (bank1.flags, bank1.power);
+            writeln!(
+                f,
+                "\nGPIO Map Bank1:\nflags: 0x{flags:02X}\npower: 0x{power:02X}"
+            )?;
         }
+        let pins = PinTable::from_eeprom(self).soc(soc).colour(colour);
+        write!(f, "\nGPIO Pins ({}):\n{pins}", soc.name())?;
         if let Some(current_ma) = self.power_supply {
             writeln!(f, "\nPower Supply: {current_ma} mA")?
         }
@@ -1379,14 +1617,27 @@ impl core::fmt::Display for Eeprom {
         if !self.custom_atoms.is_empty() {
             writeln!(f, "\nCustom Atoms:")?;
             for (i, data) in self.custom_atoms.iter().enumerate() {
-                writeln!(f, "  [{i}] {} bytes: {data:02X?}", data.len())?
+                match codecs.describe(data) {
+                    Some((codec, text)) => {
+                        writeln!(f, "  [{i}] {} bytes, {codec}: {text}", data.len())?
+                    }
+                    None => writeln!(f, "  [{i}] {} bytes: {data:02X?}", data.len())?,
+                }
             }
         }
         #[cfg(not(feature = "alloc"))]
         if !self.custom_atoms.is_empty() {
             writeln!(f, "\nCustom Atoms:")?;
             for (i, data) in self.custom_atoms.iter().enumerate() {
-                writeln!(f, "  [{i}] {} bytes: {data:02X?}", data.len())?
+                if tlv::validate(data).is_ok() {
+                    write!(f, "  [{i}] {} bytes, tlv tags:", data.len())?;
+                    for record in tlv::records(data).flatten() {
+                        write!(f, " 0x{:04X}", record.tag)?;
+                    }
+                    writeln!(f)?
+                } else {
+                    writeln!(f, "  [{i}] {} bytes: {data:02X?}", data.len())?
+                }
             }
         }
         Ok(())
@@ -0,0 +1,384 @@
+//  _  _       _             _  _
+// | || |  ___| |_ _ __ ___ | || |
+// | || |_/ __| __| '_ ` _ \| || |_
+// |__   _\__ | |_| | | | | |__   _|
+//   |_| |___/\__|_|_|_| |_|  |_|
+//! # ehatrom — EEPROM HAT library for Raspberry Pi HATs
+//! - [Documentation (docs.rs)](https://docs.rs/ehatrom)
+//! - [GitHub](https://github.com/4stm4/ehatrom)
+//!
+//! ## HAT specification lint
+//!
+//! [`Eeprom::validate`](crate::Eeprom::validate) checks structure and CRCs;
+//! [`lint`] checks what the HAT specification says the contents must mean.
+//! Each [`Finding`] names the [`Rule`] it breaks, by a stable kebab-case ID,
+//! and carries the rule's [`Severity`]. [`RULES`] lists them all:
+//!
+//! | ID | severity | rule |
+//! |----|----------|------|
+//! | `structure` | error | the image passes `validate` (header, atoms, CRC-16s) |
+//! | `atom-order` | error | vendor info is the first atom and the bank0 GPIO map the second |
+//! | `id-pins` | error | GPIO 0/1 (ID_SD/ID_SC, the EEPROM bus) are not claimed |
+//! | `bank1-without-bank0` | error | a bank1 GPIO map only appears with a bank0 one |
+//! | `vendor-empty` | error | the vendor string is not empty |
+//! | `product-empty` | error | the product string is not empty |
+//! | `uuid-zero` | error | the UUID is not all zero |
+//! | `back-power` | warning | `back_power` and `current_supply` agree |
+//! | `alt-function` | error | a pin's ALT function is not reserved on a target SoC |
+//!
+//! ALT functions are checked against the [`Soc`] tables. [`lint`] targets
+//! every SoC in [`Soc::ALL`], since a HAT may be fitted to any Pi; use
+//! [`lint_for`] to target some. GPIOs past a table's end (bank1 on RP1) are
+//! not checked against it.
+
+use crate::diff::atom_name;
+use crate::soc::alt_number;
+use crate::view::{EepromView, GpioView};
+use crate::{AtomType, Eeprom, Soc, atoms};
+use alloc::{format, string::String, vec::Vec};
+use core::fmt;
+
+/// How serious a [`Finding`] is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize),
+    serde(rename_all = "lowercase")
+)]
+pub enum Severity {
+    /// The image breaks the specification.
+    Error,
+    /// The image is legal but probably not what was meant.
+    Warning,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        })
+    }
+}
+
+/// A lint rule.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rule {
+    /// Stable kebab-case ID.
+    pub id: &'static str,
+    pub severity: Severity,
+    /// One-line description.
+    pub summary: &'static str,
+}
+
+const fn rule(id: &'static str, severity: Severity, summary: &'static str) -> Rule {
+    Rule {
+        id,
+        severity,
+        summary,
+    }
+}
+
+pub const STRUCTURE: Rule = rule(
+    "structure",
+    Severity::Error,
+    "the image passes validate (header, atoms, CRC-16s)",
+);
+pub const ATOM_ORDER: Rule = rule(
+    "atom-order",
+    Severity::Error,
+    "vendor info is the first atom and the bank0 GPIO map the second",
+);
+pub const ID_PINS: Rule = rule(
+    "id-pins",
+    Severity::Error,
+    "GPIO 0/1 (ID_SD/ID_SC) are not claimed",
+);
+pub const BANK1_WITHOUT_BANK0: Rule = rule(
+    "bank1-without-bank0",
+    Severity::Error,
+    "a bank1 GPIO map only appears with a bank0 one",
+);
+pub const VENDOR_EMPTY: Rule = rule(
+    "vendor-empty",
+    Severity::Error,
+    "the vendor string is not empty",
+);
+pub const PRODUCT_EMPTY: Rule = rule(
+    "product-empty",
+    Severity::Error,
+    "the product string is not empty",
+);
+pub const UUID_ZERO: Rule = rule("uuid-zero", Severity::Error, "the UUID is not all zero");
+pub const BACK_POWER: Rule = rule(
+    "back-power",
+    Severity::Warning,
+    "back_power and current_supply agree",
+);
+pub const ALT_FUNCTION: Rule = rule(
+    "alt-function",
+    Severity::Error,
+    "a pin's ALT function is not reserved on a target SoC",
+);
+
+/// Every rule [`lint`] applies, in the order it applies them.
+pub const RULES: &[Rule] = &[
+    STRUCTURE,
+    ATOM_ORDER,
+    ID_PINS,
+    BANK1_WITHOUT_BANK0,
+    VENDOR_EMPTY,
+    PRODUCT_EMPTY,
+    UUID_ZERO,
+    BACK_POWER,
+    ALT_FUNCTION,
+];
+
+/// One rule violation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Finding {
+    /// The rule ID.
+    pub rule: &'static str,
+    pub severity: Severity,
+    pub message: String,
+}
+
+impl Finding {
+    fn new(rule: Rule, message: String) -> Self {
+        Finding {
+            rule: rule.id,
+            severity: rule.severity,
+            message,
+        }
+    }
+}
+
+impl fmt::Display for Finding {
+    /// `error[id-pins]: GPIO 0 (ID_SD) is claimed as INPUT`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}[{}]: {}", self.severity, self.rule, self.message)
+    }
+}
+
+/// Checks `data` against every rule in [`RULES`], for every SoC.
+///
+/// Findings come in rule order. An image that does not pass `validate` gets a
+/// `structure` finding and is still checked as far as it can be read; the
+/// only hard error is a missing or unreadable header.
+pub fn lint(data: &[u8]) -> Result<Vec<Finding>, &'static str> {
+    lint_for(data, &Soc::ALL)
+}
+
+/// Like [`lint`], but checks ALT functions only against `socs`.
+pub fn lint_for(data: &[u8], socs: &[Soc]) -> Result<Vec<Finding>, &'static str> {
+    let view = EepromView::new(data)?;
+    let mut findings = Vec::new();
+    if let Err(e) = Eeprom::validate(data) {
+        findings.push(Finding::new(STRUCTURE, format!("{e}")));
+    }
+
+    let order: Vec<u16> = atoms(data).take(2).map(|atom| atom.atom_type).collect();
+    for (position, expected) in [(0, AtomType::VendorInfo), (1, AtomType::GpioMapBank0)] {
+        let name = atom_name(expected as u16);
+        let message = match order.get(position) {
+            Some(&found) if AtomType::from(found) == expected => continue,
+            Some(&found) => format!("atom {position} is {}, expected {name}", atom_name(found)),
+            None => format!("atom {position} ({name}) is missing"),
+        };
+        findings.push(Finding::new(ATOM_ORDER, message));
+    }
+
+    let bank0 = view.gpio_bank0();
+    if let Some(bank0) = bank0 {
+        for (gpio, name) in [(0, "ID_SD"), (1, "ID_SC")] {
+            if let Some(pin) = bank0.pin(gpio).filter(|pin| pin.used) {
+                findings.push(Finding::new(
+                    ID_PINS,
+                    format!("GPIO {gpio} ({name}) is claimed as {}", pin.func.keyword()),
+                ));
+            }
+        }
+    }
+    let bank1 = view.gpio_bank1();
+    if bank1.is_some() && bank0.is_none() {
+        findings.push(Finding::new(
+            BANK1_WITHOUT_BANK0,
+            "gpio_bank1 is present without gpio_bank0".into(),
+        ));
+    }
+
+    if let Some(vendor) = view.vendor_info() {
+        if vendor.vendor_bytes().is_empty() {
+            findings.push(Finding::new(VENDOR_EMPTY, "vendor string is empty".into()));
+        }
+        if vendor.product_bytes().is_empty() {
+            findings.push(Finding::new(
+                PRODUCT_EMPTY,
+                "product string is empty".into(),
+            ));
+        }
+        if vendor.uuid().iter().all(|&b| b == 0) {
+            findings.push(Finding::new(UUID_ZERO, "UUID is all zero".into()));
+        }
+    }
+
+    lint_back_power(bank0, view.power_supply(), &mut findings);
+
+    let banks = [(bank0, 0), (bank1, crate::GPIO_COUNT)];
+    for (bank, first_gpio) in banks {
+        let Some(bank) = bank else { continue };
+        for (index, &byte) in bank.pins().iter().enumerate() {
+            let gpio = first_gpio + index;
+            let pin = crate::decode_pin(byte);
+            let Some(alt) = alt_number(pin.func).filter(|_| pin.used) else {
+                continue;
+            };
+            let reserved: Vec<&str> = socs
+                .iter()
+                .filter(|soc| gpio < soc.gpio_count() && soc.alt_function(gpio, pin.func).is_none())
+                .map(|soc| soc.name())
+                .collect();
+            if !reserved.is_empty() {
+                findings.push(Finding::new(
+                    ALT_FUNCTION,
+                    format!(
+                        "GPIO {gpio} uses ALT{alt}, which is reserved on {}",
+                        reserved.join(", ")
+                    ),
+                ));
+            }
+        }
+    }
+    Ok(findings)
+}
+
+/// `back_power` 1 and 2 promise at least 1.3 A and 2 A to the Pi; a HAT+
+/// `current_supply` must not claim less, and is meaningless without
+/// back-powering. Classic images have no power-supply atom and are only
+/// checked for a reserved `back_power`.
+fn lint_back_power(bank0: Option<GpioView>, current: Option<u32>, findings: &mut Vec<Finding>) {
+    let back_power = bank0.map_or(0, |bank| bank.power());
+    let limit = match back_power {
+        0 => None,
+        1 => Some(1300),
+        2 => Some(2000),
+        other => {
+            findings.push(Finding::new(
+                BACK_POWER,
+                format!("back_power {other} is reserved"),
+            ));
+            return;
+        }
+    };
+    match (limit, current) {
+        (None, Some(ma)) if ma > 0 => findings.push(Finding::new(
+            BACK_POWER,
+            format!("current_supply is {ma} mA but back_power is 0"),
+        )),
+        (Some(limit), Some(ma)) if ma < limit => findings.push(Finding::new(
+            BACK_POWER,
+            format!(
+                "current_supply is {ma} mA but back_power {back_power} promises at least {limit} mA"
+            ),
+        )),
+        _ => {}
+    }
+}
+
+/// Whether any finding is an error.
+pub fn has_errors(findings: &[Finding]) -> bool {
+    findings.iter().any(|f| f.severity == Severity::Error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_settings;
+    use alloc::string::ToString;
+
+    fn rules(settings: &str) -> Vec<&'static str> {
+        let image = parse_settings(settings).unwrap().serialize();
+        lint(&image).unwrap().iter().map(|f| f.rule).collect()
+    }
+
+    const CLEAN: &str = "product_uuid 6a2f3c4e-0000-4000-8000-00000000beef\n\
+                         vendor \"ACME\"\nproduct \"Sensor HAT\"\n";
+
+    #[test]
+    fn clean_image_has_no_findings() {
+        assert_eq!(rules(CLEAN), Vec::<&str>::new());
+        let pins = "setgpio 4 ALT0 DEFAULT\nsetgpio 18 ALT5 DEFAULT\nsetgpio 17 OUTPUT UP\n";
+        assert_eq!(rules(&(CLEAN.to_string() + pins)), Vec::<&str>::new());
+    }
+
+    #[test]
+    fn each_rule_fires() {
+        assert_eq!(
+            rules("product_uuid 00000000-0000-0000-0000-000000000000\n"),
+            ["vendor-empty", "product-empty", "uuid-zero"]
+        );
+        let id_pins = CLEAN.to_string() + "setgpio 0 INPUT DEFAULT\nsetgpio 1 ALT0 DEFAULT\n";
+        assert_eq!(rules(&id_pins), ["id-pins", "id-pins"]);
+        let alt = CLEAN.to_string() + "setgpio 26 ALT0 DEFAULT\nsetgpio 16 ALT3 DEFAULT\n";
+        assert_eq!(rules(&alt), ["alt-function", "alt-function"]);
+        let image = parse_settings(&alt).unwrap().serialize();
+        assert!(lint_for(&image, &[Soc::Bcm2711]).unwrap().is_empty());
+        let findings = lint_for(&image, &[Soc::Rp1]).unwrap();
+        assert_eq!(findings.len(), 1);
+        assert_eq!(
+            findings[0].message,
+            "GPIO 16 uses ALT3, which is reserved on RP1"
+        );
+        assert_eq!(
+            rules(&(CLEAN.to_string() + "current_supply 1500\n")),
+            ["back-power"]
+        );
+        assert_eq!(
+            rules(&(CLEAN.to_string() + "back_power 1\ncurrent_supply 1500\n")),
+            Vec::<&str>::new()
+        );
+        assert_eq!(
+            rules(&(CLEAN.to_string() + "back_power 2\ncurrent_supply 1500\n")),
+            ["back-power"]
+        );
+        // A classic image carries no power-supply atom.
+        assert_eq!(
+            rules(&(CLEAN.to_string() + "back_power 2\n")),
+            Vec::<&str>::new()
+        );
+
+        // Vendor info after the GPIO map, and a corrupted CRC.
+        let mut image = parse_settings(CLEAN).unwrap().serialize();
+        let vendor_len =
+            crate::ATOM_HDR_SIZE + u32::from_le_bytes(image[16..20].try_into().unwrap()) as usize;
+        let (header, rest) = image.split_at(crate::HEADER_SIZE);
+        let (vendor, gpio) = rest.split_at(vendor_len);
+        let mut swapped = [header, gpio, vendor].concat();
+        *swapped.last_mut().unwrap() ^= 1;
+        let findings = lint(&swapped).unwrap();
+        assert!(findings.iter().any(|f| f.rule == "structure"));
+        assert_eq!(
+            findings.iter().filter(|f| f.rule == "atom-order").count(),
+            2
+        );
+        assert_eq!(
+            findings
+                .iter()
+                .find(|f| f.rule == "atom-order")
+                .unwrap()
+                .to_string(),
+            "error[atom-order]: atom 0 is gpio_bank0, expected vendor_info"
+        );
+        image.truncate(crate::HEADER_SIZE);
+        image[6] = 0;
+        assert_eq!(
+            lint(&image)
+                .unwrap()
+                .iter()
+                .filter(|f| f.rule == "atom-order")
+                .count(),
+            2
+        );
+    }
+}
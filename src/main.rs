@@ -18,20 +18,62 @@ fn main() {
 
     let args: Vec<String> = env::args().collect();
     if args.len() < 2 {
-        eprintln!("Usage: ehatrom <read|write|make|show|dump|verify|detect> [options]");
+        eprintln!(
+            "Usage: ehatrom <read|write|make|show|dump|extract|export|verify|lint|hash|sign|auth|diff|conflicts|edit|repair|salvage|detect> [options]"
+        );
         eprintln!("Commands:");
         eprintln!(
             "  read [i2c-dev] <output.bin>             Read HAT EEPROM via I2C and save to file"
         );
+        eprintln!(
+            "  read --auth <se-addr> [i2c-dev] <out>   Read only if the secure element answers"
+        );
         eprintln!(
             "  write [i2c-dev] <input.bin>             Write HAT EEPROM from file to I2C device"
         );
         eprintln!(
             "  make <settings.txt> <output.bin>        Build a HAT EEPROM image from settings.txt"
         );
+        eprintln!("  make --from-json <in.json> <out.bin>    Build a HAT EEPROM image from JSON");
+        eprintln!("  make --dts <overlay.dts> <in> <out.bin> Compile an overlay into the DT atom");
+        eprintln!("  make --gpio-from <pinctrl.txt> [<in>] <out.bin> GPIO maps from pinctrl get");
         eprintln!("  show <input.bin>                        Show parsed EEPROM info (debug)");
         eprintln!("  dump <input.bin>                        Show parsed EEPROM info (readable)");
+        eprintln!("  dump --json <input.bin>                 Export parsed EEPROM as JSON");
+        eprintln!("  dump --dts <input.bin>                  Dump plus the DT blob as DTS source");
+        eprintln!(
+            "  dump --header <input.bin>               Dump plus ASCII art of the 40-pin header"
+        );
+        eprintln!("  dump --soc <bcm2835|bcm2711|rp1> <in>   Name ALT pin functions for that SoC");
+        eprintln!("  extract <input.bin> <out-dir>           Write each atom and a settings file");
+        eprintln!("  extract dt <input.bin> [out.dts]        Decompile the DT blob to DTS source");
+        eprintln!("  export --config-txt <in> [out]          GPIO map as config.txt gpio= lines");
+        eprintln!("  export --pinctrl-dts <in> [out.dts]     GPIO map as a pinctrl overlay source");
         eprintln!("  verify <input.bin>                      Check every per-atom CRC-16");
+        eprintln!("  verify --pubkey <key> <input.bin>       Also check the Ed25519 signature");
+        eprintln!(
+            "  lint [--json] <input.bin>               Check HAT spec rules (ID pins, ALTs…)"
+        );
+        eprintln!("  lint --soc <soc> <input.bin>            Check ALT functions for one SoC only");
+        eprintln!("  lint --rules                            List the lint rules");
+        eprintln!(
+            "  hash <input.bin>                        CRC-32 and SHA-256 of the eeplen bytes"
+        );
+        eprintln!("  hash --embed <in> <out.bin>             Append the digest as a custom atom");
+        eprintln!("  sign --key <secret.key> <in> <out.bin>  Append an Ed25519 signature atom");
+        eprintln!("  sign --new-key <secret.key> <public.key> Generate an Ed25519 key pair");
+        eprintln!("  auth --add-key <public.key> <in> <out>  Embed a secure-element public key");
+        eprintln!("  auth <in|i2c-dev> <se-addr> [se-i2c-dev] Challenge the HAT's secure element");
+        eprintln!("  auth --simulate <secret.key> <in>       Answer the challenge in software");
+        eprintln!("  diff [--raw] <a> <b>                    Compare two images (file or i2c-dev)");
+        eprintln!("  conflicts [--config <config.txt>] <a> <b>... Pins claimed by several boards");
+        eprintln!("  edit <in> <out> <path=value>...         Change fields, recompute CRCs/header");
+        eprintln!(
+            "  repair <in> <out>                       Fix CRCs, counts, numatoms and eeplen"
+        );
+        eprintln!(
+            "  salvage <in> <out>                      Rebuild a corrupted image from CRC-valid atoms"
+        );
         eprintln!(
             "  detect [i2c-dev]                        Auto-detect HAT EEPROM on specific device"
         );
@@ -43,7 +85,14 @@ fn main() {
         eprintln!("Examples:");
         eprintln!("  ehatrom make settings.txt hat.bin       # Build image from settings.txt");
         eprintln!("  ehatrom dump hat.bin                    # Human-readable dump + CRC check");
+        eprintln!("  ehatrom dump --json hat.bin > hat.json  # Export for other tooling");
         eprintln!("  ehatrom verify hat.bin                  # Exit non-zero on CRC mismatch");
+        eprintln!("  ehatrom lint hat.bin                    # Exit non-zero on spec errors");
+        eprintln!("  ehatrom hash /dev/i2c-0                 # Fingerprint a programmed unit");
+        eprintln!("  ehatrom sign --key hat.key hat.bin signed.bin");
+        eprintln!("  ehatrom verify --pubkey hat.pub signed.bin");
+        eprintln!("  sudo ehatrom diff golden.bin /dev/i2c-0 # Field return vs golden image");
+        eprintln!("  ehatrom edit hat.bin new.bin vendor_info.product_ver=0x0003");
         eprintln!("  sudo ehatrom read hat_data.bin          # Read from /dev/i2c-0 to file");
         eprintln!("  sudo ehatrom write hat_data.bin         # Write from file to /dev/i2c-0");
         eprintln!("  sudo ehatrom read /dev/i2c-1 hat.bin    # Read from specific I2C device");
@@ -55,11 +104,19 @@ fn main() {
     }
     match args[1].as_str() {
         "read" => {
-            // ehatrom read [i2c-dev] <output.bin>
+            // ehatrom read [--auth <se-addr>] [i2c-dev] <output.bin>
+            let mut args = args.clone();
+            #[cfg_attr(not(feature = "linux"), allow(unused_variables))]
+            let auth = args.iter().position(|a| a == "--auth").map(|pos| {
+                let addr = args.get(pos + 1).cloned().unwrap_or_default();
+                args.drain(pos..(pos + 2).min(args.len()));
+                addr
+            });
             if args.len() < 3 || args.len() > 4 {
-                eprintln!("Usage: ehatrom read [i2c-dev] <output.bin>");
+                eprintln!("Usage: ehatrom read [--auth <se-addr>] [i2c-dev] <output.bin>");
                 eprintln!("  Default I2C device: /dev/i2c-0");
                 eprintln!("  HAT EEPROM address: 0x50 (automatic)");
+                eprintln!("  --auth: authenticate the unit against its secure element first");
                 process::exit(1);
             }
             #[cfg(all(target_os = "linux", feature = "linux"))]
@@ -71,6 +128,47 @@ fn main() {
                     // ehatrom read <i2c-dev> <output.bin>
                     (args[2].as_str(), &args[3])
                 };
+                if let Some(se_addr) = auth {
+                    #[cfg(all(feature = "sign", feature = "alloc"))]
+                    {
+                        use ehatrom::auth::{I2cSecureElement, read_authenticated};
+                        let se_addr = parse_i2c_addr(&se_addr).unwrap_or_else(|e| {
+                            eprintln!("{e}");
+                            process::exit(1);
+                        });
+                        let mut element = match I2cSecureElement::new(dev, se_addr) {
+                            Ok(element) => element,
+                            Err(e) => {
+                                eprintln!("secure element on {dev}: {e}");
+                                process::exit(1);
+                            }
+                        };
+                        match read_authenticated(dev, &mut element, &random_bytes()) {
+                            Ok(image) => {
+                                if let Err(e) = std::fs::write(output_file, &image) {
+                                    eprintln!("Failed to write output: {e}");
+                                    process::exit(1);
+                                }
+                                println!(
+                                    "HAT EEPROM read from {dev} (0x50), authenticated and saved to {output_file} ({} bytes)",
+                                    image.len()
+                                );
+                            }
+                            Err(e) => {
+                                eprintln!("FAIL: {e}");
+                                process::exit(1);
+                            }
+                        }
+                        return;
+                    }
+                    #[cfg(not(all(feature = "sign", feature = "alloc")))]
+                    {
+                        let _ = se_addr;
+                        eprintln!("read --auth requires --features=sign");
+                        eprintln!("Please rebuild with: cargo build --features linux,sign");
+                        process::exit(1);
+                    }
+                }
                 let addr = 0x50u16; // HAT EEPROM fixed address
 
                 // Support reading large EEPROMs - default buffer is 32 KB
@@ -196,21 +294,58 @@ fn main() {
             }
         }
         "dump" => {
-            // ehatrom dump <input.bin>
-            if args.len() != 3 {
-                eprintln!("Usage: ehatrom dump <input.bin>");
+            // ehatrom dump [--json|--dts] [--soc <name>] [--header] <input.bin>
+            let mut args = args.clone();
+            #[cfg_attr(not(feature = "alloc"), allow(unused_variables))]
+            let header = args
+                .iter()
+                .position(|a| a == "--header")
+                .map(|pos| args.remove(pos));
+            #[cfg_attr(not(feature = "alloc"), allow(unused_variables))]
+            let soc = take_soc(&mut args).unwrap_or_else(|e| {
+                eprintln!("{e}");
+                process::exit(1);
+            });
+            let json = args.len() == 4 && args[2] == "--json";
+            let dts = args.len() == 4 && args[2] == "--dts";
+            if args.len() != 3 && !json && !dts {
+                eprintln!(
+                    "Usage: ehatrom dump [--json|--dts] [--soc <name>] [--header] <input.bin>"
+                );
                 process::exit(1);
             }
             #[cfg_attr(not(feature = "alloc"), allow(unused_variables))]
-            let data = match std::fs::read(&args[2]) {
+            let data = match std::fs::read(&args[args.len() - 1]) {
                 Ok(d) => d,
                 Err(e) => {
                     eprintln!("Failed to read input: {e}");
                     process::exit(1);
                 }
             };
+            if json {
+                #[cfg(feature = "json")]
+                match Eeprom::from_bytes(&data) {
+                    Ok(eeprom) => match serde_json::to_string_pretty(&eeprom) {
+                        Ok(text) => println!("{text}"),
+                        Err(e) => {
+                            eprintln!("JSON error: {e}");
+                            process::exit(1);
+                        }
+                    },
+                    Err(e) => {
+                        eprintln!("Parse error: {e}");
+                        process::exit(1);
+                    }
+                }
+                #[cfg(not(feature = "json"))]
+                {
+                    eprintln!("JSON output requires --features=json");
+                    eprintln!("Please rebuild with: cargo build --features json");
+                    process::exit(1);
+                }
+            }
             #[cfg(feature = "alloc")]
-            {
+            if !json {
                 use ehatrom::AtomType;
 
                 // Per-atom overview, walked zero-copy straight from the bytes.
@@ -243,11 +378,41 @@ fn main() {
 
                 match Eeprom::from_bytes(&data) {
                     Ok(eeprom) => {
-                        print!("{eeprom}");
+                        let codecs = ehatrom::custom::CodecRegistry::builtin();
+                        let soc = soc.unwrap_or(ehatrom::Soc::Bcm2835);
+                        use std::io::IsTerminal;
+
+                        // Colour only on a terminal, and never with NO_COLOR set.
+                        let colour = std::io::stdout().is_terminal()
+                            && std::env::var_os("NO_COLOR").is_none_or(|v| v.is_empty());
+                        print!("{}", eeprom.display_with(&codecs).soc(soc).colour(colour));
+                        if header.is_some() {
+                            let table = ehatrom::PinTable::from_eeprom(&eeprom);
+                            print!(
+                                "\n40-pin header:\n{}",
+                                table.soc(soc).colour(colour).header()
+                            );
+                        }
                         match Eeprom::validate(&data) {
                             Ok(()) => println!("\nCRC-16: all atoms valid"),
                             Err(e) => println!("\nCRC-16: {e}"),
                         }
+                        if dts {
+                            match eeprom.dt_atom() {
+                                Some(ehatrom::DtAtom::Blob(blob)) => {
+                                    match ehatrom::dts::decompile_dtb(blob) {
+                                        Ok(source) => print!("\nDT Blob source:\n{source}"),
+                                        Err(e) => {
+                                            println!("\nDT Blob source: not a valid FDT ({e})")
+                                        }
+                                    }
+                                }
+                                Some(ehatrom::DtAtom::Overlay { .. }) => {
+                                    println!("\nDT Blob source: the DT atom names an overlay")
+                                }
+                                None => println!("\nDT Blob source: image has no DT blob"),
+                            }
+                        }
                     }
                     Err(e) => {
                         eprintln!("Parse error: {e}");
@@ -261,14 +426,199 @@ fn main() {
                 process::exit(1);
             }
         }
+        "extract" => {
+            // ehatrom extract <input.bin> <out-dir>
+            // ehatrom extract dt <input.bin> [output.dts]
+            let dt = args.len() >= 4 && args[2] == "dt";
+            let arity_ok = if dt { args.len() <= 5 } else { args.len() == 4 };
+            if !arity_ok {
+                eprintln!("Usage: ehatrom extract <input.bin> <out-dir>");
+                eprintln!("       ehatrom extract dt <input.bin> [output.dts]");
+                process::exit(1);
+            }
+            #[cfg(feature = "alloc")]
+            if !dt {
+                let (input, dir) = (&args[2], std::path::Path::new(&args[3]));
+                let data = match std::fs::read(input) {
+                    Ok(d) => d,
+                    Err(e) => {
+                        eprintln!("Failed to read input: {e}");
+                        process::exit(1);
+                    }
+                };
+                let extraction = match ehatrom::extract::extract(&data) {
+                    Ok(x) => x,
+                    Err(e) => {
+                        eprintln!("Parse error: {e}");
+                        process::exit(1);
+                    }
+                };
+                if let Err(e) = std::fs::create_dir_all(dir) {
+                    eprintln!("Failed to create {}: {e}", dir.display());
+                    process::exit(1);
+                }
+                let settings = (
+                    ehatrom::extract::SETTINGS_FILE,
+                    extraction.settings.as_bytes(),
+                );
+                let files = extraction
+                    .files
+                    .iter()
+                    .map(|f| (f.name.as_str(), &f.data[..]));
+                for (name, contents) in files.chain([settings]) {
+                    let path = dir.join(name);
+                    if let Err(e) = std::fs::write(&path, contents) {
+                        eprintln!("Failed to write {}: {e}", path.display());
+                        process::exit(1);
+                    }
+                    println!("  {name} ({} bytes)", contents.len());
+                }
+                // Anything past eeplen (e.g. erased EEPROM) is not part of the image.
+                let eeplen = u32::from_le_bytes([data[8], data[9], data[10], data[11]]) as usize;
+                let image = data.get(..eeplen).unwrap_or(&data);
+                match extraction.rebuild() {
+                    Ok(rebuilt) if rebuilt == image => println!(
+                        "Wrote {} atoms to {}; `ehatrom make {} <out.bin>` rebuilds the identical image",
+                        extraction.files.len(),
+                        dir.display(),
+                        dir.join(ehatrom::extract::SETTINGS_FILE).display()
+                    ),
+                    _ => println!(
+                        "Wrote {} atoms to {}; note: the settings file does not rebuild a \
+                         byte-identical image (see its comments)",
+                        extraction.files.len(),
+                        dir.display()
+                    ),
+                }
+                return;
+            }
+            #[cfg(feature = "alloc")]
+            {
+                let data = match std::fs::read(&args[3]) {
+                    Ok(d) => d,
+                    Err(e) => {
+                        eprintln!("Failed to read input: {e}");
+                        process::exit(1);
+                    }
+                };
+                let eeprom = match Eeprom::from_bytes(&data) {
+                    Ok(eeprom) => eeprom,
+                    Err(e) => {
+                        eprintln!("Parse error: {e}");
+                        process::exit(1);
+                    }
+                };
+                let blob = match eeprom.dt_atom() {
+                    Some(ehatrom::DtAtom::Blob(blob)) => blob,
+                    Some(ehatrom::DtAtom::Overlay { name, .. }) => {
+                        eprintln!(
+                            "{} has no DT blob: its DT atom names overlay {name}",
+                            args[3]
+                        );
+                        process::exit(1);
+                    }
+                    None => {
+                        eprintln!("{} has no DT blob atom", args[3]);
+                        process::exit(1);
+                    }
+                };
+                let source = match ehatrom::dts::decompile_dtb(blob) {
+                    Ok(s) => s,
+                    Err(e) => {
+                        eprintln!("DT blob is not a valid FDT: {e}");
+                        process::exit(1);
+                    }
+                };
+                match args.get(4) {
+                    Some(output) => {
+                        if let Err(e) = std::fs::write(output, &source) {
+                            eprintln!("Failed to write output: {e}");
+                            process::exit(1);
+                        }
+                        println!("Wrote {output} ({} bytes of DT blob)", blob.len());
+                    }
+                    None => print!("{source}"),
+                }
+            }
+            #[cfg(not(feature = "alloc"))]
+            {
+                eprintln!("The 'extract' command requires the 'alloc' feature");
+                process::exit(1);
+            }
+        }
+        "export" => {
+            // ehatrom export --config-txt [--drive-low|--drive-high] <in.bin|i2c-dev> [out]
+            // ehatrom export --pinctrl-dts <in.bin|i2c-dev> [out.dts]
+            let mut args = args.clone();
+            let level = match args.get(3).map(String::as_str) {
+                Some(flag @ ("--drive-low" | "--drive-high")) => {
+                    let high = flag == "--drive-high";
+                    args.remove(3);
+                    Some(high)
+                }
+                _ => None,
+            };
+            let format = args.get(2).map(String::as_str);
+            let format_ok = match format {
+                Some("--config-txt") => true,
+                Some("--pinctrl-dts") => level.is_none(),
+                _ => false,
+            };
+            if !format_ok || !(4..=5).contains(&args.len()) {
+                eprintln!(
+                    "Usage: ehatrom export --config-txt [--drive-low|--drive-high] <in.bin|i2c-dev> [out]"
+                );
+                eprintln!("       ehatrom export --pinctrl-dts <in.bin|i2c-dev> [out.dts]");
+                process::exit(1);
+            }
+            #[cfg(feature = "alloc")]
+            {
+                use ehatrom::export::{OutputLevel, config_txt, pinctrl_dts};
+                let data = read_image(&args[3]).unwrap_or_else(|e| {
+                    eprintln!("{e}");
+                    process::exit(1);
+                });
+                let text = if format == Some("--config-txt") {
+                    let level = match level {
+                        None => OutputLevel::Keep,
+                        Some(false) => OutputLevel::Low,
+                        Some(true) => OutputLevel::High,
+                    };
+                    config_txt(&data, level)
+                } else {
+                    pinctrl_dts(&data)
+                };
+                let text = text.unwrap_or_else(|e| {
+                    eprintln!("Parse error: {e}");
+                    process::exit(1);
+                });
+                match args.get(4) {
+                    Some(output) => {
+                        if let Err(e) = std::fs::write(output, &text) {
+                            eprintln!("Failed to write output: {e}");
+                            process::exit(1);
+                        }
+                        println!("Wrote {output}");
+                    }
+                    None => print!("{text}"),
+                }
+            }
+            #[cfg(not(feature = "alloc"))]
+            {
+                let _ = level;
+                eprintln!("The 'export' command requires the 'alloc' feature");
+                process::exit(1);
+            }
+        }
         "verify" => {
-            // ehatrom verify <input.bin>
-            if args.len() != 3 {
-                eprintln!("Usage: ehatrom verify <input.bin>");
+            // ehatrom verify [--pubkey <public.key>] <input.bin>
+            let pubkey = args.len() == 5 && args[2] == "--pubkey";
+            if args.len() != 3 && !pubkey {
+                eprintln!("Usage: ehatrom verify [--pubkey <public.key>] <input.bin>");
                 process::exit(1);
             }
             #[cfg_attr(not(feature = "alloc"), allow(unused_variables))]
-            let data = match std::fs::read(&args[2]) {
+            let data = match std::fs::read(&args[args.len() - 1]) {
                 Ok(d) => d,
                 Err(e) => {
                     eprintln!("Failed to read input: {e}");
@@ -284,45 +634,746 @@ fn main() {
                     process::exit(1);
                 }
             }
+            use ehatrom::digest::{DigestCheck, check_embedded};
+            match check_embedded(&data) {
+                DigestCheck::Absent => {}
+                DigestCheck::Valid(digest) => {
+                    println!("OK: embedded image digest matches ({digest})");
+                }
+                DigestCheck::Mismatch { stored, computed } => {
+                    eprintln!("FAIL: embedded image digest does not match the image");
+                    eprintln!("  stored:   {stored}");
+                    eprintln!("  computed: {computed}");
+                    process::exit(1);
+                }
+            }
+            if pubkey {
+                #[cfg(feature = "sign")]
+                {
+                    use ehatrom::sign::{VerifyingKey, verify_image};
+                    let key = match VerifyingKey::from_bytes(&load_key(&args[3])) {
+                        Ok(key) => key,
+                        Err(_) => {
+                            eprintln!("{}: not an Ed25519 public key", args[3]);
+                            process::exit(1);
+                        }
+                    };
+                    match verify_image(&data, &key) {
+                        Ok(()) => println!("OK: Ed25519 signature matches {}", args[3]),
+                        Err(e) => {
+                            eprintln!("FAIL: {e}");
+                            process::exit(1);
+                        }
+                    }
+                }
+                #[cfg(not(feature = "sign"))]
+                {
+                    eprintln!("Signature checks require --features=sign");
+                    eprintln!("Please rebuild with: cargo build --features sign");
+                    process::exit(1);
+                }
+            }
         }
-        "make" => {
-            // ehatrom make <settings.txt> <output.bin>
-            if args.len() != 4 {
-                eprintln!("Usage: ehatrom make <settings.txt> <output.bin>");
+        "lint" => {
+            // ehatrom lint [--json] [--soc <name>] <in.bin|i2c-dev>
+            // ehatrom lint --rules
+            let mut args = args.clone();
+            #[cfg_attr(not(feature = "alloc"), allow(unused_variables))]
+            let soc = take_soc(&mut args).unwrap_or_else(|e| {
+                eprintln!("{e}");
+                process::exit(2);
+            });
+            let json = args.len() == 4 && args[2] == "--json";
+            let list = args.len() == 3 && args[2] == "--rules";
+            if args.len() != 3 && !json {
+                eprintln!("Usage: ehatrom lint [--json] [--soc <name>] <in.bin|i2c-dev>");
+                eprintln!("       ehatrom lint --rules");
+                eprintln!(
+                    "  Exit status: 0 if no errors (warnings allowed), 1 on errors, 2 on failure"
+                );
+                process::exit(2);
+            }
+            #[cfg(feature = "alloc")]
+            {
+                use ehatrom::lint::{RULES, has_errors, lint_for};
+                if list {
+                    for rule in RULES {
+                        println!("{:<20} {:<8} {}", rule.id, rule.severity, rule.summary);
+                    }
+                    return;
+                }
+                let input = &args[args.len() - 1];
+                let targets = match &soc {
+                    Some(soc) => core::slice::from_ref(soc),
+                    None => &ehatrom::Soc::ALL[..],
+                };
+                let findings = match read_image(input).and_then(|data| {
+                    lint_for(&data, targets).map_err(|e| format!("Cannot lint {input}: {e}"))
+                }) {
+                    Ok(findings) => findings,
+                    Err(e) => {
+                        eprintln!("{e}");
+                        process::exit(2);
+                    }
+                };
+                if json {
+                    #[cfg(feature = "json")]
+                    match serde_json::to_string_pretty(&findings) {
+                        Ok(text) => println!("{text}"),
+                        Err(e) => {
+                            eprintln!("JSON error: {e}");
+                            process::exit(2);
+                        }
+                    }
+                    #[cfg(not(feature = "json"))]
+                    {
+                        eprintln!("JSON output requires --features=json");
+                        eprintln!("Please rebuild with: cargo build --features json");
+                        process::exit(2);
+                    }
+                } else {
+                    for finding in &findings {
+                        println!("{finding}");
+                    }
+                    let errors = findings
+                        .iter()
+                        .filter(|f| f.severity == ehatrom::lint::Severity::Error)
+                        .count();
+                    println!(
+                        "{input}: {errors} error(s), {} warning(s)",
+                        findings.len() - errors
+                    );
+                }
+                if has_errors(&findings) {
+                    process::exit(1);
+                }
+            }
+            #[cfg(not(feature = "alloc"))]
+            {
+                let _ = (json, list);
+                eprintln!("The 'lint' command requires the 'alloc' feature");
+                process::exit(2);
+            }
+        }
+        "hash" => {
+            // ehatrom hash <in.bin|i2c-dev>
+            // ehatrom hash --embed <in.bin|i2c-dev> <out.bin|i2c-dev>
+            let embed = args.len() == 5 && args[2] == "--embed";
+            if args.len() != 3 && !embed {
+                eprintln!("Usage: ehatrom hash <in.bin|i2c-dev>");
+                eprintln!("       ehatrom hash --embed <in.bin|i2c-dev> <out.bin|i2c-dev>");
                 process::exit(1);
             }
             #[cfg(feature = "alloc")]
             {
-                let settings = match std::fs::read_to_string(&args[2]) {
-                    Ok(s) => s,
+                let input = if embed { &args[3] } else { &args[2] };
+                let data = match read_image(input) {
+                    Ok(d) => d,
                     Err(e) => {
-                        eprintln!("Failed to read settings file: {e}");
+                        eprintln!("{e}");
                         process::exit(1);
                     }
                 };
-                match ehatrom::parse_settings(&settings) {
-                    Ok(eeprom) => {
-                        let bytes = eeprom.serialize();
-                        if let Err(e) = std::fs::write(&args[3], &bytes) {
-                            eprintln!("Failed to write output: {e}");
+                if embed {
+                    let (bytes, digest) = match ehatrom::digest::embed_digest(&data) {
+                        Ok(r) => r,
+                        Err(e) => {
+                            eprintln!("Cannot hash {input}: {e}");
+                            process::exit(1);
+                        }
+                    };
+                    if let Err(e) = write_image(&args[4], &bytes) {
+                        eprintln!("{e}");
+                        process::exit(1);
+                    }
+                    println!("{digest}");
+                    println!(
+                        "Wrote {} ({} bytes) with the digest in a trailing custom atom",
+                        args[4],
+                        bytes.len()
+                    );
+                } else {
+                    let image = match ehatrom::digest::image_bytes(&data) {
+                        Ok(image) => image,
+                        Err(e) => {
+                            eprintln!("Cannot hash {input}: {e}");
+                            process::exit(1);
+                        }
+                    };
+                    println!("{}", ehatrom::digest::ImageDigest::of(image));
+                    println!("eeplen: {} bytes", image.len());
+                    use ehatrom::digest::{DigestCheck, check_embedded};
+                    match check_embedded(image) {
+                        DigestCheck::Absent => {}
+                        DigestCheck::Valid(_) => println!("embedded digest: valid"),
+                        DigestCheck::Mismatch { .. } => println!("embedded digest: MISMATCH"),
+                    }
+                }
+            }
+            #[cfg(not(feature = "alloc"))]
+            {
+                let _ = embed;
+                eprintln!("The 'hash' command requires the 'alloc' feature");
+                process::exit(1);
+            }
+        }
+        "sign" => {
+            // ehatrom sign --key <secret.key> <in.bin|i2c-dev> <out.bin|i2c-dev>
+            // ehatrom sign --new-key <secret.key> <public.key>
+            let sign = args.len() == 6 && args[2] == "--key";
+            let new_key = args.len() == 5 && args[2] == "--new-key";
+            if !sign && !new_key {
+                eprintln!(
+                    "Usage: ehatrom sign --key <secret.key> <in.bin|i2c-dev> <out.bin|i2c-dev>"
+                );
+                eprintln!("       ehatrom sign --new-key <secret.key> <public.key>");
+                process::exit(1);
+            }
+            #[cfg(all(feature = "sign", feature = "alloc"))]
+            {
+                use ehatrom::sign::{SigningKey, sign_image};
+                if new_key {
+                    let key = SigningKey::from_bytes(&random_bytes());
+                    for (path, bytes, secret) in [
+                        (&args[3], key.to_bytes(), true),
+                        (&args[4], key.verifying_key().to_bytes(), false),
+                    ] {
+                        let hex: String = bytes.iter().map(|b| format!("{b:02x}")).collect();
+                        let written = if secret {
+                            write_secret(path, hex + "\n")
+                        } else {
+                            std::fs::write(path, hex + "\n")
+                        };
+                        if let Err(e) = written {
+                            eprintln!("Failed to write {path}: {e}");
                             process::exit(1);
                         }
-                        println!(
-                            "Wrote {} ({} bytes, {} atoms) from {}",
-                            args[3],
-                            bytes.len(),
-                            eeprom.atom_count(),
-                            args[2]
-                        );
                     }
+                    println!("Wrote secret key {} and public key {}", args[3], args[4]);
+                    return;
+                }
+                let key = SigningKey::from_bytes(&load_key(&args[3]));
+                let data = match read_image(&args[4]) {
+                    Ok(d) => d,
                     Err(e) => {
                         eprintln!("{e}");
                         process::exit(1);
                     }
+                };
+                let bytes = match sign_image(&data, &key) {
+                    Ok(b) => b,
+                    Err(e) => {
+                        eprintln!("Cannot sign {}: {e}", args[4]);
+                        process::exit(1);
+                    }
+                };
+                if let Err(e) = write_image(&args[5], &bytes) {
+                    eprintln!("{e}");
+                    process::exit(1);
                 }
+                println!(
+                    "Wrote {} ({} bytes) with an Ed25519 signature atom",
+                    args[5],
+                    bytes.len()
+                );
+            }
+            #[cfg(not(all(feature = "sign", feature = "alloc")))]
+            {
+                let _ = (sign, new_key);
+                eprintln!("The 'sign' command requires --features=sign");
+                eprintln!("Please rebuild with: cargo build --features sign");
+                process::exit(1);
+            }
+        }
+        "auth" => {
+            // ehatrom auth --add-key <public.key> <in.bin|i2c-dev> <out.bin|i2c-dev>
+            // ehatrom auth <in.bin|i2c-dev> <se-addr> [se-i2c-dev]
+            // ehatrom auth --simulate <secret.key> <in.bin|i2c-dev>
+            let add_key = args.len() == 6 && args[2] == "--add-key";
+            let simulate = args.len() == 5 && args[2] == "--simulate";
+            let chip = (args.len() == 4 || args.len() == 5) && !args[2].starts_with("--");
+            if !add_key && !simulate && !chip {
+                eprintln!(
+                    "Usage: ehatrom auth --add-key <public.key> <in.bin|i2c-dev> <out.bin|i2c-dev>"
+                );
+                eprintln!("       ehatrom auth <in.bin|i2c-dev> <se-addr> [se-i2c-dev]");
+                eprintln!("       ehatrom auth --simulate <secret.key> <in.bin|i2c-dev>");
+                process::exit(1);
+            }
+            #[cfg(all(feature = "sign", feature = "alloc"))]
+            {
+                use ehatrom::auth::{AuthKeyAtom, authenticate};
+                let input = if chip { &args[2] } else { &args[4] };
+                let data = match read_image(input) {
+                    Ok(d) => d,
+                    Err(e) => {
+                        eprintln!("{e}");
+                        process::exit(1);
+                    }
+                };
+                if add_key {
+                    let mut eeprom = match Eeprom::from_bytes(&data) {
+                        Ok(e) => e,
+                        Err(e) => {
+                            eprintln!("Cannot parse {input}: {e}");
+                            process::exit(1);
+                        }
+                    };
+                    if eeprom
+                        .custom_atoms
+                        .iter()
+                        .any(|atom| ehatrom::sign::SignatureAtom::from_atom(atom).is_some())
+                    {
+                        eprintln!("{input} is signed; add the key first, then sign the image");
+                        process::exit(1);
+                    }
+                    let key = AuthKeyAtom(load_key(&args[3]));
+                    eeprom.add_custom_atom(key.to_atom().to_vec());
+                    let bytes = eeprom.serialize();
+                    if let Err(e) = write_image(&args[5], &bytes) {
+                        eprintln!("{e}");
+                        process::exit(1);
+                    }
+                    println!("Wrote {} ({} bytes) with the {key}", args[5], bytes.len());
+                    return;
+                }
+                let challenge = random_bytes();
+                let result = if simulate {
+                    let mut element =
+                        ehatrom::auth::SimulatedSecureElement::new(load_key(&args[3]));
+                    authenticate(&data, &mut element, &challenge).map_err(|e| e.to_string())
+                } else {
+                    #[cfg(all(target_os = "linux", feature = "linux"))]
+                    {
+                        use ehatrom::auth::I2cSecureElement;
+                        let bus = match args.get(4) {
+                            Some(bus) => bus.as_str(),
+                            None if input.starts_with("/dev/i2c-") => input.as_str(),
+                            None => "/dev/i2c-0",
+                        };
+                        let addr = match parse_i2c_addr(&args[3]) {
+                            Ok(addr) => addr,
+                            Err(e) => {
+                                eprintln!("{e}");
+                                process::exit(1);
+                            }
+                        };
+                        match I2cSecureElement::new(bus, addr) {
+                            Ok(mut element) => authenticate(&data, &mut element, &challenge)
+                                .map_err(|e| e.to_string()),
+                            Err(e) => Err(format!("secure element on {bus}: {e}")),
+                        }
+                    }
+                    #[cfg(not(all(target_os = "linux", feature = "linux")))]
+                    {
+                        eprintln!("Talking to a secure element requires --features=linux");
+                        eprintln!("Please rebuild with: cargo build --features linux,sign");
+                        process::exit(1);
+                    }
+                };
+                match result {
+                    Ok(()) => println!("OK: secure element answered the challenge"),
+                    Err(e) => {
+                        eprintln!("FAIL: {e}");
+                        process::exit(1);
+                    }
+                }
+            }
+            #[cfg(not(all(feature = "sign", feature = "alloc")))]
+            {
+                let _ = (add_key, simulate, chip);
+                eprintln!("The 'auth' command requires --features=sign");
+                eprintln!("Please rebuild with: cargo build --features sign");
+                process::exit(1);
+            }
+        }
+        "diff" => {
+            // ehatrom diff [--raw] <a.bin|i2c-dev> <b.bin|i2c-dev>
+            let raw = args.len() == 5 && args[2] == "--raw";
+            if args.len() != 4 && !raw {
+                eprintln!("Usage: ehatrom diff [--raw] <a.bin|i2c-dev> <b.bin|i2c-dev>");
+                eprintln!("  Exit status: 0 if identical, 1 if different, 2 on error");
+                process::exit(2);
+            }
+            #[cfg(feature = "alloc")]
+            {
+                let (old, new) = match (
+                    read_image(&args[args.len() - 2]),
+                    read_image(&args[args.len() - 1]),
+                ) {
+                    (Ok(old), Ok(new)) => (old, new),
+                    (Err(e), _) | (_, Err(e)) => {
+                        eprintln!("{e}");
+                        process::exit(2);
+                    }
+                };
+                if old == new {
+                    println!("Images are identical ({} bytes)", old.len());
+                    return;
+                }
+                if raw {
+                    print!("{}", ehatrom::diff::hexdump_diff(&old, &new));
+                } else {
+                    let changes = ehatrom::diff::diff_images(&old, &new);
+                    for change in &changes {
+                        println!("{change}");
+                    }
+                    if changes.is_empty() {
+                        println!("No semantic differences (bytes outside the atoms differ)");
+                    }
+                }
+                process::exit(1);
+            }
+            #[cfg(not(feature = "alloc"))]
+            {
+                eprintln!("The 'diff' command requires the 'alloc' feature");
+                process::exit(2);
+            }
+        }
+        "conflicts" => {
+            // ehatrom conflicts [--config <config.txt>] [--soc <name>] <a.bin|i2c-dev>...
+            let mut args = args.clone();
+            #[cfg_attr(not(feature = "alloc"), allow(unused_variables))]
+            let soc = take_soc(&mut args).unwrap_or_else(|e| {
+                eprintln!("{e}");
+                process::exit(2);
+            });
+            let config = args.iter().position(|a| a == "--config").map(|pos| {
+                let path = args.get(pos + 1).cloned().unwrap_or_default();
+                args.drain(pos..(pos + 2).min(args.len()));
+                path
+            });
+            if args.len() < 3 || args.len() < 4 && config.is_none() {
+                eprintln!(
+                    "Usage: ehatrom conflicts [--config <config.txt>] [--soc <name>] <a.bin|i2c-dev>..."
+                );
+                eprintln!("  Give two or more images, or one with --config");
+                eprintln!(
+                    "  Exit status: 0 if no conflicts (shared pins allowed), 1 on conflicts, 2 on error"
+                );
+                process::exit(2);
+            }
+            #[cfg(feature = "alloc")]
+            {
+                use ehatrom::conflicts::{ID_EEPROM, enabled_peripherals, find_conflicts};
+                let peripherals = match &config {
+                    Some(path) => match std::fs::read_to_string(path) {
+                        Ok(text) => enabled_peripherals(&text),
+                        Err(e) => {
+                            eprintln!("Failed to read {path}: {e}");
+                            process::exit(2);
+                        }
+                    },
+                    None => vec![ID_EEPROM],
+                };
+                let mut images = Vec::new();
+                for source in &args[2..] {
+                    match read_image(source) {
+                        Ok(data) => images.push(data),
+                        Err(e) => {
+                            eprintln!("{e}");
+                            process::exit(2);
+                        }
+                    }
+                }
+                let boards: Vec<(&str, &[u8])> = args[2..]
+                    .iter()
+                    .map(String::as_str)
+                    .zip(images.iter().map(Vec::as_slice))
+                    .collect();
+                let soc = soc.unwrap_or(ehatrom::Soc::Bcm2835);
+                let conflicts = match find_conflicts(&boards, &peripherals, soc) {
+                    Ok(conflicts) => conflicts,
+                    Err(e) => {
+                        eprintln!("Cannot read pins of {}: {}", boards[e.board].0, e.reason);
+                        process::exit(2);
+                    }
+                };
+                for conflict in &conflicts {
+                    let level = if conflict.is_error() { "error" } else { "note" };
+                    println!("{level}: {conflict}");
+                }
+                let errors = conflicts.iter().filter(|c| c.is_error()).count();
+                println!(
+                    "{} board(s): {errors} conflict(s), {} shared pin(s)",
+                    boards.len(),
+                    conflicts.len() - errors
+                );
+                if errors > 0 {
+                    process::exit(1);
+                }
+            }
+            #[cfg(not(feature = "alloc"))]
+            {
+                let _ = config;
+                eprintln!("The 'conflicts' command requires the 'alloc' feature");
+                process::exit(2);
+            }
+        }
+        "edit" => {
+            // ehatrom edit <in.bin|i2c-dev> <out.bin|i2c-dev> <path=value>...
+            if args.len() < 5 {
+                eprintln!("Usage: ehatrom edit <in.bin|i2c-dev> <out.bin|i2c-dev> <path=value>...");
+                eprintln!("  e.g. vendor_info.product_ver=0x0003 gpio.bank0.pin[17]=OUTPUT,UP");
+                eprintln!("       power_supply=2000 (see the ehatrom::edit docs for all paths)");
+                process::exit(1);
+            }
+            #[cfg(feature = "alloc")]
+            {
+                let data = match read_image(&args[2]) {
+                    Ok(d) => d,
+                    Err(e) => {
+                        eprintln!("{e}");
+                        process::exit(1);
+                    }
+                };
+                let edits = args[4..].iter().map(String::as_str);
+                let bytes = match ehatrom::edit::edit_image(&data, edits) {
+                    Ok(b) => b,
+                    Err(e) => {
+                        eprintln!("{e}");
+                        process::exit(1);
+                    }
+                };
+                if let Err(e) = write_image(&args[3], &bytes) {
+                    eprintln!("{e}");
+                    process::exit(1);
+                }
+                println!(
+                    "Applied {} edit(s); wrote {} ({} bytes)",
+                    args.len() - 4,
+                    args[3],
+                    bytes.len()
+                );
+            }
+            #[cfg(not(feature = "alloc"))]
+            {
+                eprintln!("The 'edit' command requires the 'alloc' feature");
+                process::exit(1);
+            }
+        }
+        "repair" => {
+            // ehatrom repair <in.bin|i2c-dev> <out.bin|i2c-dev>
+            if args.len() != 4 {
+                eprintln!("Usage: ehatrom repair <in.bin|i2c-dev> <out.bin|i2c-dev>");
+                process::exit(1);
+            }
+            #[cfg(feature = "alloc")]
+            {
+                let data = match read_image(&args[2]) {
+                    Ok(d) => d,
+                    Err(e) => {
+                        eprintln!("{e}");
+                        process::exit(1);
+                    }
+                };
+                let (bytes, actions) = match ehatrom::repair::repair(&data) {
+                    Ok(r) => r,
+                    Err(e) => {
+                        eprintln!("Cannot repair {}: {e}", args[2]);
+                        process::exit(1);
+                    }
+                };
+                for action in &actions {
+                    println!("{action}");
+                }
+                if let Err(e) = write_image(&args[3], &bytes) {
+                    eprintln!("{e}");
+                    process::exit(1);
+                }
+                println!(
+                    "Wrote {} ({} bytes, {} change(s))",
+                    args[3],
+                    bytes.len(),
+                    actions.len()
+                );
+            }
+            #[cfg(not(feature = "alloc"))]
+            {
+                eprintln!("The 'repair' command requires the 'alloc' feature");
+                process::exit(1);
+            }
+        }
+        "salvage" => {
+            // ehatrom salvage <in.bin|i2c-dev> <out.bin>
+            if args.len() != 4 {
+                eprintln!("Usage: ehatrom salvage <in.bin|i2c-dev> <out.bin>");
+                process::exit(1);
+            }
+            #[cfg(feature = "alloc")]
+            {
+                let data = match read_image(&args[2]) {
+                    Ok(d) => d,
+                    Err(e) => {
+                        eprintln!("{e}");
+                        process::exit(1);
+                    }
+                };
+                let salvage = ehatrom::repair::salvage(&data);
+                for atom in &salvage.atoms {
+                    println!(
+                        "recovered atom type 0x{:04X} at offset 0x{:04X} ({} bytes)",
+                        atom.atom_type, atom.offset, atom.len
+                    );
+                }
+                for range in &salvage.lost {
+                    println!(
+                        "unrecoverable: 0x{:04X}..0x{:04X} ({} bytes)",
+                        range.start,
+                        range.end,
+                        range.len()
+                    );
+                }
+                if let Err(e) = ehatrom::Eeprom::from_bytes(&salvage.image) {
+                    eprintln!("Warning: rebuilt image is incomplete ({e})");
+                }
+                if let Err(e) = write_image(&args[3], &salvage.image) {
+                    eprintln!("{e}");
+                    process::exit(1);
+                }
+                println!(
+                    "Wrote {} ({} bytes, {} atoms recovered)",
+                    args[3],
+                    salvage.image.len(),
+                    salvage.atoms.len()
+                );
+            }
+            #[cfg(not(feature = "alloc"))]
+            {
+                eprintln!("The 'salvage' command requires the 'alloc' feature");
+                process::exit(1);
+            }
+        }
+        "make" => {
+            // ehatrom make [--from-json] [--dts <overlay.dts>]
+            //              [--gpio-from <pinctrl.txt> [--pins <list>]] [<input>] <output.bin>
+            let mut from_json = false;
+            let mut dts: Option<&str> = None;
+            let mut gpio_from: Option<&str> = None;
+            let mut pins: Option<&str> = None;
+            let mut files = Vec::new();
+            let mut rest = args[2..].iter();
+            while let Some(arg) = rest.next() {
+                match arg.as_str() {
+                    "--from-json" => from_json = true,
+                    "--dts" => match rest.next() {
+                        Some(path) => dts = Some(path),
+                        // Missing value: fall through to the usage message.
+                        None => files.clear(),
+                    },
+                    "--gpio-from" => match rest.next() {
+                        Some(path) => gpio_from = Some(path),
+                        None => files.clear(),
+                    },
+                    "--pins" => match rest.next() {
+                        Some(list) => pins = Some(list),
+                        None => files.clear(),
+                    },
+                    _ => files.push(arg),
+                }
+            }
+            // With --gpio-from the input may be left out: the pins are all there is.
+            let arity_ok = files.len() == 2 || files.len() == 1 && gpio_from.is_some();
+            if !arity_ok || pins.is_some() && gpio_from.is_none() {
+                eprintln!(
+                    "Usage: ehatrom make [--dts <overlay.dts>] <settings.txt|board.toml> <output.bin>"
+                );
+                eprintln!("       ehatrom make --from-json <input.json> <output.bin>");
+                eprintln!(
+                    "       ehatrom make --gpio-from <pinctrl.txt> [--pins <list>] [<input>] <output.bin>"
+                );
+                process::exit(1);
+            }
+            #[cfg(feature = "alloc")]
+            {
+                let input = if files.len() == 2 {
+                    files[0].as_str()
+                } else {
+                    ""
+                };
+                let output = files[files.len() - 1];
+                let mut eeprom = if input.is_empty() {
+                    ehatrom::parse_settings("").expect("empty settings parse")
+                } else if from_json {
+                    load_json(input)
+                } else if input.ends_with(".toml") {
+                    load_toml(input)
+                } else {
+                    let settings = match std::fs::read_to_string(input) {
+                        Ok(s) => s,
+                        Err(e) => {
+                            eprintln!("Failed to read settings file: {e}");
+                            process::exit(1);
+                        }
+                    };
+                    // File directives are relative to the settings file.
+                    let dir = std::path::Path::new(input)
+                        .parent()
+                        .unwrap_or(std::path::Path::new(""));
+                    let loaded = ehatrom::parse_settings_with_loader(&settings, |path| {
+                        let path = dir.join(path);
+                        std::fs::read(&path).map_err(|e| {
+                            eprintln!("Failed to read {}: {e}", path.display());
+                            "cannot read file"
+                        })
+                    });
+                    match loaded {
+                        Ok(eeprom) => eeprom,
+                        Err(e) => {
+                            eprintln!("{e}");
+                            process::exit(1);
+                        }
+                    }
+                };
+                if let Some(path) = dts {
+                    eeprom.add_dt_blob(compile_dts_file(path));
+                }
+                if let Some(path) = gpio_from {
+                    use ehatrom::import::{parse_pin_dump, parse_pin_list};
+                    let filter = pins.map(|list| {
+                        parse_pin_list(list).unwrap_or_else(|| {
+                            eprintln!("Invalid pin list '{list}' (expected e.g. 4,17,22-27)");
+                            process::exit(1);
+                        })
+                    });
+                    let text = std::fs::read_to_string(path).unwrap_or_else(|e| {
+                        eprintln!("Failed to read {path}: {e}");
+                        process::exit(1);
+                    });
+                    match parse_pin_dump(&text, filter.as_deref()) {
+                        Ok(imported) => imported.apply(&mut eeprom),
+                        Err(e) => {
+                            eprintln!("{path}: {e}");
+                            process::exit(1);
+                        }
+                    }
+                }
+                if let Some(ehatrom::DtAtom::Blob(blob)) = eeprom.dt_atom()
+                    && let Err(e) = ehatrom::fdt::Fdt::parse(blob)
+                {
+                    eprintln!("Refusing to build {output}: DT blob is not a valid FDT ({e})");
+                    process::exit(1);
+                }
+                let bytes = eeprom.serialize();
+                if let Err(e) = std::fs::write(output, &bytes) {
+                    eprintln!("Failed to write output: {e}");
+                    process::exit(1);
+                }
+                println!(
+                    "Wrote {} ({} bytes, {} atoms) from {}",
+                    output,
+                    bytes.len(),
+                    eeprom.atom_count(),
+                    if input.is_empty() {
+                        gpio_from.unwrap_or_default()
+                    } else {
+                        input
+                    }
+                );
             }
             #[cfg(not(feature = "alloc"))]
             {
+                let _ = (from_json, dts);
                 eprintln!("The 'make' command requires the 'alloc' feature");
                 process::exit(1);
             }
@@ -420,3 +1471,193 @@ fn main() {
         }
     }
 }
+
+/// Loads an [`Eeprom`](ehatrom::Eeprom) from a JSON document (`make --from-json`).
+#[cfg(feature = "alloc")]
+fn load_json(path: &str) -> ehatrom::Eeprom {
+    #[cfg(feature = "json")]
+    {
+        let text = match std::fs::read_to_string(path) {
+            Ok(s) => s,
+            Err(e) => {
+                eprintln!("Failed to read JSON file: {e}");
+                std::process::exit(1);
+            }
+        };
+        match serde_json::from_str(&text) {
+            Ok(e) => e,
+            Err(e) => {
+                eprintln!("JSON error: {e}");
+                std::process::exit(1);
+            }
+        }
+    }
+    #[cfg(not(feature = "json"))]
+    {
+        let _ = path;
+        eprintln!("JSON input requires --features=json");
+        eprintln!("Please rebuild with: cargo build --features json");
+        std::process::exit(1);
+    }
+}
+
+/// Loads an [`Eeprom`](ehatrom::Eeprom) from a TOML board description.
+#[cfg(feature = "alloc")]
+fn load_toml(path: &str) -> ehatrom::Eeprom {
+    #[cfg(feature = "toml")]
+    match ehatrom::board::load_board(std::path::Path::new(path)) {
+        Ok(eeprom) => eeprom,
+        Err(e) => {
+            eprintln!("{e}");
+            std::process::exit(1);
+        }
+    }
+    #[cfg(not(feature = "toml"))]
+    {
+        let _ = path;
+        eprintln!("TOML board files require --features=toml");
+        eprintln!("Please rebuild with: cargo build --features toml");
+        std::process::exit(1);
+    }
+}
+
+/// 32 bytes from `/dev/urandom`, for keys and challenges.
+#[cfg(all(feature = "sign", feature = "alloc"))]
+fn random_bytes() -> [u8; 32] {
+    let mut bytes = [0u8; 32];
+    if let Err(e) = std::fs::File::open("/dev/urandom")
+        .and_then(|mut f| std::io::Read::read_exact(&mut f, &mut bytes))
+    {
+        eprintln!("Failed to read /dev/urandom: {e}");
+        std::process::exit(1);
+    }
+    bytes
+}
+
+/// Writes a secret-key file readable by its owner only (mode 0600 on Unix).
+#[cfg(all(feature = "sign", feature = "alloc"))]
+fn write_secret(path: &str, contents: String) -> std::io::Result<()> {
+    use std::io::Write;
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+        options.mode(0o600);
+        let mut file = options.open(path)?;
+        // `mode` only applies to new files; tighten an existing one too.
+        file.set_permissions(std::fs::Permissions::from_mode(0o600))?;
+        file.write_all(contents.as_bytes())
+    }
+    #[cfg(not(unix))]
+    options.open(path)?.write_all(contents.as_bytes())
+}
+
+/// Reads a 32-byte Ed25519 key file: raw bytes, or 64 hex digits as written
+/// by `sign --new-key`.
+#[cfg(feature = "sign")]
+fn load_key(path: &str) -> [u8; 32] {
+    let bytes = match std::fs::read(path) {
+        Ok(b) => b,
+        Err(e) => {
+            eprintln!("Failed to read {path}: {e}");
+            std::process::exit(1);
+        }
+    };
+    if let Ok(raw) = <[u8; 32]>::try_from(bytes.as_slice()) {
+        return raw;
+    }
+    let hex = core::str::from_utf8(&bytes).unwrap_or("").trim();
+    let mut key = [0u8; 32];
+    let parsed = hex.len() == 64
+        && key.iter_mut().enumerate().all(|(i, byte)| {
+            hex.get(2 * i..2 * i + 2)
+                .and_then(|pair| u8::from_str_radix(pair, 16).ok())
+                .map(|b| *byte = b)
+                .is_some()
+        });
+    if !parsed {
+        eprintln!("{path}: expected 32 raw bytes or 64 hex digits");
+        std::process::exit(1);
+    }
+    key
+}
+
+/// Parses a 7-bit I2C address given as `0x60` or `96`.
+#[cfg(all(target_os = "linux", feature = "linux", feature = "sign"))]
+fn parse_i2c_addr(arg: &str) -> Result<u16, String> {
+    let addr = match arg.strip_prefix("0x") {
+        Some(hex) => u16::from_str_radix(hex, 16),
+        None => arg.parse(),
+    };
+    match addr {
+        Ok(addr) if addr < 0x80 => Ok(addr),
+        _ => Err(format!("Invalid I2C address '{arg}' (expected e.g. 0x60)")),
+    }
+}
+
+/// Removes `--soc <name>` from `args`, returning the SoC it names.
+fn take_soc(args: &mut Vec<String>) -> Result<Option<ehatrom::Soc>, String> {
+    let Some(pos) = args.iter().position(|a| a == "--soc") else {
+        return Ok(None);
+    };
+    let name = args.get(pos + 1).cloned().unwrap_or_default();
+    args.drain(pos..(pos + 2).min(args.len()));
+    ehatrom::Soc::from_name(&name).map(Some).ok_or(format!(
+        "Unknown SoC '{name}' (expected bcm2835, bcm2711 or rp1)"
+    ))
+}
+
+/// Reads and compiles an overlay source file. Compile errors are reported
+/// with the file name and line.
+#[cfg(feature = "alloc")]
+fn compile_dts_file(path: &str) -> Vec<u8> {
+    let source = match std::fs::read_to_string(path) {
+        Ok(s) => s,
+        Err(e) => {
+            eprintln!("Failed to read {path}: {e}");
+            std::process::exit(1);
+        }
+    };
+    match ehatrom::dts::compile_dts(&source) {
+        Ok(blob) => blob,
+        Err(e) => {
+            eprintln!("{path}: {e}");
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Reads an image from a file, or from the HAT EEPROM when `source` is an I2C
+/// device path such as `/dev/i2c-0`.
+#[cfg(feature = "alloc")]
+fn read_image(source: &str) -> Result<Vec<u8>, String> {
+    if source.starts_with("/dev/i2c-") {
+        #[cfg(all(target_os = "linux", feature = "linux"))]
+        return ehatrom::read_eeprom_image(source)
+            .map_err(|e| format!("Read error on {source}: {e}"));
+        #[cfg(not(all(target_os = "linux", feature = "linux")))]
+        return Err(format!(
+            "Reading {source} requires --features=linux\n\
+             Please rebuild with: cargo build --features linux"
+        ));
+    }
+    std::fs::read(source).map_err(|e| format!("Failed to read {source}: {e}"))
+}
+
+/// Writes an image to a file, or to the HAT EEPROM (address 0x50) when `target`
+/// is an I2C device path.
+#[cfg(feature = "alloc")]
+fn write_image(target: &str, data: &[u8]) -> Result<(), String> {
+    if target.starts_with("/dev/i2c-") {
+        #[cfg(all(target_os = "linux", feature = "linux"))]
+        return ehatrom::write_to_eeprom_i2c(data, target, 0x50)
+            .map_err(|e| format!("Write error on {target}: {e}"));
+        #[cfg(not(all(target_os = "linux", feature = "linux")))]
+        return Err(format!(
+            "Writing {target} requires --features=linux\n\
+             Please rebuild with: cargo build --features linux"
+        ));
+    }
+    std::fs::write(target, data).map_err(|e| format!("Failed to write {target}: {e}"))
+}
@@ -0,0 +1,302 @@
+//  _  _       _             _  _
+// | || |  ___| |_ _ __ ___ | || |
+// | || |_/ __| __| '_ ` _ \| || |_
+// |__   _\__ | |_| | | | | |__   _|
+//   |_| |___/\__|_|_|_| |_|  |_|
+//! # ehatrom — EEPROM HAT library for Raspberry Pi HATs
+//! - [Documentation (docs.rs)](https://docs.rs/ehatrom)
+//! - [GitHub](https://github.com/4stm4/ehatrom)
+//!
+//! ## GPIO tables with 40-pin header positions
+//!
+//! [`PinTable`] renders the GPIO maps for people rather than as pin bytes:
+//! one row per GPIO with its physical header pin, function (ALT functions
+//! named from a [`Soc`] table), pull and the bank's drive strength.
+//!
+//! ```text
+//!   Pin  GPIO  Function              Pull     Drive
+//!     3     2  ALT0 (I2C1_SDA)       UP       default
+//!     5     3  unused
+//! ```
+//!
+//! Every bank0 GPIO gets a row, unused ones marked as such; bank1 GPIOs,
+//! which are not on the header, are listed only when used. With
+//! [`PinTable::colour`] the output carries ANSI colours: inputs green,
+//! outputs yellow, ALT functions cyan, reserved ALT slots red and unused
+//! pins dimmed. [`PinTable::header`] draws the 2x20 header itself, used pins
+//! in square brackets.
+//!
+//! Nothing here allocates, so the renderer also works without `alloc`.
+
+use crate::{Eeprom, GPIO_COUNT, GpioMapAtom, PinConfig, PinFunc, Soc, decode_pin, soc};
+use core::fmt;
+
+/// What sits on a pin of the 40-pin header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HeaderPin {
+    /// A BCM GPIO.
+    Gpio(u8),
+    Power3V3,
+    Power5V,
+    Ground,
+}
+
+use HeaderPin::{Gpio, Ground, Power3V3, Power5V};
+
+/// The 40-pin header, pin 1 first: odd pins are the inner row.
+#[rustfmt::skip]
+pub const HEADER: [HeaderPin; 40] = [
+    Power3V3, Power5V,   Gpio(2), Power5V,   Gpio(3), Ground,    Gpio(4), Gpio(14),
+    Ground,   Gpio(15),  Gpio(17), Gpio(18), Gpio(27), Ground,   Gpio(22), Gpio(23),
+    Power3V3, Gpio(24),  Gpio(10), Ground,   Gpio(9), Gpio(25),  Gpio(11), Gpio(8),
+    Ground,   Gpio(7),   Gpio(0), Gpio(1),   Gpio(5), Ground,    Gpio(6), Gpio(12),
+    Gpio(13), Ground,    Gpio(19), Gpio(16), Gpio(26), Gpio(20), Ground,  Gpio(21),
+];
+
+/// The header pin (1–40) that carries BCM `gpio`, if any.
+pub fn header_pin(gpio: usize) -> Option<u8> {
+    HEADER
+        .iter()
+        .position(|&pin| pin == Gpio(gpio as u8) && gpio < GPIO_COUNT)
+        .map(|i| i as u8 + 1)
+}
+
+const RESET: &str = "\x1b[0m";
+const DIM: &str = "\x1b[2m";
+const RED: &str = "\x1b[31m";
+
+/// Human-readable GPIO table of one or two GPIO maps.
+#[derive(Debug, Clone, Copy)]
+pub struct PinTable {
+    bank0: GpioMapAtom,
+    bank1: Option<GpioMapAtom>,
+    soc: Soc,
+    colour: bool,
+}
+
+impl PinTable {
+    /// A table of `bank0` and optionally `bank1`, naming ALT functions from
+    /// the BCM2835 table, without colours.
+    pub fn new(bank0: GpioMapAtom, bank1: Option<GpioMapAtom>) -> Self {
+        PinTable {
+            bank0,
+            bank1,
+            soc: Soc::Bcm2835,
+            colour: false,
+        }
+    }
+
+    /// A table of `eeprom`'s GPIO maps.
+    pub fn from_eeprom(eeprom: &Eeprom) -> Self {
+        PinTable::new(eeprom.gpio_map_bank0, eeprom.gpio_map_bank1)
+    }
+
+    /// Names ALT pin functions from `soc`'s table.
+    pub fn soc(mut self, soc: Soc) -> Self {
+        self.soc = soc;
+        self
+    }
+
+    /// Colours the output with ANSI escape codes.
+    pub fn colour(mut self, colour: bool) -> Self {
+        self.colour = colour;
+        self
+    }
+
+    /// The 2x20 header as ASCII art.
+    pub fn header(&self) -> HeaderArt<'_> {
+        HeaderArt { table: self }
+    }
+
+    /// The decoded pin and bank flags of `gpio`.
+    fn pin(&self, gpio: usize) -> Option<(PinConfig, u8)> {
+        let (bank, index) = match gpio.checked_sub(GPIO_COUNT) {
+            None => (&self.bank0, gpio),
+            Some(index) => (self.bank1.as_ref()?, index),
+        };
+        let pins = bank.pins;
+        Some((decode_pin(*pins.get(index)?), bank.flags))
+    }
+
+    /// `ALT0` plus `I2C1_SDA`, `reserved` or nothing, and the colour.
+    fn function(&self, gpio: usize, func: PinFunc) -> (&'static str, Option<&'static str>, &str) {
+        let colour = match func {
+            PinFunc::Input => "\x1b[32m",
+            PinFunc::Output => "\x1b[33m",
+            _ => "\x1b[36m",
+        };
+        match self.soc.alt_function(gpio, func) {
+            Some(name) => (func.keyword(), Some(name), colour),
+            None if soc::alt_number(func).is_some() && gpio < self.soc.gpio_count() => {
+                (func.keyword(), Some("reserved"), RED)
+            }
+            None => (func.keyword(), None, colour),
+        }
+    }
+
+    fn paint(&self, f: &mut fmt::Formatter<'_>, colour: &str) -> fmt::Result {
+        if self.colour {
+            f.write_str(colour)?;
+        }
+        Ok(())
+    }
+
+    fn row(&self, f: &mut fmt::Formatter<'_>, gpio: usize) -> fmt::Result {
+        let Some((pin, flags)) = self.pin(gpio) else {
+            return Ok(());
+        };
+        if !pin.used && gpio >= GPIO_COUNT {
+            return Ok(());
+        }
+        match header_pin(gpio) {
+            Some(n) => write!(f, "  {n:>3}")?,
+            None => f.write_str("    -")?,
+        }
+        write!(f, "  {gpio:>4}  ")?;
+        if !pin.used {
+            self.paint(f, DIM)?;
+            f.write_str("unused")?;
+            self.paint(f, RESET)?;
+            return writeln!(f);
+        }
+        let (keyword, name, colour) = self.function(gpio, pin.func);
+        self.paint(f, colour)?;
+        f.write_str(keyword)?;
+        let mut width = keyword.len();
+        if let Some(name) = name {
+            write!(f, " ({name})")?;
+            width += name.len() + 3;
+        }
+        self.paint(f, RESET)?;
+        write!(f, "{:1$}", "", 22usize.saturating_sub(width).max(1))?;
+        write!(f, "{:<9}", pin.pull.keyword())?;
+        match flags & 0x0F {
+            0 => writeln!(f, "default"),
+            drive => writeln!(f, "{} mA", drive * 2),
+        }
+    }
+}
+
+impl fmt::Display for PinTable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "  Pin  GPIO  Function              Pull     Drive")?;
+        (0..GPIO_COUNT + crate::GPIO_COUNT_BANK1).try_for_each(|gpio| self.row(f, gpio))
+    }
+}
+
+/// `Display` adapter returned by [`PinTable::header`].
+pub struct HeaderArt<'a> {
+    table: &'a PinTable,
+}
+
+impl HeaderArt<'_> {
+    /// Writes the label of header pin `pin`, right-aligned to `width` when
+    /// `width` is non-zero.
+    fn label(&self, f: &mut fmt::Formatter<'_>, pin: HeaderPin, width: usize) -> fmt::Result {
+        let table = self.table;
+        let (colour, text) = match pin {
+            Power3V3 => (RED, "3V3"),
+            Power5V => (RED, "5V"),
+            Ground => (DIM, "GND"),
+            Gpio(gpio) => {
+                let gpio = gpio as usize;
+                let (pin, _) = table.pin(gpio).expect("header GPIOs are in bank0");
+                let digits = if gpio < 10 { 1 } else { 2 };
+                let (colour, name) = if pin.used {
+                    let (keyword, name, colour) = table.function(gpio, pin.func);
+                    (
+                        colour,
+                        Some(name.filter(|&n| n != "reserved").unwrap_or(keyword)),
+                    )
+                } else {
+                    (DIM, None)
+                };
+                let len = 4 + digits + name.map_or(0, |n| n.len() + 1);
+                write!(f, "{:1$}", "", width.saturating_sub(len))?;
+                table.paint(f, colour)?;
+                write!(f, "GPIO{gpio}")?;
+                if let Some(name) = name {
+                    write!(f, " {name}")?;
+                }
+                return table.paint(f, RESET);
+            }
+        };
+        write!(f, "{:1$}", "", width.saturating_sub(text.len()))?;
+        table.paint(f, colour)?;
+        f.write_str(text)?;
+        table.paint(f, RESET)
+    }
+
+    fn pin_box(&self, f: &mut fmt::Formatter<'_>, n: usize) -> fmt::Result {
+        let used = match HEADER[n - 1] {
+            Gpio(gpio) => self
+                .table
+                .pin(gpio as usize)
+                .is_some_and(|(pin, _)| pin.used),
+            _ => false,
+        };
+        match used {
+            true => write!(f, "[{n:>2}]"),
+            false => write!(f, "({n:>2})"),
+        }
+    }
+}
+
+impl fmt::Display for HeaderArt<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for row in 0..20 {
+            let (odd, even) = (2 * row + 1, 2 * row + 2);
+            self.label(f, HEADER[odd - 1], 22)?;
+            f.write_str("  ")?;
+            self.pin_box(f, odd)?;
+            self.pin_box(f, even)?;
+            f.write_str("  ")?;
+            self.label(f, HEADER[even - 1], 0)?;
+            writeln!(f)?;
+        }
+        writeln!(f, "[n] used by the HAT, (n) unused or power")
+    }
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    use super::*;
+    use crate::parse_settings;
+    use alloc::string::ToString;
+
+    #[test]
+    fn maps_gpios_to_header_pins() {
+        assert_eq!(header_pin(2), Some(3));
+        assert_eq!(header_pin(21), Some(40));
+        assert_eq!(header_pin(0), Some(27));
+        assert_eq!(header_pin(30), None);
+        let gpios = HEADER.iter().filter(|pin| matches!(pin, Gpio(_))).count();
+        assert_eq!(gpios, GPIO_COUNT);
+    }
+
+    #[test]
+    fn renders_table_and_header() {
+        let eeprom = parse_settings(
+            "gpio_drive 4\nsetgpio 2 ALT0 UP\nsetgpio 17 OUTPUT\nsetgpio 16 ALT3\n\
+             setgpio 30 ALT3 DOWN\n",
+        )
+        .unwrap();
+        let table = PinTable::from_eeprom(&eeprom).soc(Soc::Rp1);
+        let text = table.to_string();
+        assert!(text.contains("\n    3     2  ALT0 (SPI0_CSN3)      UP       8 mA\n"));
+        assert!(text.contains("\n    5     3  unused\n"));
+        assert!(text.contains("\n   36    16  ALT3 (reserved)       DEFAULT  8 mA\n"));
+        // Bank1 pins are not on the header; RP1 has no table for them.
+        assert!(text.contains("\n    -    30  ALT3                  DOWN     default\n"));
+        assert!(!text.contains("    -    31  "));
+
+        let art = table.header().to_string();
+        assert!(art.starts_with("                   3V3  ( 1)( 2)  5V\n"));
+        assert!(art.contains("\n       GPIO2 SPI0_CSN3  [ 3]( 4)  5V\n"));
+        assert!(art.contains("\n         GPIO17 OUTPUT  [11](12)  GPIO18\n"));
+
+        let coloured = table.colour(true).to_string();
+        assert!(coloured.contains("\x1b[36mALT0 (SPI0_CSN3)\x1b[0m"));
+        assert!(coloured.contains("\x1b[2munused\x1b[0m"));
+    }
+}
@@ -0,0 +1,417 @@
+//  _  _       _             _  _
+// | || |  ___| |_ _ __ ___ | || |
+// | || |_/ __| __| '_ ` _ \| || |_
+// |__   _\__ | |_| | | | | |__   _|
+//   |_| |___/\__|_|_|_| |_|  |_|
+//! # ehatrom — EEPROM HAT library for Raspberry Pi HATs
+//! - [Documentation (docs.rs)](https://docs.rs/ehatrom)
+//! - [GitHub](https://github.com/4stm4/ehatrom)
+//!
+//! ## Repair of inconsistent images
+//!
+//! [`repair`] rewrites an image whose atoms are intact but whose bookkeeping
+//! is wrong. It recomputes every per-atom CRC-16, renumbers the `count`
+//! fields, corrects `numatoms`/`eeplen` (and a zero `version`), and drops a
+//! truncated trailing atom. Atoms are copied byte for byte through
+//! [`atoms`](crate::atoms) and re-emitted with `write_atom`, so their order is
+//! kept and unknown atom types survive unchanged.
+//!
+//! Atoms beyond the header's `numatoms` are recovered too, but only if they
+//! lie within `eeplen` and their stored CRC-16 is valid. A stale tail from an
+//! earlier, longer image is left alone.
+//!
+//! [`salvage`] is the last resort when the header or an early atom is damaged
+//! and [`atoms`](crate::atoms) stops short. It scans every offset for an
+//! 8-byte atom header with a known type and a `dlen` that fits, and whose
+//! CRC-16 verifies. It rebuilds a best-effort image from whatever it finds and
+//! reports the byte ranges it could not account for.
+
+use crate::{ATOM_HDR_SIZE, AtomIter, CRC_SIZE, EEPROM_SIGNATURE, FORMAT_VERSION, HEADER_SIZE};
+use crate::{AtomRef, AtomType};
+use crate::{diff::atom_name, write_atom};
+use alloc::{vec, vec::Vec};
+use core::fmt;
+use core::ops::Range;
+
+/// One change made by [`repair`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RepairAction {
+    /// A zero header `version` was set to [`FORMAT_VERSION`].
+    Version { old: u8, new: u8 },
+    /// The header `numatoms` was corrected.
+    NumAtoms { old: u16, new: u16 },
+    /// The header `eeplen` was corrected.
+    EepLen { old: u32, new: u32 },
+    /// An atom's `count` field was renumbered.
+    Count { index: usize, old: u16, new: u16 },
+    /// An atom's stored CRC-16 was replaced with the recomputed one.
+    Crc {
+        index: usize,
+        atom_type: u16,
+        old: u16,
+        new: u16,
+    },
+    /// An atom found past `numatoms` (within `eeplen`, CRC valid) was kept.
+    Recovered { index: usize, offset: usize },
+    /// A truncated or malformed trailing atom starting at `offset` was dropped,
+    /// together with any atoms the header claimed after it.
+    DroppedTail { offset: usize, atoms: u16 },
+}
+
+impl fmt::Display for RepairAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RepairAction::Version { old, new } => write!(f, "header.version: {old} -> {new}"),
+            RepairAction::NumAtoms { old, new } => write!(f, "header.numatoms: {old} -> {new}"),
+            RepairAction::EepLen { old, new } => write!(f, "header.eeplen: {old} -> {new}"),
+            RepairAction::Count { index, old, new } => {
+                write!(f, "atom {index}: count {old} -> {new}")
+            }
+            RepairAction::Crc {
+                index,
+                atom_type,
+                old,
+                new,
+            } => write!(
+                f,
+                "atom {index} ({}): CRC-16 0x{old:04X} -> 0x{new:04X}",
+                atom_name(*atom_type)
+            ),
+            RepairAction::Recovered { index, offset } => write!(
+                f,
+                "atom {index}: recovered beyond numatoms at offset 0x{offset:04X}"
+            ),
+            RepairAction::DroppedTail { offset, atoms } => write!(
+                f,
+                "dropped {atoms} missing or truncated atom(s) from offset 0x{offset:04X}"
+            ),
+        }
+    }
+}
+
+/// Repairs the bookkeeping of `data`, returning the rewritten image and the
+/// list of changes made (empty when the image was already consistent).
+///
+/// Fails only when `data` has no `R-Pi` header.
+pub fn repair(data: &[u8]) -> Result<(Vec<u8>, Vec<RepairAction>), &'static str> {
+    if data.len() < HEADER_SIZE {
+        return Err("Not enough data for EEPROM header");
+    }
+    if data[0..4] != EEPROM_SIGNATURE {
+        return Err("Invalid EEPROM signature");
+    }
+    let version = data[4];
+    let numatoms = u16::from_le_bytes([data[6], data[7]]);
+    let eeplen = u32::from_le_bytes([data[8], data[9], data[10], data[11]]);
+    // Past `numatoms`, only trust atoms inside a plausible `eeplen`.
+    let limit = match eeplen as usize {
+        n if (HEADER_SIZE..=data.len()).contains(&n) => n,
+        _ => HEADER_SIZE,
+    };
+
+    let mut actions = Vec::new();
+    let mut kept = Vec::new();
+    let mut iter = AtomIter {
+        data,
+        offset: HEADER_SIZE,
+        remaining: u16::MAX,
+    };
+    loop {
+        let start = iter.offset;
+        let claimed = kept.len() < numatoms as usize;
+        match iter.next() {
+            Some(atom) if claimed => kept.push(atom),
+            Some(atom) if iter.offset <= limit && atom.crc_valid() => {
+                actions.push(RepairAction::Recovered {
+                    index: kept.len(),
+                    offset: start,
+                });
+                kept.push(atom);
+            }
+            None if claimed => {
+                actions.push(RepairAction::DroppedTail {
+                    offset: start,
+                    atoms: numatoms - kept.len() as u16,
+                });
+                break;
+            }
+            _ => break,
+        }
+    }
+
+    let (mut out, count) = rebuild(&kept, &mut actions);
+    let new_version = if version == 0 {
+        FORMAT_VERSION
+    } else {
+        version
+    };
+    if new_version != version {
+        actions.push(RepairAction::Version {
+            old: version,
+            new: new_version,
+        });
+    }
+    if count != numatoms {
+        actions.push(RepairAction::NumAtoms {
+            old: numatoms,
+            new: count,
+        });
+    }
+    let new_eeplen = out.len() as u32;
+    if new_eeplen != eeplen {
+        actions.push(RepairAction::EepLen {
+            old: eeplen,
+            new: new_eeplen,
+        });
+    }
+    write_header(&mut out, new_version, data[5], count);
+    Ok((out, actions))
+}
+
+/// Re-emits `kept` with sequential counts and fresh CRC-16s after a blank
+/// header, logging renumbered counts and replaced CRCs into `actions`.
+fn rebuild(kept: &[AtomRef<'_>], actions: &mut Vec<RepairAction>) -> (Vec<u8>, u16) {
+    let body: usize = kept
+        .iter()
+        .map(|a| ATOM_HDR_SIZE + a.data.len() + CRC_SIZE)
+        .sum();
+    let mut out = vec![0u8; HEADER_SIZE + body];
+    let mut offset = HEADER_SIZE;
+    let mut count = 0u16;
+    for (index, atom) in kept.iter().enumerate() {
+        if atom.count != count {
+            actions.push(RepairAction::Count {
+                index,
+                old: atom.count,
+                new: count,
+            });
+        }
+        // `out` is sized for exactly these atoms, so this cannot fail.
+        let _ = write_atom(&mut out, &mut offset, &mut count, atom.atom_type, atom.data);
+        let new_crc = u16::from_le_bytes([out[offset - 2], out[offset - 1]]);
+        // Renumbering alone changes the CRC; only report CRCs that were wrong.
+        if !atom.crc_valid() {
+            actions.push(RepairAction::Crc {
+                index,
+                atom_type: atom.atom_type,
+                old: atom.stored_crc(),
+                new: new_crc,
+            });
+        }
+    }
+    (out, count)
+}
+
+fn write_header(out: &mut [u8], version: u8, reserved: u8, numatoms: u16) {
+    let eeplen = out.len() as u32;
+    out[0..4].copy_from_slice(&EEPROM_SIGNATURE);
+    out[4] = version;
+    out[5] = reserved;
+    out[6..8].copy_from_slice(&numatoms.to_le_bytes());
+    out[8..12].copy_from_slice(&eeplen.to_le_bytes());
+}
+
+/// An atom found by [`salvage`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SalvagedAtom {
+    /// Offset of the atom header in the damaged input.
+    pub offset: usize,
+    /// Raw atom type field.
+    pub atom_type: u16,
+    /// Payload length (without header and CRC).
+    pub len: usize,
+}
+
+/// Result of [`salvage`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Salvage {
+    /// Rebuilt image: a fresh header followed by every recovered atom, in the
+    /// order found. It may lack the mandatory vendor or GPIO atoms.
+    pub image: Vec<u8>,
+    /// Atoms recovered from the input.
+    pub atoms: Vec<SalvagedAtom>,
+    /// Input byte ranges not covered by a valid header or a recovered atom.
+    /// Erased trailing space (a run of `0x00` or `0xFF`) is not reported.
+    pub lost: Vec<Range<usize>>,
+}
+
+/// Scans `data` byte by byte for CRC-verified atoms and rebuilds an image
+/// from them. Never fails; an unrecognisable input yields a header-only image.
+pub fn salvage(data: &[u8]) -> Salvage {
+    let header_ok = data.len() >= HEADER_SIZE && data[0..4] == EEPROM_SIGNATURE;
+    let mut kept: Vec<AtomRef<'_>> = Vec::new();
+    let mut atoms = Vec::new();
+    let mut lost: Vec<Range<usize>> = Vec::new();
+    let mut gap_start = if header_ok { HEADER_SIZE } else { 0 };
+    let mut offset = gap_start;
+
+    while offset + ATOM_HDR_SIZE + CRC_SIZE <= data.len() {
+        let atom_type = u16::from_le_bytes([data[offset], data[offset + 1]]);
+        let candidate = if AtomType::from(atom_type) == AtomType::Unknown {
+            None
+        } else {
+            let mut iter = AtomIter {
+                data,
+                offset,
+                remaining: 1,
+            };
+            iter.next()
+                .filter(|a| a.crc_valid())
+                .map(|a| (a, iter.offset))
+        };
+        match candidate {
+            Some((atom, end)) => {
+                if gap_start < offset {
+                    lost.push(gap_start..offset);
+                }
+                atoms.push(SalvagedAtom {
+                    offset,
+                    atom_type,
+                    len: atom.data.len(),
+                });
+                kept.push(atom);
+                offset = end;
+                gap_start = end;
+            }
+            None => offset += 1,
+        }
+    }
+    // Leave out erased space (a run of 0xFF or 0x00) at the end of the input.
+    let mut end = data.len();
+    if let Some(&fill) = data.last().filter(|&&b| b == 0xFF || b == 0x00) {
+        while end > gap_start && data[end - 1] == fill {
+            end -= 1;
+        }
+    }
+    if gap_start < end {
+        lost.push(gap_start..end);
+    }
+
+    let version = match data.get(4) {
+        Some(&v) if header_ok && v != 0 => v,
+        _ => FORMAT_VERSION,
+    };
+    // Counts are renumbered silently: the atom list already reports what was kept.
+    let (mut image, count) = rebuild(&kept, &mut Vec::new());
+    write_header(&mut image, version, 0, count);
+    Salvage { image, atoms, lost }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Eeprom, atoms, parse_settings};
+
+    fn image() -> Vec<u8> {
+        let mut e = parse_settings("vendor \"ACME\"\nproduct \"Test HAT\"\n").unwrap();
+        e.add_custom_atom(b"serial=42".to_vec());
+        e.serialize()
+    }
+
+    #[test]
+    fn consistent_image_is_unchanged() {
+        let img = image();
+        let (out, actions) = repair(&img).unwrap();
+        assert_eq!(out, img);
+        assert!(actions.is_empty());
+    }
+
+    #[test]
+    fn fixes_crc_count_and_header() {
+        let img = image();
+        let mut bad = img.clone();
+        let last = bad.len() - 1;
+        bad[last] ^= 0xFF; // custom atom CRC
+        bad[HEADER_SIZE + 2] = 7; // vendor atom count
+        bad[8] = 0; // eeplen
+        bad[4] = 0; // version
+        let (out, actions) = repair(&bad).unwrap();
+        assert_eq!(out, img);
+        assert!(Eeprom::verify(&out));
+        assert!(actions.contains(&RepairAction::Count {
+            index: 0,
+            old: 7,
+            new: 0
+        }));
+        assert!(
+            actions
+                .iter()
+                .any(|a| matches!(a, RepairAction::Crc { index: 2, .. }))
+        );
+        assert!(actions.contains(&RepairAction::Version { old: 0, new: 1 }));
+        assert!(actions.contains(&RepairAction::EepLen {
+            old: 0,
+            new: img.len() as u32
+        }));
+    }
+
+    #[test]
+    fn recovers_unclaimed_atoms_within_eeplen() {
+        let img = image();
+        let mut bad = img.clone();
+        bad[6] = 1;
+        let (out, actions) = repair(&bad).unwrap();
+        assert_eq!(out, img);
+        assert_eq!(
+            actions,
+            [
+                RepairAction::Recovered {
+                    index: 1,
+                    offset: 0x38
+                },
+                RepairAction::Recovered {
+                    index: 2,
+                    offset: 0x60
+                },
+                RepairAction::NumAtoms { old: 1, new: 3 },
+            ]
+        );
+    }
+
+    #[test]
+    fn drops_truncated_tail_and_keeps_unknown_atoms() {
+        let mut img = image();
+        // Retype the custom atom to an unknown type and fix its CRC by hand.
+        let custom = img.len() - (ATOM_HDR_SIZE + 9 + CRC_SIZE);
+        img[custom] = 0x42;
+        let crc = crate::utils::crc16::crc16(&img[custom..img.len() - 2]);
+        let end = img.len();
+        img[end - 2..].copy_from_slice(&crc.to_le_bytes());
+        let unknown = img.clone();
+        // Claim one more atom than exists, then chop it mid-payload.
+        img[6] = 4;
+        img.extend_from_slice(&[0x04, 0x00, 0x03, 0x00, 0x20, 0x00, 0x00, 0x00, 1, 2, 3]);
+        let (out, actions) = repair(&img).unwrap();
+        assert_eq!(out, unknown);
+        assert!(actions.contains(&RepairAction::DroppedTail {
+            offset: unknown.len(),
+            atoms: 1
+        }));
+    }
+
+    #[test]
+    fn salvage_survives_a_wiped_header() {
+        let img = image();
+        let mut bad = img.clone();
+        bad[..HEADER_SIZE].fill(0xFF);
+        bad.extend_from_slice(&[0xFF; 64]); // erased tail is not "lost"
+        let s = salvage(&bad);
+        assert_eq!(s.image, img);
+        assert_eq!(s.atoms.len(), 3);
+        assert_eq!(s.lost, vec![0..HEADER_SIZE]);
+    }
+
+    #[test]
+    fn salvage_skips_a_damaged_atom() {
+        let img = image();
+        let mut bad = img.clone();
+        bad[0x38 + 12] ^= 0x80; // a pin byte inside the GPIO atom
+        assert!(atoms(&bad).any(|a| !a.crc_valid()));
+        let s = salvage(&bad);
+        let types: Vec<u16> = s.atoms.iter().map(|a| a.atom_type).collect();
+        assert_eq!(types, [0x0001, 0x0004]);
+        assert_eq!(s.lost, vec![0x38..0x60]);
+        assert!(Eeprom::verify(&s.image));
+        assert!(Eeprom::from_bytes(&s.image).is_err()); // bank0 is gone
+    }
+}
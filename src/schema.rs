@@ -0,0 +1,402 @@
+//  _  _       _             _  _
+// | || |  ___| |_ _ __ ___ | || |
+// | || |_/ __| __| '_ ` _ \| || |_
+// |__   _\__ | |_| | | | | |__   _|
+//   |_| |___/\__|_|_|_| |_|  |_|
+//! # ehatrom — EEPROM HAT library for Raspberry Pi HATs
+//! - [Documentation (docs.rs)](https://docs.rs/ehatrom)
+//! - [GitHub](https://github.com/4stm4/ehatrom)
+//!
+//! ## Serde schema for the EEPROM model (`serde` feature)
+//!
+//! [`Eeprom`], [`VendorInfoAtom`] and [`GpioMapAtom`] serialize to a stable,
+//! human-oriented document rather than their wire bytes. In JSON:
+//!
+//! ```json
+//! {
+//!   "version": 1,
+//!   "vendor_info": {
+//!     "uuid": "12345678-9abc-def0-1234-56789abcdef0",
+//!     "product_id": 1,
+//!     "product_ver": 2,
+//!     "vendor": "ACME",
+//!     "product": "Test HAT"
+//!   },
+//!   "gpio_bank0": {
+//!     "drive": 5, "slew": 1, "hysteresis": 1, "back_power": 2,
+//!     "pins": [
+//!       { "gpio": 4, "func": "INPUT", "pull": "UP" },
+//!       { "gpio": 17, "func": "OUTPUT", "pull": "DEFAULT" }
+//!     ]
+//!   },
+//!   "gpio_bank1": null,
+//!   "current_supply": 1500,
+//!   "dt_blob": "d00dfeed...",
+//!   "custom_atoms": ["73657269616c3a31"]
+//! }
+//! ```
+//!
+//! - The UUID uses the same text form as the `product_uuid` settings directive.
+//! - GPIO `flags`/`power` are split into their `drive`/`slew`/`hysteresis`/
+//!   `back_power` fields; only pins the board uses are listed, keyed by BCM
+//!   GPIO number (bank1 pins are numbered 28..45). A pin byte that does not
+//!   decode cleanly (reserved bits set, or non-zero without the "used" flag)
+//!   is kept as `{ "gpio": n, "raw": byte }` so no information is lost.
+//! - Device-tree and custom atom payloads are lowercase hex strings. A DT atom
+//!   that names an overlay (HAT+, see [`DtAtom`](crate::DtAtom)) is written as
+//!   `"dt_overlay": "my-hat,addr=0x51"` instead of `dt_blob`.
+//! - `numatoms`/`eeplen` are derived and therefore not part of the document.
+//!
+//! Optional fields may be omitted on input; `version` defaults to
+//! [`FORMAT_VERSION`](crate::FORMAT_VERSION).
+
+use crate::settings::{format_uuid, parse_uuid};
+use crate::utils::hex;
+use crate::{
+    DtAtom, Eeprom, EepromHeader, FORMAT_VERSION, GPIO_COUNT, GPIO_COUNT_BANK1, GpioMapAtom,
+    PinFunc, PinPull, VendorInfoAtom, decode_pin, encode_pin,
+};
+use alloc::{string::String, vec::Vec};
+use serde::{Deserialize, Deserializer, Serialize, Serializer, de::Error as _};
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PinDoc {
+    gpio: u8,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    func: Option<PinFunc>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pull: Option<PinPull>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    raw: Option<u8>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct GpioBankDoc {
+    #[serde(default)]
+    drive: u8,
+    #[serde(default)]
+    slew: u8,
+    #[serde(default)]
+    hysteresis: u8,
+    #[serde(default)]
+    back_power: u8,
+    #[serde(default)]
+    pins: Vec<PinDoc>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct VendorDoc {
+    uuid: String,
+    product_id: u16,
+    product_ver: u16,
+    vendor: String,
+    product: String,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct EepromDoc {
+    #[serde(default = "default_version")]
+    version: u8,
+    vendor_info: VendorDoc,
+    gpio_bank0: GpioBankDoc,
+    #[serde(default)]
+    gpio_bank1: Option<GpioBankDoc>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    current_supply: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    dt_blob: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    dt_overlay: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    custom_atoms: Vec<String>,
+}
+
+fn default_version() -> u8 {
+    FORMAT_VERSION
+}
+
+/// Returns the used prefix of a fixed string buffer as text.
+fn buf_str(buf: &[u8; 16]) -> String {
+    let len = buf.iter().position(|&b| b == 0).unwrap_or(buf.len());
+    String::from_utf8_lossy(&buf[..len]).into_owned()
+}
+
+fn str_buf(s: &str, field: &'static str) -> Result<[u8; 16], &'static str> {
+    let bytes = s.as_bytes();
+    if bytes.len() > 16 {
+        return Err(field);
+    }
+    let mut buf = [0u8; 16];
+    buf[..bytes.len()].copy_from_slice(bytes);
+    Ok(buf)
+}
+
+impl VendorDoc {
+    fn from_atom(v: &VendorInfoAtom) -> Self {
+        VendorDoc {
+            uuid: format_uuid(&v.uuid),
+            product_id: v.product_id,
+            product_ver: v.product_ver,
+            vendor: buf_str(&v.vendor),
+            product: buf_str(&v.product),
+        }
+    }
+
+    pub(crate) fn into_atom(self) -> Result<VendorInfoAtom, &'static str> {
+        Ok(VendorInfoAtom {
+            uuid: parse_uuid(&self.uuid).ok_or("vendor_info.uuid: invalid UUID")?,
+            product_id: self.product_id,
+            product_ver: self.product_ver,
+            vendor: str_buf(&self.vendor, "vendor_info.vendor: longer than 16 bytes")?,
+            product: str_buf(&self.product, "vendor_info.product: longer than 16 bytes")?,
+        })
+    }
+}
+
+impl GpioBankDoc {
+    /// `base` is the BCM number of the bank's first pin; `len` its pin count.
+    fn from_atom(g: &GpioMapAtom, base: u8, len: usize) -> Self {
+        let pins = g.pins[..len]
+            .iter()
+            .enumerate()
+            .filter(|&(_, &byte)| byte != 0)
+            .map(|(i, &byte)| {
+                let gpio = base + i as u8;
+                let cfg = decode_pin(byte);
+                if cfg.used && encode_pin(cfg.func, cfg.pull) == byte {
+                    PinDoc {
+                        gpio,
+                        func: Some(cfg.func),
+                        pull: Some(cfg.pull),
+                        raw: None,
+                    }
+                } else {
+                    PinDoc {
+                        gpio,
+                        func: None,
+                        pull: None,
+                        raw: Some(byte),
+                    }
+                }
+            })
+            .collect();
+        let (drive, slew, hysteresis, back_power) = g.bank_flags();
+        GpioBankDoc {
+            drive,
+            slew,
+            hysteresis,
+            back_power,
+            pins,
+        }
+    }
+
+    fn into_atom(self, base: u8, len: usize) -> Result<GpioMapAtom, &'static str> {
+        let mut pins = [0u8; GPIO_COUNT];
+        for pin in self.pins {
+            let idx = pin
+                .gpio
+                .checked_sub(base)
+                .map(usize::from)
+                .filter(|&i| i < len)
+                .ok_or("gpio: pin number outside this bank")?;
+            pins[idx] = match (pin.raw, pin.func) {
+                (Some(raw), _) => raw,
+                (None, Some(func)) => encode_pin(func, pin.pull.unwrap_or(PinPull::Default)),
+                (None, None) => return Err("gpio: pin needs either `func` or `raw`"),
+            };
+        }
+        let mut atom = GpioMapAtom {
+            flags: 0,
+            power: 0,
+            pins,
+        };
+        atom.set_bank_flags(self.drive, self.slew, self.hysteresis, self.back_power)?;
+        Ok(atom)
+    }
+}
+
+impl EepromDoc {
+    fn from_eeprom(e: &Eeprom) -> Self {
+        // An overlay name stored without a trailing NUL reads back unchanged
+        // as text; anything else stays a hex blob.
+        let overlay = match (e.dt_atom(), e.dt_blob.as_deref()) {
+            (Some(DtAtom::Overlay { .. }), Some(bytes)) if !bytes.ends_with(&[0]) => {
+                core::str::from_utf8(bytes).ok().map(String::from)
+            }
+            _ => None,
+        };
+        EepromDoc {
+            version: e.header.version,
+            vendor_info: VendorDoc::from_atom(&e.vendor_info),
+            gpio_bank0: GpioBankDoc::from_atom(&e.gpio_map_bank0, 0, GPIO_COUNT),
+            gpio_bank1: e
+                .gpio_map_bank1
+                .as_ref()
+                .map(|g| GpioBankDoc::from_atom(g, GPIO_COUNT as u8, GPIO_COUNT_BANK1)),
+            current_supply: e.power_supply,
+            dt_blob: match overlay {
+                Some(_) => None,
+                None => e.dt_blob.as_deref().map(hex::encode),
+            },
+            dt_overlay: overlay,
+            custom_atoms: e.custom_atoms.iter().map(|d| hex::encode(d)).collect(),
+        }
+    }
+
+    fn into_eeprom(self) -> Result<Eeprom, &'static str> {
+        let custom_atoms = self
+            .custom_atoms
+            .iter()
+            .map(|s| hex::decode(s).ok_or("custom_atoms: invalid hex"))
+            .collect::<Result<Vec<_>, _>>()?;
+        let dt_blob = match (self.dt_blob, self.dt_overlay) {
+            (Some(s), None) => Some(hex::decode(&s).ok_or("dt_blob: invalid hex")?),
+            (None, Some(spec)) => {
+                DtAtom::overlay(&spec).ok_or("dt_overlay: invalid overlay name or parameters")?;
+                Some(spec.into_bytes())
+            }
+            (None, None) => None,
+            (Some(_), Some(_)) => return Err("set only one of dt_blob and dt_overlay"),
+        };
+        let gpio_map_bank1 = match self.gpio_bank1 {
+            Some(b) => Some(b.into_atom(GPIO_COUNT as u8, GPIO_COUNT_BANK1)?),
+            None => None,
+        };
+        let mut eeprom = Eeprom {
+            header: EepromHeader::new(),
+            vendor_info: self.vendor_info.into_atom()?,
+            gpio_map_bank0: self.gpio_bank0.into_atom(0, GPIO_COUNT)?,
+            dt_blob,
+            gpio_map_bank1,
+            power_supply: self.current_supply,
+            custom_atoms,
+        };
+        eeprom.set_version(self.version);
+        eeprom.update_header();
+        Ok(eeprom)
+    }
+}
+
+impl Serialize for Eeprom {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        EepromDoc::from_eeprom(self).serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Eeprom {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        EepromDoc::deserialize(deserializer)?
+            .into_eeprom()
+            .map_err(D::Error::custom)
+    }
+}
+
+impl Serialize for VendorInfoAtom {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        VendorDoc::from_atom(self).serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for VendorInfoAtom {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        VendorDoc::deserialize(deserializer)?
+            .into_atom()
+            .map_err(D::Error::custom)
+    }
+}
+
+/// A standalone GPIO map numbers its pins from 0; an [`Eeprom`]'s bank1 map
+/// is numbered from 28 instead.
+impl Serialize for GpioMapAtom {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        GpioBankDoc::from_atom(self, 0, GPIO_COUNT).serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for GpioMapAtom {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        GpioBankDoc::deserialize(deserializer)?
+            .into_atom(0, GPIO_COUNT)
+            .map_err(D::Error::custom)
+    }
+}
+
+#[cfg(all(test, feature = "json"))]
+mod tests {
+    use super::*;
+    use crate::parse_settings;
+    use alloc::string::ToString;
+
+    const SETTINGS: &str = "\
+product_uuid 12345678-9abc-def0-1234-56789abcdef0
+product_id 0x0001
+product_ver 0x0002
+vendor \"ACME\"
+product \"Test HAT\"
+current_supply 1500
+gpio_drive 5
+back_power 2
+setgpio 4 INPUT UP
+setgpio 17 OUTPUT DEFAULT
+setgpio 40 ALT0 DOWN
+";
+
+    #[test]
+    fn json_roundtrip_is_byte_identical() {
+        let mut eeprom = parse_settings(SETTINGS).unwrap();
+        eeprom.add_dt_blob(alloc::vec![0xD0, 0x0D, 0xFE, 0xED]);
+        eeprom.add_custom_atom(b"serial:1".to_vec());
+        eeprom.gpio_map_bank0.pins[5] = 0x01; // not a clean setgpio byte
+
+        let json = serde_json::to_string_pretty(&eeprom).unwrap();
+        assert!(json.contains("\"uuid\": \"12345678-9abc-def0-1234-56789abcdef0\""));
+        assert!(json.contains("\"func\": \"OUTPUT\""));
+        assert!(json.contains("\"gpio\": 40"));
+        assert!(json.contains("\"raw\": 1"));
+        assert!(json.contains("\"dt_blob\": \"d00dfeed\""));
+
+        let back: Eeprom = serde_json::from_str(&json).unwrap();
+        assert_eq!(back.serialize(), eeprom.serialize());
+    }
+
+    #[test]
+    fn dt_overlay_roundtrips_as_text() {
+        let mut eeprom = parse_settings(SETTINGS).unwrap();
+        eeprom.set_dt_overlay("my-hat,addr=0x51").unwrap();
+        let json = serde_json::to_string(&eeprom).unwrap();
+        assert!(json.contains("\"dt_overlay\":\"my-hat,addr=0x51\""));
+        assert!(!json.contains("dt_blob"));
+        let back: Eeprom = serde_json::from_str(&json).unwrap();
+        assert_eq!(back.serialize(), eeprom.serialize());
+    }
+
+    #[test]
+    fn rejects_pin_outside_bank() {
+        let json = r#"{
+            "vendor_info": { "uuid": "00000000-0000-0000-0000-000000000000",
+                             "product_id": 1, "product_ver": 1,
+                             "vendor": "V", "product": "P" },
+            "gpio_bank0": { "pins": [ { "gpio": 30, "func": "INPUT" } ] }
+        }"#;
+        let err = serde_json::from_str::<Eeprom>(json).unwrap_err();
+        assert!(err.to_string().contains("outside this bank"));
+
+        // Bank flags get the same ranges as TOML boards and `edit`.
+        for (field, reason) in [
+            ("\"drive\": 9", "GPIO drive out of range (0..8)"),
+            ("\"slew\": 3", "GPIO slew out of range (0..2)"),
+            ("\"back_power\": 3", "GPIO back_power out of range (0..2)"),
+        ] {
+            let bad = json.replace(
+                "\"pins\": [ { \"gpio\": 30",
+                &alloc::format!("{field}, \"pins\": [ {{ \"gpio\": 3"),
+            );
+            let err = serde_json::from_str::<Eeprom>(&bad).unwrap_err();
+            assert!(err.to_string().contains(reason), "{err}");
+        }
+    }
+}
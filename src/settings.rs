@@ -20,28 +20,56 @@
 //! - `current_supply <mA>` — power-supply atom (`0x0006`)
 //! - `gpio_drive`, `gpio_slew`, `gpio_hysteresis`, `back_power` (bank0 flags)
 //! - `bank1_gpio_drive`, `bank1_gpio_slew`, `bank1_gpio_hysteresis` (bank1 flags)
-//! - `setgpio <pin> <FUNC> <PULL>` — pins 0..27 go to bank0, 28..45 to bank1
+//! - `setgpio <pin> <FUNC> [PULL]` — pins 0..27 go to bank0, 28..45 to bank1;
+//!   `FUNC` is `INPUT`, `OUTPUT`, `ALT0`..`ALT5` or a function name such as
+//!   `UART0_TXD`, looked up in the table of the current `soc`. `PULL`
+//!   defaults to `DEFAULT`
+//! - `soc <name>` — [`Soc`] whose function names later `setgpio` lines use:
+//!   `bcm2835` (the default), `bcm2711` or `rp1`
+//! - `dt_blob "<name>"`, `dtoverlay <name>[,param[=value]]...` — HAT+ style DT
+//!   atom naming an overlay for the firmware to load, instead of a blob (see
+//!   [`DtAtom`](crate::DtAtom))
+//! - `dt_source <file.dts>` — overlay source, compiled with
+//!   [`compile_dts`](crate::dts::compile_dts) into the DT blob atom
+//! - `dt_file <file>` — the DT atom payload as-is (e.g. a compiled `.dtb`)
+//! - `custom_file <file>` — appends a custom atom with the file's contents;
+//!   repeat for several, in order
 //!
-//! Lines beginning with `#` and blank lines are ignored, as are unknown
-//! directives (matching `eepmake`'s lenient behaviour). Device-tree and custom
-//! file includes are **not** handled (they reference external files); add those
-//! atoms via the [`Eeprom`](crate::Eeprom) API after parsing.
+//! The three file directives are only available through
+//! [`parse_settings_with_loader`]. Lines beginning with `#` and blank lines are
+//! ignored, as are unknown directives (matching `eepmake`'s lenient
+//! behaviour).
 
-use crate::{Eeprom, EepromHeader, GpioMapAtom, PinFunc, PinPull, VendorInfoAtom, encode_pin};
-use alloc::vec::Vec;
+use crate::dts::{DtsError, compile_dts};
+use crate::soc::Soc;
+use crate::{
+    DtAtom, Eeprom, EepromHeader, GpioMapAtom, PinFunc, PinPull, VendorInfoAtom, encode_pin,
+};
+use alloc::{format, string::String, vec::Vec};
 
 /// Error returned by [`parse_settings`], carrying the 1-based line number.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+///
+/// Non-exhaustive so that more detail can be attached without breaking
+/// callers again.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
 pub struct SettingsError {
     /// 1-based line number where parsing failed.
     pub line: usize,
     /// Human-readable reason.
     pub reason: &'static str,
+    /// For a `dt_source` that does not compile, the compiler's error, with
+    /// the line in the overlay source.
+    pub dts: Option<DtsError>,
 }
 
 impl core::fmt::Display for SettingsError {
     fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
-        write!(f, "settings error on line {}: {}", self.line, self.reason)
+        write!(f, "settings error on line {}: {}", self.line, self.reason)?;
+        match &self.dts {
+            Some(e) => write!(f, " ({e})"),
+            None => Ok(()),
+        }
     }
 }
 
@@ -49,7 +77,19 @@ impl core::fmt::Display for SettingsError {
 impl std::error::Error for SettingsError {}
 
 /// Parses `eepmake`-style settings text into an [`Eeprom`].
+///
+/// Directives that reference files (`dt_source`, `dt_file`, `custom_file`)
+/// are rejected; use [`parse_settings_with_loader`] for those.
 pub fn parse_settings(input: &str) -> Result<Eeprom, SettingsError> {
+    parse_settings_with_loader(input, |_| Err("file directives need a loader"))
+}
+
+/// Like [`parse_settings`], but resolves file directives through `loader`,
+/// which maps the path as written in the settings text to the file contents.
+pub fn parse_settings_with_loader<F>(input: &str, mut loader: F) -> Result<Eeprom, SettingsError>
+where
+    F: FnMut(&str) -> Result<Vec<u8>, &'static str>,
+{
     let mut vendor_info = VendorInfoAtom {
         uuid: [0u8; 16],
         product_id: 0,
@@ -64,10 +104,17 @@ pub fn parse_settings(input: &str) -> Result<Eeprom, SettingsError> {
     };
     let mut bank1: Option<GpioMapAtom> = None;
     let mut power_supply: Option<u32> = None;
+    let mut dt_blob: Option<Vec<u8>> = None;
+    let mut custom_atoms: Vec<Vec<u8>> = Vec::new();
+    let mut soc = Soc::Bcm2835;
 
     for (idx, raw) in input.lines().enumerate() {
         let line = idx + 1;
-        let err = |reason| SettingsError { line, reason };
+        let err = |reason| SettingsError {
+            line,
+            reason,
+            dts: None,
+        };
 
         let trimmed = raw.trim();
         if trimmed.is_empty() || trimmed.starts_with('#') {
@@ -132,6 +179,10 @@ pub fn parse_settings(input: &str) -> Result<Eeprom, SettingsError> {
                 let v = parse_nibble(rest, 2).ok_or(err("invalid bank1_gpio_hysteresis"))?;
                 bank1_mut(&mut bank1).flags |= v << 6;
             }
+            "soc" => {
+                soc = Soc::from_name(rest)
+                    .ok_or(err("unknown soc (expected bcm2835, bcm2711 or rp1)"))?;
+            }
             "setgpio" => {
                 let mut it = rest.split_whitespace();
                 let pin: usize = it
@@ -140,12 +191,12 @@ pub fn parse_settings(input: &str) -> Result<Eeprom, SettingsError> {
                     .ok_or(err("setgpio: invalid pin number"))?;
                 let func = it
                     .next()
-                    .and_then(parse_func)
+                    .and_then(|tok| parse_func(tok).or_else(|| soc.alt_for(pin, tok)))
                     .ok_or(err("setgpio: invalid function"))?;
-                let pull = it
-                    .next()
-                    .and_then(parse_pull)
-                    .ok_or(err("setgpio: invalid pull"))?;
+                let pull = match it.next() {
+                    Some(tok) => parse_pull(tok).ok_or(err("setgpio: invalid pull"))?,
+                    None => PinPull::Default,
+                };
                 let byte = encode_pin(func, pull);
                 if pin < 28 {
                     gpio0.pins[pin] = byte;
@@ -155,6 +206,37 @@ pub fn parse_settings(input: &str) -> Result<Eeprom, SettingsError> {
                     return Err(err("setgpio: pin number out of range (0..45)"));
                 }
             }
+            "dt_blob" | "dtoverlay" => {
+                let spec = unquote(rest);
+                DtAtom::overlay(spec).ok_or(err("invalid overlay name or parameters"))?;
+                dt_blob = Some(spec.as_bytes().to_vec());
+            }
+            "dt_file" | "custom_file" => {
+                let path = unquote(rest);
+                if path.is_empty() {
+                    return Err(err("missing file name"));
+                }
+                let data = loader(path).map_err(err)?;
+                if directive == "dt_file" {
+                    dt_blob = Some(data);
+                } else {
+                    custom_atoms.push(data);
+                }
+            }
+            "dt_source" => {
+                let path = unquote(rest);
+                if path.is_empty() {
+                    return Err(err("dt_source: missing file name"));
+                }
+                let source = loader(path).map_err(err)?;
+                let source =
+                    core::str::from_utf8(&source).map_err(|_| err("dt_source: not UTF-8 text"))?;
+                let blob = compile_dts(source).map_err(|e| SettingsError {
+                    dts: Some(e),
+                    ..err("dt_source: overlay does not compile")
+                })?;
+                dt_blob = Some(blob);
+            }
             _ => {
                 // Unknown directive: ignore, like eepmake.
             }
@@ -165,10 +247,10 @@ pub fn parse_settings(input: &str) -> Result<Eeprom, SettingsError> {
         header: EepromHeader::new(),
         vendor_info,
         gpio_map_bank0: gpio0,
-        dt_blob: None,
+        dt_blob,
         gpio_map_bank1: bank1,
         power_supply,
-        custom_atoms: Vec::new(),
+        custom_atoms,
     };
     eeprom.update_header();
     Ok(eeprom)
@@ -184,7 +266,7 @@ fn bank1_mut(slot: &mut Option<GpioMapAtom>) -> &mut GpioMapAtom {
 }
 
 /// Copies a string into a fixed 16-byte buffer (truncating, zero-padded).
-fn copy_str(buf: &mut [u8; 16], s: &str) {
+pub(crate) fn copy_str(buf: &mut [u8; 16], s: &str) {
     *buf = [0u8; 16];
     let bytes = s.as_bytes();
     let n = bytes.len().min(16);
@@ -192,7 +274,7 @@ fn copy_str(buf: &mut [u8; 16], s: &str) {
 }
 
 /// Strips a single pair of surrounding double quotes, if present.
-fn unquote(s: &str) -> &str {
+pub(crate) fn unquote(s: &str) -> &str {
     let s = s.trim();
     if s.len() >= 2 && s.starts_with('"') && s.ends_with('"') {
         &s[1..s.len() - 1]
@@ -202,21 +284,21 @@ fn unquote(s: &str) -> &str {
 }
 
 /// Parses the first token as a hexadecimal `u16`.
-fn parse_hex_u16(s: &str) -> Option<u16> {
+pub(crate) fn parse_hex_u16(s: &str) -> Option<u16> {
     let tok = s.split_whitespace().next()?;
     let tok = tok.strip_prefix("0x").unwrap_or(tok);
     u16::from_str_radix(tok, 16).ok()
 }
 
 /// Parses the first token as a single hex nibble, rejecting values above `max`.
-fn parse_nibble(s: &str, max: u8) -> Option<u8> {
+pub(crate) fn parse_nibble(s: &str, max: u8) -> Option<u8> {
     let tok = s.split_whitespace().next()?;
     let tok = tok.strip_prefix("0x").unwrap_or(tok);
     let v = u8::from_str_radix(tok, 16).ok()?;
     if v > max { None } else { Some(v) }
 }
 
-fn parse_func(tok: &str) -> Option<PinFunc> {
+pub(crate) fn parse_func(tok: &str) -> Option<PinFunc> {
     Some(match tok {
         "INPUT" => PinFunc::Input,
         "OUTPUT" => PinFunc::Output,
@@ -230,7 +312,7 @@ fn parse_func(tok: &str) -> Option<PinFunc> {
     })
 }
 
-fn parse_pull(tok: &str) -> Option<PinPull> {
+pub(crate) fn parse_pull(tok: &str) -> Option<PinPull> {
     Some(match tok {
         "DEFAULT" => PinPull::Default,
         "UP" => PinPull::Up,
@@ -242,7 +324,7 @@ fn parse_pull(tok: &str) -> Option<PinPull> {
 
 /// Parses `AAAAAAAA-BBBB-CCCC-DDDD-EEEEFFFFFFFF` into the 16-byte vendor UUID,
 /// packing it into `serial[4]` (per-word little-endian) exactly as `eepmake`.
-fn parse_uuid(s: &str) -> Option<[u8; 16]> {
+pub(crate) fn parse_uuid(s: &str) -> Option<[u8; 16]> {
     let tok = s.split_whitespace().next()?;
     let hex: Vec<u8> = tok.bytes().filter(|&b| b != b'-').collect();
     if hex.len() != 32 {
@@ -269,9 +351,33 @@ fn parse_uuid(s: &str) -> Option<[u8; 16]> {
     Some(out)
 }
 
+/// Formats a packed vendor UUID back into `AAAAAAAA-BBBB-CCCC-DDDD-EEEEFFFFFFFF`
+/// (the inverse of [`parse_uuid`]).
+pub(crate) fn format_uuid(uuid: &[u8; 16]) -> String {
+    let word = |i: usize| {
+        u32::from_le_bytes([
+            uuid[i * 4],
+            uuid[i * 4 + 1],
+            uuid[i * 4 + 2],
+            uuid[i * 4 + 3],
+        ])
+    };
+    let (s0, s1, s2, s3) = (word(0), word(1), word(2), word(3));
+    format!(
+        "{:08x}-{:04x}-{:04x}-{:04x}-{:04x}{:08x}",
+        s3,
+        s2 >> 16,
+        s2 & 0xFFFF,
+        s1 >> 16,
+        s1 & 0xFFFF,
+        s0
+    )
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use alloc::string::ToString;
 
     #[test]
     fn uuid_packing_matches_eepmake() {
@@ -283,6 +389,7 @@ mod tests {
                 0x34, 0x12,
             ]
         );
+        assert_eq!(format_uuid(&u), "12345678-9abc-def0-1234-56789abcdef0");
     }
 
     #[test]
@@ -344,4 +451,79 @@ setgpio 40 ALT0 DOWN
         let e = parse_settings(text).unwrap_err();
         assert_eq!(e.line, 2);
     }
+
+    #[test]
+    fn dt_source_compiles_through_the_loader() {
+        let text = "product_id 0x1\ndt_source \"hat.dts\"\n";
+        let eeprom = parse_settings_with_loader(text, |path| {
+            assert_eq!(path, "hat.dts");
+            Ok(b"/dts-v1/;\n/plugin/;\n&i2c1 { status = \"okay\"; };\n".to_vec())
+        })
+        .unwrap();
+        let blob = eeprom.dt_blob.as_deref().unwrap();
+        assert!(crate::fdt::Fdt::parse(blob).is_ok());
+
+        assert_eq!(parse_settings(text).unwrap_err().line, 2);
+        let bad = parse_settings_with_loader(text, |_| Ok(b"/ {\n  a = <;\n};".to_vec()));
+        let bad = bad.unwrap_err();
+        assert_eq!(
+            (bad.line, bad.reason),
+            (2, "dt_source: overlay does not compile")
+        );
+        assert_eq!(bad.dts.as_ref().map(|e| e.line), Some(2));
+        assert!(
+            bad.to_string()
+                .ends_with("does not compile (dts error on line 2: expected a cell value)")
+        );
+    }
+
+    #[test]
+    fn dt_overlay_directives_name_an_overlay() {
+        let eeprom = parse_settings("dtoverlay my-hat,addr=0x51,irq\n").unwrap();
+        let bytes = eeprom.serialize();
+        let parsed = Eeprom::from_bytes(&bytes).unwrap();
+        let atom = parsed.dt_atom().unwrap();
+        assert_eq!(
+            atom,
+            DtAtom::Overlay {
+                name: "my-hat",
+                params: "addr=0x51,irq"
+            }
+        );
+        let params: Vec<_> = atom.params().collect();
+        assert_eq!(params, [("addr", Some("0x51")), ("irq", None)]);
+
+        let eeprom = parse_settings("dt_blob \"my-hat\"\n").unwrap();
+        assert_eq!(eeprom.dt_blob.as_deref(), Some(&b"my-hat"[..]));
+        assert_eq!(
+            DtAtom::parse(b"my-hat\0"),
+            DtAtom::Overlay {
+                name: "my-hat",
+                params: ""
+            }
+        );
+        assert!(matches!(
+            DtAtom::parse(&[0xD0, 0x0D, 0xFE, 0xED]),
+            DtAtom::Blob(_)
+        ));
+        assert_eq!(parse_settings("dtoverlay my hat\n").unwrap_err().line, 1);
+    }
+
+    #[test]
+    fn setgpio_resolves_function_names_for_the_soc() {
+        let text = "setgpio 14 UART0_TXD\nsoc rp1\nsetgpio 15 uart0_rxd UP\n";
+        let eeprom = parse_settings(text).unwrap();
+        assert_eq!(
+            eeprom.gpio_map_bank0.pins[14],
+            encode_pin(PinFunc::Alt0, PinPull::Default)
+        );
+        assert_eq!(
+            eeprom.gpio_map_bank0.pins[15],
+            encode_pin(PinFunc::Alt4, PinPull::Up)
+        );
+
+        let err = parse_settings("setgpio 15 UART0_TXD DEFAULT\n").unwrap_err();
+        assert_eq!(err.reason, "setgpio: invalid function");
+        assert_eq!(parse_settings("soc bcm9999\n").unwrap_err().line, 1);
+    }
 }
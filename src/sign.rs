@@ -0,0 +1,274 @@
+//  _  _       _             _  _
+// | || |  ___| |_ _ __ ___ | || |
+// | || |_/ __| __| '_ ` _ \| || |_
+// |__   _\__ | |_| | | | | |__   _|
+//   |_| |___/\__|_|_|_| |_|  |_|
+//! # ehatrom — EEPROM HAT library for Raspberry Pi HATs
+//! - [Documentation (docs.rs)](https://docs.rs/ehatrom)
+//! - [GitHub](https://github.com/4stm4/ehatrom)
+//!
+//! ## Ed25519-signed images (`sign` feature)
+//!
+//! [`sign_image`] appends a signature custom atom: [`SIGNATURE_MAGIC`]
+//! followed by a 64-byte Ed25519 signature. The signed message is every byte
+//! of the preceding atoms, headers and CRCs included, from the end of the
+//! 12-byte EEPROM header to the signature atom. The header is left out
+//! because appending the atom changes `numatoms` and `eeplen`.
+//!
+//! Only an embedded [`digest`](crate::digest) atom may follow the signature;
+//! [`sign_image`] re-embeds one that was there. Any other atom after the
+//! signature makes [`verify_image`] fail, so unsigned data cannot be
+//! appended to a signed image.
+//!
+//! Verification does not allocate and works in `no_std` firmware:
+//!
+//! ```
+//! # #[cfg(feature = "alloc")] {
+//! use ehatrom::sign::{SigningKey, sign_image, verify_image};
+//!
+//! let key = SigningKey::from_bytes(&[7; 32]);
+//! let image = ehatrom::parse_settings("product_id 0x0001\n").unwrap().serialize();
+//! let signed = sign_image(&image, &key).unwrap();
+//! assert_eq!(verify_image(&signed, &key.verifying_key()), Ok(()));
+//! # }
+//! ```
+
+use crate::digest::{self, ImageDigest};
+use crate::{AtomType, HEADER_SIZE, ValidationError, atoms};
+use core::fmt;
+use ed25519_dalek::Signature;
+pub use ed25519_dalek::{
+    PUBLIC_KEY_LENGTH, SECRET_KEY_LENGTH, SIGNATURE_LENGTH, SigningKey, VerifyingKey,
+};
+
+/// First bytes of a signature atom payload.
+pub const SIGNATURE_MAGIC: [u8; 4] = *b"EHS1";
+/// Payload length of a signature atom.
+pub const SIGNATURE_ATOM_LEN: usize = SIGNATURE_MAGIC.len() + SIGNATURE_LENGTH;
+
+/// Why an image cannot be signed or does not verify.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SignError {
+    /// The image is malformed (header, `eeplen` or atom structure).
+    Image(&'static str),
+    /// An atom fails its CRC-16, so the image is not signed.
+    Invalid(ValidationError),
+    /// The image has no signature atom.
+    Unsigned,
+    /// Atoms other than a digest follow the signature.
+    TrailingAtoms,
+    /// The signature does not match the atoms and key.
+    BadSignature,
+}
+
+impl fmt::Display for SignError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SignError::Image(reason) => write!(f, "{reason}"),
+            SignError::Invalid(e) => write!(f, "{e}"),
+            SignError::Unsigned => write!(f, "no signature atom"),
+            SignError::TrailingAtoms => write!(f, "unsigned atoms follow the signature"),
+            SignError::BadSignature => write!(f, "signature does not match"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for SignError {}
+
+/// A signature atom payload.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SignatureAtom(pub [u8; SIGNATURE_LENGTH]);
+
+impl SignatureAtom {
+    /// Parses a signature atom payload.
+    pub fn from_atom(data: &[u8]) -> Option<Self> {
+        let rest = data.strip_prefix(&SIGNATURE_MAGIC)?;
+        rest.try_into().ok().map(SignatureAtom)
+    }
+
+    /// The signature atom payload.
+    pub fn to_atom(&self) -> [u8; SIGNATURE_ATOM_LEN] {
+        let mut out = [0u8; SIGNATURE_ATOM_LEN];
+        out[..4].copy_from_slice(&SIGNATURE_MAGIC);
+        out[4..].copy_from_slice(&self.0);
+        out
+    }
+}
+
+impl fmt::Display for SignatureAtom {
+    /// `ed25519 ` and the first eight bytes in hex.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "ed25519 ")?;
+        for byte in &self.0[..8] {
+            write!(f, "{byte:02x}")?;
+        }
+        write!(f, "…")
+    }
+}
+
+fn is_custom(kind: AtomType) -> bool {
+    kind == AtomType::Custom
+}
+
+/// The signed bytes of `data` and the signature over them.
+pub fn signed_message(data: &[u8]) -> Result<(&[u8], SignatureAtom), SignError> {
+    let data = digest::image_bytes(data).map_err(SignError::Image)?;
+    let mut found = None;
+    let mut trailing = false;
+    for (offset, atom) in atoms(data).with_offsets() {
+        let custom = is_custom(atom.kind());
+        if let Some(signature) = SignatureAtom::from_atom(atom.data).filter(|_| custom) {
+            found = Some((offset, signature));
+            trailing = false;
+        } else if found.is_some() && !(custom && ImageDigest::from_atom(atom.data).is_some()) {
+            trailing = true;
+        }
+    }
+    let (offset, signature) = found.ok_or(SignError::Unsigned)?;
+    if trailing {
+        return Err(SignError::TrailingAtoms);
+    }
+    Ok((&data[HEADER_SIZE..offset], signature))
+}
+
+/// Checks the signature atom of `data` against `key`.
+pub fn verify_image(data: &[u8], key: &VerifyingKey) -> Result<(), SignError> {
+    let (message, signature) = signed_message(data)?;
+    key.verify_strict(message, &Signature::from_bytes(&signature.0))
+        .map_err(|_| SignError::BadSignature)
+}
+
+/// Signs the atoms of `data` and appends the signature atom.
+///
+/// A trailing signature is replaced, and an embedded digest is dropped and
+/// re-embedded after the new signature. Atoms appended after an old
+/// signature are kept and covered by the new one.
+#[cfg(feature = "alloc")]
+pub fn sign_image(data: &[u8], key: &SigningKey) -> Result<alloc::vec::Vec<u8>, SignError> {
+    use ed25519_dalek::Signer;
+
+    let data = digest::image_bytes(data).map_err(SignError::Image)?;
+    crate::Eeprom::validate(data).map_err(SignError::Invalid)?;
+
+    // Drop a trailing digest, then a signature that is last or was only
+    // followed by that digest. Every other atom is kept and signed.
+    let mut kept: alloc::vec::Vec<(usize, bool, bool)> = atoms(data)
+        .with_offsets()
+        .map(|(offset, atom)| {
+            let custom = is_custom(atom.kind());
+            let end = offset + crate::ATOM_HDR_SIZE + atom.data.len() + crate::CRC_SIZE;
+            let signature = custom && SignatureAtom::from_atom(atom.data).is_some();
+            let digest = custom && ImageDigest::from_atom(atom.data).is_some();
+            (end, signature, digest)
+        })
+        .collect();
+    let had_digest = kept.last().is_some_and(|&(.., digest)| digest);
+    if had_digest {
+        kept.pop();
+    }
+    if kept.last().is_some_and(|&(_, signature, _)| signature) {
+        kept.pop();
+    }
+    let end = kept.last().map_or(HEADER_SIZE, |&(end, ..)| end);
+    let numatoms = kept.len() as u16;
+    let next = numatoms
+        .checked_add(1)
+        .ok_or(SignError::Image("too many atoms"))?;
+
+    let signature = SignatureAtom(key.sign(&data[HEADER_SIZE..end]).to_bytes());
+    let payload = signature.to_atom();
+    let total = end + crate::ATOM_HDR_SIZE + payload.len() + crate::CRC_SIZE;
+    let mut out = alloc::vec![0u8; total];
+    out[..end].copy_from_slice(&data[..end]);
+    out[6..8].copy_from_slice(&next.to_le_bytes());
+    out[8..12].copy_from_slice(&(total as u32).to_le_bytes());
+    let (mut offset, mut count) = (end, numatoms);
+    crate::write_atom(
+        &mut out,
+        &mut offset,
+        &mut count,
+        AtomType::Custom as u16,
+        &payload,
+    )
+    .map_err(|_| SignError::Image("signature atom does not fit"))?;
+    if had_digest {
+        out = digest::embed_digest(&out).map_err(SignError::Image)?.0;
+    }
+    Ok(out)
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    use super::*;
+    use crate::digest::{DigestCheck, check_embedded, embed_digest};
+    use crate::{Eeprom, parse_settings};
+    use alloc::vec::Vec;
+
+    fn image() -> Vec<u8> {
+        let mut eeprom = parse_settings("product_id 0x0001\nvendor \"ACME\"\n").unwrap();
+        eeprom.add_custom_atom(b"serial=42".to_vec());
+        eeprom.serialize()
+    }
+
+    #[test]
+    fn signed_image_verifies_only_with_its_key() {
+        let key = SigningKey::from_bytes(&[1; 32]);
+        let signed = sign_image(&image(), &key).unwrap();
+        assert!(Eeprom::verify(&signed));
+        assert_eq!(verify_image(&signed, &key.verifying_key()), Ok(()));
+
+        let other = SigningKey::from_bytes(&[2; 32]).verifying_key();
+        assert_eq!(verify_image(&signed, &other), Err(SignError::BadSignature));
+        assert_eq!(
+            verify_image(&image(), &key.verifying_key()),
+            Err(SignError::Unsigned)
+        );
+
+        // Changing a signed atom, or appending one, breaks verification.
+        let mut tampered = signed.clone();
+        tampered[HEADER_SIZE + 8 + 16] ^= 1;
+        assert_eq!(
+            verify_image(&tampered, &key.verifying_key()),
+            Err(SignError::BadSignature)
+        );
+        let mut appended = Eeprom::from_bytes(&signed).unwrap();
+        appended.add_custom_atom(b"clone".to_vec());
+        assert_eq!(
+            verify_image(&appended.serialize(), &key.verifying_key()),
+            Err(SignError::TrailingAtoms)
+        );
+    }
+
+    #[test]
+    fn resigning_replaces_the_signature_and_keeps_the_digest() {
+        let key = SigningKey::from_bytes(&[3; 32]);
+        let signed = sign_image(&image(), &key).unwrap();
+        let (hashed, _) = embed_digest(&signed).unwrap();
+        assert_eq!(verify_image(&hashed, &key.verifying_key()), Ok(()));
+
+        let resigned = sign_image(&hashed, &key).unwrap();
+        assert_eq!(resigned, hashed);
+        assert!(matches!(check_embedded(&resigned), DigestCheck::Valid(_)));
+        assert_eq!(Eeprom::from_bytes(&resigned).unwrap().custom_atoms.len(), 3);
+    }
+
+    #[test]
+    fn resigning_keeps_atoms_appended_after_the_signature() {
+        let key = SigningKey::from_bytes(&[4; 32]);
+        let mut appended = Eeprom::from_bytes(&sign_image(&image(), &key).unwrap()).unwrap();
+        appended.add_custom_atom(b"calibration-data".to_vec());
+        // A user atom shaped like a digest, followed by another atom.
+        let digest_like = ImageDigest::of(b"user").to_atom();
+        appended.add_custom_atom(digest_like.to_vec());
+        appended.add_custom_atom(b"tail".to_vec());
+
+        let resigned = sign_image(&appended.serialize(), &key).unwrap();
+        assert_eq!(verify_image(&resigned, &key.verifying_key()), Ok(()));
+        let custom = Eeprom::from_bytes(&resigned).unwrap().custom_atoms;
+        // serial, old signature, calibration, digest-like, tail, new signature
+        assert_eq!(custom.len(), 6);
+        assert_eq!(custom[2], b"calibration-data");
+        assert_eq!(custom[4], b"tail");
+    }
+}
@@ -0,0 +1,288 @@
+//  _  _       _             _  _
+// | || |  ___| |_ _ __ ___ | || |
+// | || |_/ __| __| '_ ` _ \| || |_
+// |__   _\__ | |_| | | | | |__   _|
+//   |_| |___/\__|_|_|_| |_|  |_|
+//! # ehatrom — EEPROM HAT library for Raspberry Pi HATs
+//! - [Documentation (docs.rs)](https://docs.rs/ehatrom)
+//! - [GitHub](https://github.com/4stm4/ehatrom)
+//!
+//! ## Alternate-function tables per SoC
+//!
+//! A pin byte only says `ALT0`…`ALT5`; what that selects depends on the GPIO
+//! and the SoC. [`Soc`] names the function behind each slot, so `dump` can
+//! print `ALT0 (UART0_TXD)` and `setgpio 14 UART0_TXD` works in settings
+//! files (see [`Soc::alt_for`]).
+//!
+//! Names follow the SoC datasheets, except that UARTs are spelled
+//! `UARTn_TXD`/`UARTn_RXD`/`UARTn_CTS`/`UARTn_RTS`, I2C buses `I2Cn_SDA`/
+//! `I2Cn_SCL` and the secondary memory interface `SMI_*` on every SoC.
+//!
+//! - [`Soc::Bcm2835`] covers the BCM2835/6/7 (Pi 1–3, Zero), GPIO 0–45.
+//! - [`Soc::Bcm2711`] is the Pi 4 / CM4, GPIO 0–45.
+//! - [`Soc::Rp1`] is the I/O controller of the BCM2712 (Pi 5), GPIO 0–27
+//!   (the header bank). RP1 has nine functions per pin; `ALT0`…`ALT5` map to
+//!   `a0`…`a5`, and `a6`…`a8` cannot be expressed in a HAT pin byte.
+
+use crate::PinFunc;
+
+/// A Raspberry Pi SoC with a known alternate-function table.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Soc {
+    /// BCM2835, BCM2836 and BCM2837.
+    Bcm2835,
+    /// BCM2711.
+    Bcm2711,
+    /// RP1, the BCM2712's I/O controller.
+    Rp1,
+}
+
+impl Soc {
+    /// Every SoC with a table.
+    pub const ALL: [Soc; 3] = [Soc::Bcm2835, Soc::Bcm2711, Soc::Rp1];
+
+    /// Parses `bcm2835` (or `bcm2836`/`bcm2837`), `bcm2711`, or `rp1`
+    /// (or `bcm2712`), ignoring case.
+    pub fn from_name(name: &str) -> Option<Soc> {
+        const NAMES: [(&str, Soc); 6] = [
+            ("bcm2835", Soc::Bcm2835),
+            ("bcm2836", Soc::Bcm2835),
+            ("bcm2837", Soc::Bcm2835),
+            ("bcm2711", Soc::Bcm2711),
+            ("bcm2712", Soc::Rp1),
+            ("rp1", Soc::Rp1),
+        ];
+        NAMES
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|&(_, soc)| soc)
+    }
+
+    /// Display name, e.g. `BCM2835`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Soc::Bcm2835 => "BCM2835",
+            Soc::Bcm2711 => "BCM2711",
+            Soc::Rp1 => "RP1",
+        }
+    }
+
+    fn table(self) -> &'static [[&'static str; 6]] {
+        match self {
+            Soc::Bcm2835 => &BCM2835,
+            Soc::Bcm2711 => &BCM2711,
+            Soc::Rp1 => &RP1,
+        }
+    }
+
+    /// Number of GPIOs the table covers, starting at GPIO 0.
+    pub fn gpio_count(self) -> usize {
+        self.table().len()
+    }
+
+    /// The function `func` selects on `gpio`, or `None` for `INPUT`/`OUTPUT`,
+    /// a reserved slot, or a GPIO past [`Soc::gpio_count`].
+    pub fn alt_function(self, gpio: usize, func: PinFunc) -> Option<&'static str> {
+        let name = self.table().get(gpio)?[alt_number(func)? as usize];
+        (!name.is_empty()).then_some(name)
+    }
+
+    /// The ALT setting that selects function `name` on `gpio`, ignoring case.
+    pub fn alt_for(self, gpio: usize, name: &str) -> Option<PinFunc> {
+        let row = self.table().get(gpio)?;
+        let alt = row
+            .iter()
+            .position(|f| !f.is_empty() && f.eq_ignore_ascii_case(name))?;
+        Some(ALTS[alt])
+    }
+}
+
+const ALTS: [PinFunc; 6] = [
+    PinFunc::Alt0,
+    PinFunc::Alt1,
+    PinFunc::Alt2,
+    PinFunc::Alt3,
+    PinFunc::Alt4,
+    PinFunc::Alt5,
+];
+
+/// The ALT number of `func`, if it is an alternate function.
+pub fn alt_number(func: PinFunc) -> Option<u8> {
+    ALTS.iter().position(|&f| f == func).map(|n| n as u8)
+}
+
+// ALT0..ALT5 per GPIO; "" marks a reserved or unassigned slot.
+
+/// BCM2835 ARM Peripherals, table 6-31.
+#[rustfmt::skip]
+const BCM2835: [[&str; 6]; 46] = [
+    ["I2C0_SDA", "SMI_SA5", "", "", "", ""],
+    ["I2C0_SCL", "SMI_SA4", "", "", "", ""],
+    ["I2C1_SDA", "SMI_SA3", "", "", "", ""],
+    ["I2C1_SCL", "SMI_SA2", "", "", "", ""],
+    ["GPCLK0", "SMI_SA1", "", "", "", "ARM_TDI"],
+    ["GPCLK1", "SMI_SA0", "", "", "", "ARM_TDO"],
+    ["GPCLK2", "SMI_SOE_N", "", "", "", "ARM_RTCK"],
+    ["SPI0_CE1_N", "SMI_SWE_N", "", "", "", ""],
+    ["SPI0_CE0_N", "SMI_SD0", "", "", "", ""],
+    ["SPI0_MISO", "SMI_SD1", "", "", "", ""],
+    ["SPI0_MOSI", "SMI_SD2", "", "", "", ""],
+    ["SPI0_SCLK", "SMI_SD3", "", "", "", ""],
+    ["PWM0", "SMI_SD4", "", "", "", "ARM_TMS"],
+    ["PWM1", "SMI_SD5", "", "", "", "ARM_TCK"],
+    ["UART0_TXD", "SMI_SD6", "", "", "", "UART1_TXD"],
+    ["UART0_RXD", "SMI_SD7", "", "", "", "UART1_RXD"],
+    ["", "SMI_SD8", "", "UART0_CTS", "SPI1_CE2_N", "UART1_CTS"],
+    ["", "SMI_SD9", "", "UART0_RTS", "SPI1_CE1_N", "UART1_RTS"],
+    ["PCM_CLK", "SMI_SD10", "", "BSCSL_SDA", "SPI1_CE0_N", "PWM0"],
+    ["PCM_FS", "SMI_SD11", "", "BSCSL_SCL", "SPI1_MISO", "PWM1"],
+    ["PCM_DIN", "SMI_SD12", "", "BSCSL_MISO", "SPI1_MOSI", "GPCLK0"],
+    ["PCM_DOUT", "SMI_SD13", "", "BSCSL_CE_N", "SPI1_SCLK", "GPCLK1"],
+    ["", "SMI_SD14", "", "SD1_CLK", "ARM_TRST", ""],
+    ["", "SMI_SD15", "", "SD1_CMD", "ARM_RTCK", ""],
+    ["", "SMI_SD16", "", "SD1_DAT0", "ARM_TDO", ""],
+    ["", "SMI_SD17", "", "SD1_DAT1", "ARM_TCK", ""],
+    ["", "", "", "SD1_DAT2", "ARM_TDI", ""],
+    ["", "", "", "SD1_DAT3", "ARM_TMS", ""],
+    ["I2C0_SDA", "SMI_SA5", "PCM_CLK", "", "", ""],
+    ["I2C0_SCL", "SMI_SA4", "PCM_FS", "", "", ""],
+    ["", "SMI_SA3", "PCM_DIN", "UART0_CTS", "", "UART1_CTS"],
+    ["", "SMI_SA2", "PCM_DOUT", "UART0_RTS", "", "UART1_RTS"],
+    ["GPCLK0", "SMI_SA1", "", "UART0_TXD", "", "UART1_TXD"],
+    ["", "SMI_SA0", "", "UART0_RXD", "", "UART1_RXD"],
+    ["GPCLK0", "SMI_SOE_N", "", "SD1_CLK", "", ""],
+    ["SPI0_CE1_N", "SMI_SWE_N", "", "SD1_CMD", "", ""],
+    ["SPI0_CE0_N", "SMI_SD0", "UART0_TXD", "SD1_DAT0", "", ""],
+    ["SPI0_MISO", "SMI_SD1", "UART0_RXD", "SD1_DAT1", "", ""],
+    ["SPI0_MOSI", "SMI_SD2", "UART0_RTS", "SD1_DAT2", "", ""],
+    ["SPI0_SCLK", "SMI_SD3", "UART0_CTS", "SD1_DAT3", "", ""],
+    ["PWM0", "SMI_SD4", "", "SD1_DAT4", "SPI2_MISO", "UART1_TXD"],
+    ["PWM1", "SMI_SD5", "", "SD1_DAT5", "SPI2_MOSI", "UART1_RXD"],
+    ["GPCLK1", "SMI_SD6", "", "SD1_DAT6", "SPI2_SCLK", "UART1_RTS"],
+    ["GPCLK2", "SMI_SD7", "", "SD1_DAT7", "SPI2_CE0_N", "UART1_CTS"],
+    ["GPCLK1", "I2C0_SDA", "I2C1_SDA", "", "SPI2_CE1_N", ""],
+    ["PWM1", "I2C0_SCL", "I2C1_SCL", "", "SPI2_CE2_N", ""],
+];
+
+/// BCM2711 ARM Peripherals, section 5.3.
+#[rustfmt::skip]
+const BCM2711: [[&str; 6]; 46] = [
+    ["I2C0_SDA", "SMI_SA5", "DPI_PCLK", "SPI3_CE0_N", "UART2_TXD", "I2C6_SDA"],
+    ["I2C0_SCL", "SMI_SA4", "DPI_DE", "SPI3_MISO", "UART2_RXD", "I2C6_SCL"],
+    ["I2C1_SDA", "SMI_SA3", "DPI_VSYNC", "SPI3_MOSI", "UART2_CTS", "I2C3_SDA"],
+    ["I2C1_SCL", "SMI_SA2", "DPI_HSYNC", "SPI3_SCLK", "UART2_RTS", "I2C3_SCL"],
+    ["GPCLK0", "SMI_SA1", "DPI_D0", "SPI4_CE0_N", "UART3_TXD", "I2C3_SDA"],
+    ["GPCLK1", "SMI_SA0", "DPI_D1", "SPI4_MISO", "UART3_RXD", "I2C3_SCL"],
+    ["GPCLK2", "SMI_SOE_N", "DPI_D2", "SPI4_MOSI", "UART3_CTS", "I2C4_SDA"],
+    ["SPI0_CE1_N", "SMI_SWE_N", "DPI_D3", "SPI4_SCLK", "UART3_RTS", "I2C4_SCL"],
+    ["SPI0_CE0_N", "SMI_SD0", "DPI_D4", "BSCSL_CE_N", "UART4_TXD", "I2C4_SDA"],
+    ["SPI0_MISO", "SMI_SD1", "DPI_D5", "BSCSL_MISO", "UART4_RXD", "I2C4_SCL"],
+    ["SPI0_MOSI", "SMI_SD2", "DPI_D6", "BSCSL_SDA", "UART4_CTS", "I2C5_SDA"],
+    ["SPI0_SCLK", "SMI_SD3", "DPI_D7", "BSCSL_SCL", "UART4_RTS", "I2C5_SCL"],
+    ["PWM0_0", "SMI_SD4", "DPI_D8", "SPI5_CE0_N", "UART5_TXD", "I2C5_SDA"],
+    ["PWM0_1", "SMI_SD5", "DPI_D9", "SPI5_MISO", "UART5_RXD", "I2C5_SCL"],
+    ["UART0_TXD", "SMI_SD6", "DPI_D10", "SPI5_MOSI", "UART5_CTS", "UART1_TXD"],
+    ["UART0_RXD", "SMI_SD7", "DPI_D11", "SPI5_SCLK", "UART5_RTS", "UART1_RXD"],
+    ["", "SMI_SD8", "DPI_D12", "UART0_CTS", "SPI1_CE2_N", "UART1_CTS"],
+    ["", "SMI_SD9", "DPI_D13", "UART0_RTS", "SPI1_CE1_N", "UART1_RTS"],
+    ["PCM_CLK", "SMI_SD10", "DPI_D14", "SPI6_CE0_N", "SPI1_CE0_N", "PWM0_0"],
+    ["PCM_FS", "SMI_SD11", "DPI_D15", "SPI6_MISO", "SPI1_MISO", "PWM0_1"],
+    ["PCM_DIN", "SMI_SD12", "DPI_D16", "SPI6_MOSI", "SPI1_MOSI", "GPCLK0"],
+    ["PCM_DOUT", "SMI_SD13", "DPI_D17", "SPI6_SCLK", "SPI1_SCLK", "GPCLK1"],
+    [
+        "SD0_CLK", "SMI_SD14", "DPI_D18", "SD1_CLK", "ARM_TRST", "I2C6_SDA",
+    ],
+    [
+        "SD0_CMD", "SMI_SD15", "DPI_D19", "SD1_CMD", "ARM_RTCK", "I2C6_SCL",
+    ],
+    ["SD0_DAT0", "SMI_SD16", "DPI_D20", "SD1_DAT0", "ARM_TDO", "SPI3_CE1_N"],
+    ["SD0_DAT1", "SMI_SD17", "DPI_D21", "SD1_DAT1", "ARM_TCK", "SPI4_CE1_N"],
+    ["SD0_DAT2", "", "DPI_D22", "SD1_DAT2", "ARM_TDI", "SPI5_CE1_N"],
+    ["SD0_DAT3", "", "DPI_D23", "SD1_DAT3", "ARM_TMS", "SPI6_CE1_N"],
+    ["I2C0_SDA", "SMI_SA5", "PCM_CLK", "", "MII_A_RX_ERR", "RGMII_MDIO"],
+    ["I2C0_SCL", "SMI_SA4", "PCM_FS", "", "MII_A_TX_ERR", "RGMII_MDC"],
+    ["", "SMI_SA3", "PCM_DIN", "UART0_CTS", "MII_A_CRS", "UART1_CTS"],
+    ["", "SMI_SA2", "PCM_DOUT", "UART0_RTS", "MII_A_COL", "UART1_RTS"],
+    ["GPCLK0", "SMI_SA1", "", "UART0_TXD", "SD_CARD_PRES", "UART1_TXD"],
+    ["", "SMI_SA0", "", "UART0_RXD", "SD_CARD_WRPROT", "UART1_RXD"],
+    ["GPCLK0", "SMI_SOE_N", "", "SD1_CLK", "SD_CARD_LED", "RGMII_IRQ"],
+    ["SPI0_CE1_N", "SMI_SWE_N", "", "SD1_CMD", "RGMII_START_STOP", ""],
+    ["SPI0_CE0_N", "SMI_SD0", "UART0_TXD", "SD1_DAT0", "RGMII_RX_OK", "MII_A_RX_ERR"],
+    ["SPI0_MISO", "SMI_SD1", "UART0_RXD", "SD1_DAT1", "RGMII_MDIO", "MII_A_TX_ERR"],
+    ["SPI0_MOSI", "SMI_SD2", "UART0_RTS", "SD1_DAT2", "RGMII_MDC", "MII_A_CRS"],
+    ["SPI0_SCLK", "SMI_SD3", "UART0_CTS", "SD1_DAT3", "RGMII_IRQ", "MII_A_COL"],
+    ["PWM1_0", "SMI_SD4", "", "SD1_DAT4", "SPI0_MISO", "UART1_TXD"],
+    ["PWM1_1", "SMI_SD5", "", "SD1_DAT5", "SPI0_MOSI", "UART1_RXD"],
+    ["GPCLK1", "SMI_SD6", "", "SD1_DAT6", "SPI0_SCLK", "UART1_RTS"],
+    ["GPCLK2", "SMI_SD7", "", "SD1_DAT7", "SPI0_CE0_N", "UART1_CTS"],
+    ["GPCLK1", "I2C0_SDA", "I2C1_SDA", "", "SPI0_CE1_N", "SD_CARD_VOLT"],
+    ["PWM0_1", "I2C0_SCL", "I2C1_SCL", "", "SPI0_CE2_N", "SD_CARD_PWR0"],
+];
+
+/// RP1 Peripherals, section 3.1.1 (functions a0..a5 of bank 0).
+#[rustfmt::skip]
+const RP1: [[&str; 6]; 28] = [
+    ["SPI0_SIO3", "DPI_PCLK", "UART1_TXD", "I2C0_SDA", "", "SYS_RIO00"],
+    ["SPI0_SIO2", "DPI_DE", "UART1_RXD", "I2C0_SCL", "", "SYS_RIO01"],
+    ["SPI0_CSN3", "DPI_VSYNC", "UART1_CTS", "I2C1_SDA", "UART0_IR_RX", "SYS_RIO02"],
+    ["SPI0_CSN2", "DPI_HSYNC", "UART1_RTS", "I2C1_SCL", "UART0_IR_TX", "SYS_RIO03"],
+    ["GPCLK0", "DPI_D0", "UART2_TXD", "I2C2_SDA", "UART0_RI", "SYS_RIO04"],
+    ["GPCLK1", "DPI_D1", "UART2_RXD", "I2C2_SCL", "UART0_DTR", "SYS_RIO05"],
+    ["GPCLK2", "DPI_D2", "UART2_CTS", "I2C3_SDA", "UART0_DCD", "SYS_RIO06"],
+    ["SPI0_CSN1", "DPI_D3", "UART2_RTS", "I2C3_SCL", "UART0_DSR", "SYS_RIO07"],
+    ["SPI0_CSN0", "DPI_D4", "UART3_TXD", "I2C0_SDA", "", "SYS_RIO08"],
+    ["SPI0_SIO1", "DPI_D5", "UART3_RXD", "I2C0_SCL", "", "SYS_RIO09"],
+    ["SPI0_SIO0", "DPI_D6", "UART3_CTS", "I2C1_SDA", "", "SYS_RIO010"],
+    ["SPI0_SCLK", "DPI_D7", "UART3_RTS", "I2C1_SCL", "", "SYS_RIO011"],
+    ["PWM0_CHAN0", "DPI_D8", "UART4_TXD", "I2C2_SDA", "AUDIO_OUT_L", "SYS_RIO012"],
+    ["PWM0_CHAN1", "DPI_D9", "UART4_RXD", "I2C2_SCL", "AUDIO_OUT_R", "SYS_RIO013"],
+    ["PWM0_CHAN2", "DPI_D10", "UART4_CTS", "I2C3_SDA", "UART0_TXD", "SYS_RIO014"],
+    ["PWM0_CHAN3", "DPI_D11", "UART4_RTS", "I2C3_SCL", "UART0_RXD", "SYS_RIO015"],
+    ["SPI1_CSN2", "DPI_D12", "MIPI0_DSI_TE", "", "UART0_CTS", "SYS_RIO016"],
+    ["SPI1_CSN1", "DPI_D13", "MIPI1_DSI_TE", "", "UART0_RTS", "SYS_RIO017"],
+    ["SPI1_CSN0", "DPI_D14", "I2S0_SCLK", "PWM0_CHAN2", "I2S1_SCLK", "SYS_RIO018"],
+    ["SPI1_SIO1", "DPI_D15", "I2S0_WS", "PWM0_CHAN3", "I2S1_WS", "SYS_RIO019"],
+    ["SPI1_SIO0", "DPI_D16", "I2S0_SDI0", "GPCLK0", "I2S1_SDI0", "SYS_RIO020"],
+    ["SPI1_SCLK", "DPI_D17", "I2S0_SDO0", "GPCLK1", "I2S1_SDO0", "SYS_RIO021"],
+    ["SD0_CLK", "DPI_D18", "I2S0_SDI1", "I2C3_SDA", "I2S1_SDI1", "SYS_RIO022"],
+    ["SD0_CMD", "DPI_D19", "I2S0_SDO1", "I2C3_SCL", "I2S1_SDO1", "SYS_RIO023"],
+    ["SD0_DAT0", "DPI_D20", "I2S0_SDI2", "", "I2S1_SDI2", "SYS_RIO024"],
+    ["SD0_DAT1", "DPI_D21", "I2S0_SDO2", "AUDIO_IN_CLK", "I2S1_SDO2", "SYS_RIO025"],
+    ["SD0_DAT2", "DPI_D22", "I2S0_SDI3", "AUDIO_IN_DAT0", "I2S1_SDI3", "SYS_RIO026"],
+    ["SD0_DAT3", "DPI_D23", "I2S0_SDO3", "AUDIO_IN_DAT1", "I2S1_SDO3", "SYS_RIO027"],
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_and_lookups_agree() {
+        assert_eq!(
+            Soc::Bcm2835.alt_function(14, PinFunc::Alt0),
+            Some("UART0_TXD")
+        );
+        assert_eq!(Soc::Rp1.alt_function(14, PinFunc::Alt4), Some("UART0_TXD"));
+        assert_eq!(Soc::Bcm2835.alt_function(26, PinFunc::Alt0), None);
+        assert_eq!(Soc::Bcm2835.alt_function(14, PinFunc::Output), None);
+        assert_eq!(Soc::Rp1.alt_function(30, PinFunc::Alt0), None);
+
+        assert_eq!(Soc::Bcm2835.alt_for(14, "uart0_txd"), Some(PinFunc::Alt0));
+        assert_eq!(Soc::Rp1.alt_for(14, "UART0_TXD"), Some(PinFunc::Alt4));
+        assert_eq!(Soc::Bcm2711.alt_for(4, "I2C3_SDA"), Some(PinFunc::Alt5));
+        assert_eq!(Soc::Bcm2835.alt_for(15, "UART0_TXD"), None);
+        assert_eq!(Soc::from_name("BCM2712"), Some(Soc::Rp1));
+        assert_eq!(Soc::from_name("bcm2837"), Some(Soc::Bcm2835));
+
+        // Every table row round-trips through alt_for.
+        for soc in Soc::ALL {
+            for gpio in 0..soc.gpio_count() {
+                for func in ALTS {
+                    if let Some(name) = soc.alt_function(gpio, func) {
+                        assert_eq!(soc.alt_for(gpio, name), Some(func), "{soc:?} {gpio}");
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,326 @@
+//  _  _       _             _  _
+// | || |  ___| |_ _ __ ___ | || |
+// | || |_/ __| __| '_ ` _ \| || |_
+// |__   _\__ | |_| | | | | |__   _|
+//   |_| |___/\__|_|_|_| |_|  |_|
+//! # ehatrom — EEPROM HAT library for Raspberry Pi HATs
+//! - [Documentation (docs.rs)](https://docs.rs/ehatrom)
+//! - [GitHub](https://github.com/4stm4/ehatrom)
+//!
+//! ## TLV records inside custom atoms
+//!
+//! The HAT format gives custom atoms (`0x0004`) no sub-type. This module
+//! defines a small container format so several logical records can share one
+//! custom atom and be found by tag. A container is a sequence of records,
+//! with nothing before, between or after them:
+//!
+//! | Field   | Size      | Notes                                                  |
+//! |---------|-----------|--------------------------------------------------------|
+//! | `tag`   | `u16` LE  | bits 0–14: tag (`0x0000`–`0x7FFF`); bit 15: [`CRC_FLAG`] |
+//! | `len`   | `u16` LE  | length of `value` in bytes                             |
+//! | `value` | `len`     | opaque                                                 |
+//! | `crc`   | `u16` LE  | only if [`CRC_FLAG`] is set                            |
+//!
+//! The optional `crc` is the same CRC-16 as the atom CRC
+//! ([`crc16`](crate::utils::crc16::crc16)), computed over the record's own
+//! `tag` (flag included), `len` and `value`. The atom CRC already covers the
+//! whole container, so the per-record CRC is for records that are copied
+//! between containers or written separately from their atom.
+//!
+//! [`records`] iterates a container and [`find`] looks a record up by tag;
+//! [`TlvWriter`] builds one in a caller-supplied buffer. Nothing here
+//! allocates, so it works in `no_std` builds. [`custom::Tlv`] is the owned
+//! form used with [`Eeprom::add_custom_payload`], and `dump` lists the tags
+//! of any custom atom that parses as a container.
+//!
+//! [`custom::Tlv`]: crate::custom::Tlv
+//! [`Eeprom::add_custom_payload`]: crate::Eeprom::add_custom_payload
+//!
+//! ```
+//! use ehatrom::tlv::{self, TlvWriter};
+//!
+//! let mut buf = [0u8; 32];
+//! let mut writer = TlvWriter::new(&mut buf);
+//! writer.push(0x0001, b"SN-0042").unwrap();
+//! writer.push_with_crc(0x0002, &[0x07, 0xEA]).unwrap();
+//! let container = writer.finish();
+//!
+//! let record = tlv::find(container, 0x0002).unwrap();
+//! assert_eq!(record.value, [0x07, 0xEA]);
+//! assert!(record.crc.is_some());
+//! assert_eq!(tlv::validate(container), Ok(2));
+//! ```
+
+use crate::utils::crc16::Crc16;
+use core::fmt;
+
+/// Tag bit that marks a record followed by a CRC-16.
+pub const CRC_FLAG: u16 = 0x8000;
+/// Size of the `tag` and `len` fields.
+pub const RECORD_HEADER_SIZE: usize = 4;
+/// Size of the optional record CRC.
+pub const RECORD_CRC_SIZE: usize = 2;
+
+/// Why a container does not parse, or a record does not fit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TlvError {
+    /// The record at `offset` runs past the end of the container.
+    Truncated { offset: usize },
+    /// The record at `offset` has a CRC that does not match its contents.
+    BadCrc { offset: usize, tag: u16 },
+    /// The container holds no records.
+    Empty,
+    /// A tag with [`CRC_FLAG`] set was passed to the writer.
+    TagOutOfRange(u16),
+    /// A value longer than `u16::MAX` bytes.
+    ValueTooLong(usize),
+    /// The writer's buffer has no room for the record.
+    BufferTooSmall,
+}
+
+impl fmt::Display for TlvError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TlvError::Truncated { offset } => write!(f, "record at offset {offset} is truncated"),
+            TlvError::BadCrc { offset, tag } => {
+                write!(f, "record 0x{tag:04X} at offset {offset} fails its CRC")
+            }
+            TlvError::Empty => write!(f, "no records"),
+            TlvError::TagOutOfRange(tag) => write!(f, "tag 0x{tag:04X} is above 0x7FFF"),
+            TlvError::ValueTooLong(len) => write!(f, "value of {len} bytes exceeds 65535"),
+            TlvError::BufferTooSmall => write!(f, "buffer too small"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for TlvError {}
+
+/// One record, borrowed from its container.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Record<'a> {
+    /// The tag, without [`CRC_FLAG`].
+    pub tag: u16,
+    pub value: &'a [u8],
+    /// The stored CRC, for records written with one. [`records`] has already
+    /// checked it.
+    pub crc: Option<u16>,
+}
+
+/// Bytes a record with a `value_len`-byte value takes in a container.
+pub const fn record_len(value_len: usize, with_crc: bool) -> usize {
+    RECORD_HEADER_SIZE + value_len + if with_crc { RECORD_CRC_SIZE } else { 0 }
+}
+
+fn record_crc(header: &[u8], value: &[u8]) -> u16 {
+    let mut crc = Crc16::new();
+    crc.update(header);
+    crc.update(value);
+    crc.finalize()
+}
+
+/// Iterator returned by [`records`].
+#[derive(Debug, Clone)]
+pub struct Records<'a> {
+    data: &'a [u8],
+    offset: usize,
+    failed: bool,
+}
+
+impl<'a> Iterator for Records<'a> {
+    type Item = Result<Record<'a>, TlvError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed || self.offset >= self.data.len() {
+            return None;
+        }
+        let offset = self.offset;
+        let result = parse_record(&self.data[offset..], offset);
+        match result {
+            Ok((_, len)) => self.offset += len,
+            Err(_) => self.failed = true,
+        }
+        Some(result.map(|(record, _)| record))
+    }
+}
+
+fn parse_record(data: &[u8], offset: usize) -> Result<(Record<'_>, usize), TlvError> {
+    let truncated = TlvError::Truncated { offset };
+    let [t0, t1, l0, l1, rest @ ..] = data else {
+        return Err(truncated);
+    };
+    let raw_tag = u16::from_le_bytes([*t0, *t1]);
+    let tag = raw_tag & !CRC_FLAG;
+    let len = u16::from_le_bytes([*l0, *l1]) as usize;
+    let value = rest.get(..len).ok_or(truncated)?;
+    let crc = if raw_tag & CRC_FLAG != 0 {
+        let stored = rest.get(len..len + RECORD_CRC_SIZE).ok_or(truncated)?;
+        let stored = u16::from_le_bytes([stored[0], stored[1]]);
+        if record_crc(&data[..RECORD_HEADER_SIZE], value) != stored {
+            return Err(TlvError::BadCrc { offset, tag });
+        }
+        Some(stored)
+    } else {
+        None
+    };
+    Ok((Record { tag, value, crc }, record_len(len, crc.is_some())))
+}
+
+/// Iterates the records of a container. After the first error the iterator
+/// yields that error and stops.
+pub fn records(data: &[u8]) -> Records<'_> {
+    Records {
+        data,
+        offset: 0,
+        failed: false,
+    }
+}
+
+/// The first record with `tag`, if the container parses up to it.
+pub fn find(data: &[u8], tag: u16) -> Option<Record<'_>> {
+    records(data)
+        .map_while(Result::ok)
+        .find(|record| record.tag == tag)
+}
+
+/// Checks that `data` is a non-empty container with valid record CRCs and
+/// returns the number of records.
+pub fn validate(data: &[u8]) -> Result<usize, TlvError> {
+    let mut count = 0;
+    for record in records(data) {
+        record?;
+        count += 1;
+    }
+    if count == 0 {
+        return Err(TlvError::Empty);
+    }
+    Ok(count)
+}
+
+/// Builds a container in a caller-supplied buffer.
+#[derive(Debug)]
+pub struct TlvWriter<'a> {
+    buf: &'a mut [u8],
+    len: usize,
+}
+
+impl<'a> TlvWriter<'a> {
+    /// Starts an empty container at the beginning of `buf`.
+    pub fn new(buf: &'a mut [u8]) -> Self {
+        TlvWriter { buf, len: 0 }
+    }
+
+    /// Appends a record without a CRC.
+    pub fn push(&mut self, tag: u16, value: &[u8]) -> Result<&mut Self, TlvError> {
+        self.push_record(tag, value, false)
+    }
+
+    /// Appends a record followed by its CRC-16.
+    pub fn push_with_crc(&mut self, tag: u16, value: &[u8]) -> Result<&mut Self, TlvError> {
+        self.push_record(tag, value, true)
+    }
+
+    fn push_record(
+        &mut self,
+        tag: u16,
+        value: &[u8],
+        with_crc: bool,
+    ) -> Result<&mut Self, TlvError> {
+        if tag & CRC_FLAG != 0 {
+            return Err(TlvError::TagOutOfRange(tag));
+        }
+        let len = u16::try_from(value.len()).map_err(|_| TlvError::ValueTooLong(value.len()))?;
+        let size = record_len(value.len(), with_crc);
+        let out = self
+            .buf
+            .get_mut(self.len..self.len + size)
+            .ok_or(TlvError::BufferTooSmall)?;
+        let raw_tag = if with_crc { tag | CRC_FLAG } else { tag };
+        out[..2].copy_from_slice(&raw_tag.to_le_bytes());
+        out[2..4].copy_from_slice(&len.to_le_bytes());
+        out[4..4 + value.len()].copy_from_slice(value);
+        if with_crc {
+            let crc = record_crc(&out[..RECORD_HEADER_SIZE], value);
+            out[4 + value.len()..].copy_from_slice(&crc.to_le_bytes());
+        }
+        self.len += size;
+        Ok(self)
+    }
+
+    /// Bytes written so far.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether no record has been written.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The finished container, borrowed from the buffer.
+    pub fn finish(self) -> &'a [u8] {
+        &self.buf[..self.len]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn writer_output_parses_back() {
+        let mut buf = [0u8; 32];
+        let mut writer = TlvWriter::new(&mut buf);
+        writer
+            .push(0x0001, b"abc")
+            .unwrap()
+            .push_with_crc(0x7FFF, &[])
+            .unwrap();
+        assert_eq!(writer.len(), record_len(3, false) + record_len(0, true));
+        let container = writer.finish();
+        assert_eq!(&container[..7], [0x01, 0x00, 0x03, 0x00, b'a', b'b', b'c']);
+        assert_eq!(&container[7..11], [0xFF, 0xFF, 0x00, 0x00]);
+
+        let mut iter = records(container);
+        let first = iter.next().unwrap().unwrap();
+        let second = iter.next().unwrap().unwrap();
+        assert_eq!(iter.next(), None);
+        assert_eq!(
+            (first.tag, first.value, first.crc),
+            (0x0001, &b"abc"[..], None)
+        );
+        assert_eq!(second.tag, 0x7FFF);
+        assert!(second.crc.is_some());
+        assert_eq!(find(container, 0x0001).unwrap().crc, None);
+        assert_eq!(find(container, 0x0002), None);
+    }
+
+    #[test]
+    fn rejects_bad_containers_and_records() {
+        let mut buf = [0u8; 16];
+        let mut writer = TlvWriter::new(&mut buf);
+        assert_eq!(
+            writer.push(0x8001, &[]).err(),
+            Some(TlvError::TagOutOfRange(0x8001))
+        );
+        assert_eq!(
+            writer.push(1, &[0; 13]).err(),
+            Some(TlvError::BufferTooSmall)
+        );
+        writer.push_with_crc(5, &[1, 2]).unwrap();
+        let len = writer.len();
+
+        let mut corrupt = buf;
+        corrupt[4] ^= 0xFF;
+        assert_eq!(
+            validate(&corrupt[..len]),
+            Err(TlvError::BadCrc { offset: 0, tag: 5 })
+        );
+        assert_eq!(
+            validate(&buf[..len - 1]),
+            Err(TlvError::Truncated { offset: 0 })
+        );
+        assert_eq!(validate(&[]), Err(TlvError::Empty));
+        assert_eq!(validate(&buf[..len]), Ok(1));
+    }
+}
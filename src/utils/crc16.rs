@@ -17,9 +17,44 @@
 //! must do the same — otherwise `eepdump` reports a CRC mismatch.
 //!
 //! Reference check value: `crc16(b"123456789") == 0xBB3D`.
+//!
+//! Two kernels compute the same value. The bit-at-a-time port
+//! ([`crc16_bitwise`]) needs no tables but takes a branch per input bit. With
+//! the `crc16-table` feature (on by default), [`crc16`] and [`Crc16`] instead
+//! use the equivalent reflected form (polynomial `0xA001`) one byte at a time
+//! through a 256-entry table. That is several times faster and costs 512
+//! bytes of flash; flash-constrained targets can build without the feature.
+//!
+//! Everything here is `const fn`, so CRCs can be computed at compile time (see
+//! [`ImageBuilder`](crate::image::ImageBuilder)).
 
 /// CRC-16 polynomial used by the HAT EEPROM tools.
 pub const CRC16_POLY: u16 = 0x8005;
+/// [`CRC16_POLY`] bit-reversed, for the LSB-first table-driven form.
+#[cfg(feature = "crc16-table")]
+const CRC16_POLY_REFLECTED: u16 = 0xA001;
+
+/// `TABLE[b]` is the reflected CRC register after shifting byte `b` through it.
+#[cfg(feature = "crc16-table")]
+static TABLE: [u16; 256] = {
+    let mut table = [0u16; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u16;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ CRC16_POLY_REFLECTED
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
 
 /// Incremental HAT-format CRC-16, mirroring `crc_add`/`crc_get` in `eeptools`.
 ///
@@ -28,6 +63,8 @@ pub const CRC16_POLY: u16 = 0x8005;
 /// the concatenation of all fed bytes.
 #[derive(Debug, Clone, Copy)]
 pub struct Crc16 {
+    /// The reflected CRC register with `crc16-table`, otherwise the
+    /// unreversed `eeptools` shift register.
     out: u16,
 }
 
@@ -44,63 +81,106 @@ impl Crc16 {
     }
 
     /// Feeds more bytes into the running CRC.
-    pub fn update(&mut self, data: &[u8]) {
-        let mut out = self.out;
-        // Feed every bit of every byte, LSB-first (matches `(*data >> bits_read) & 1`).
-        for &byte in data {
-            for bit in 0..8u32 {
-                let bit_flag = out >> 15;
-                out <<= 1;
-                out |= ((byte >> bit) & 1) as u16;
-                if bit_flag != 0 {
-                    out ^= CRC16_POLY;
-                }
-            }
+    pub const fn update(&mut self, data: &[u8]) {
+        #[cfg(feature = "crc16-table")]
+        {
+            self.out = update_table(self.out, data);
+        }
+        #[cfg(not(feature = "crc16-table"))]
+        {
+            self.out = update_bitwise(self.out, data);
         }
-        self.out = out;
     }
 
     /// Consumes the state and returns the final CRC-16.
-    pub fn finalize(self) -> u16 {
-        let mut out = self.out;
-        // "Push out" the last 16 bits.
-        for _ in 0..16 {
+    pub const fn finalize(self) -> u16 {
+        #[cfg(feature = "crc16-table")]
+        return self.out;
+        #[cfg(not(feature = "crc16-table"))]
+        finalize_bitwise(self.out)
+    }
+}
+
+/// Reflected, byte-at-a-time update. Equivalent to [`update_bitwise`] followed
+/// by [`finalize_bitwise`], without the final bit reversal step.
+#[cfg(feature = "crc16-table")]
+const fn update_table(mut crc: u16, data: &[u8]) -> u16 {
+    // `while` rather than `for` so this stays usable in `const` contexts.
+    let mut i = 0;
+    while i < data.len() {
+        crc = (crc >> 8) ^ TABLE[((crc ^ data[i] as u16) & 0xFF) as usize];
+        i += 1;
+    }
+    crc
+}
+
+const fn update_bitwise(mut out: u16, data: &[u8]) -> u16 {
+    // Feed every bit of every byte, LSB-first (matches `(*data >> bits_read) & 1`).
+    let mut i = 0;
+    while i < data.len() {
+        let byte = data[i];
+        let mut bit = 0;
+        while bit < 8 {
             let bit_flag = out >> 15;
             out <<= 1;
+            out |= ((byte >> bit) & 1) as u16;
             if bit_flag != 0 {
                 out ^= CRC16_POLY;
             }
+            bit += 1;
         }
-        // Reverse the bits of the register to obtain the final CRC.
-        let mut crc: u16 = 0;
-        let mut i: u16 = 0x8000;
-        let mut j: u16 = 0x0001;
-        while i != 0 {
-            if out & i != 0 {
-                crc |= j;
-            }
-            i >>= 1;
-            j <<= 1;
+        i += 1;
+    }
+    out
+}
+
+const fn finalize_bitwise(mut out: u16) -> u16 {
+    // "Push out" the last 16 bits.
+    let mut n = 0;
+    while n < 16 {
+        let bit_flag = out >> 15;
+        out <<= 1;
+        if bit_flag != 0 {
+            out ^= CRC16_POLY;
         }
-        crc
+        n += 1;
     }
+    // Reverse the bits of the register to obtain the final CRC.
+    let mut crc: u16 = 0;
+    let mut i: u16 = 0x8000;
+    let mut j: u16 = 0x0001;
+    while i != 0 {
+        if out & i != 0 {
+            crc |= j;
+        }
+        i >>= 1;
+        j <<= 1;
+    }
+    crc
 }
 
 /// Computes the HAT-format CRC-16 over `data`.
 ///
-/// The algorithm mirrors `getcrc()` in the reference `eeptools`: bits are
+/// The result is that of `getcrc()` in the reference `eeptools`: bits are
 /// consumed from the least-significant end of each byte, the register uses
 /// polynomial [`CRC16_POLY`] starting from `0`, and the final register is
-/// bit-reversed to produce the result.
-pub fn crc16(data: &[u8]) -> u16 {
+/// bit-reversed to produce the result. Which kernel computes it depends on the
+/// `crc16-table` feature.
+pub const fn crc16(data: &[u8]) -> u16 {
     let mut crc = Crc16::new();
     crc.update(data);
     crc.finalize()
 }
 
+/// The bit-at-a-time port of `getcrc()`, whatever the features. [`crc16`]
+/// gives the same result; this one is kept as the reference to check against.
+pub const fn crc16_bitwise(data: &[u8]) -> u16 {
+    finalize_bitwise(update_bitwise(0, data))
+}
+
 #[cfg(test)]
 mod tests {
-    use super::{Crc16, crc16};
+    use super::{Crc16, crc16, crc16_bitwise};
 
     #[test]
     fn reference_check_value() {
@@ -122,4 +202,26 @@ mod tests {
         assert_eq!(crc.finalize(), crc16(data));
         assert_eq!(crc.finalize(), 0xBB3D);
     }
+
+    #[test]
+    fn kernels_agree() {
+        // A cheap LCG so the input covers every byte value in varied orders.
+        let mut state = 0x1234_5678u32;
+        let mut data = [0u8; 1024];
+        for byte in data.iter_mut() {
+            state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+            *byte = (state >> 24) as u8;
+        }
+        for len in [0, 1, 2, 7, 8, 255, 256, 1024] {
+            assert_eq!(
+                crc16(&data[..len]),
+                crc16_bitwise(&data[..len]),
+                "len {len}"
+            );
+        }
+        let mut crc = Crc16::new();
+        crc.update(&data[..300]);
+        crc.update(&data[300..]);
+        assert_eq!(crc.finalize(), crc16_bitwise(&data));
+    }
 }
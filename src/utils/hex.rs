@@ -0,0 +1,71 @@
+//  _  _       _             _  _
+// | || |  ___| |_ _ __ ___ | || |
+// | || |_/ __| __| '_ ` _ \| || |_
+// |__   _\__ | |_| | | | | |__   _|
+//   |_| |___/\__|_|_|_| |_|  |_|
+//! # ehatrom — EEPROM HAT library for Raspberry Pi HATs
+//! - [Documentation (docs.rs)](https://docs.rs/ehatrom)
+//! - [GitHub](https://github.com/4stm4/ehatrom)
+//!
+//! ## Lowercase hex encoding for atom payloads
+//!
+//! Used wherever a binary blob has to travel through a text format (JSON,
+//! TOML, reports). Decoding accepts upper- or lowercase digits and ignores
+//! ASCII whitespace, so long blobs may be wrapped across lines.
+
+use alloc::{string::String, vec::Vec};
+
+const DIGITS: &[u8; 16] = b"0123456789abcdef";
+
+/// Encodes `data` as a lowercase hex string (two digits per byte).
+pub fn encode(data: &[u8]) -> String {
+    let mut out = String::with_capacity(data.len() * 2);
+    for &b in data {
+        out.push(DIGITS[(b >> 4) as usize] as char);
+        out.push(DIGITS[(b & 0x0F) as usize] as char);
+    }
+    out
+}
+
+/// Decodes a hex string, returning `None` on an odd digit count or a non-hex
+/// character. An optional `0x` prefix is accepted.
+pub fn decode(s: &str) -> Option<Vec<u8>> {
+    let s = s.trim();
+    let s = s.strip_prefix("0x").unwrap_or(s);
+    let digits: Vec<u8> = s.bytes().filter(|b| !b.is_ascii_whitespace()).collect();
+    if !digits.len().is_multiple_of(2) {
+        return None;
+    }
+    digits
+        .chunks(2)
+        .map(|pair| Some((nibble(pair[0])? << 4) | nibble(pair[1])?))
+        .collect()
+}
+
+fn nibble(c: u8) -> Option<u8> {
+    match c {
+        b'0'..=b'9' => Some(c - b'0'),
+        b'a'..=b'f' => Some(c - b'a' + 10),
+        b'A'..=b'F' => Some(c - b'A' + 10),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decode, encode};
+
+    #[test]
+    fn roundtrip() {
+        let data = [0x00, 0x7F, 0xD0, 0x0D, 0xFE, 0xED];
+        assert_eq!(encode(&data), "007fd00dfeed");
+        assert_eq!(decode("007FD00DFEED").unwrap(), data);
+        assert_eq!(decode("0x00 7f d0\n0d fe ed").unwrap(), data);
+    }
+
+    #[test]
+    fn rejects_malformed() {
+        assert!(decode("abc").is_none());
+        assert!(decode("zz").is_none());
+    }
+}
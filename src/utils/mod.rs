@@ -1,2 +1,5 @@
 pub mod crc16;
 pub mod crc32;
+#[cfg(feature = "alloc")]
+pub mod hex;
+pub mod sha256;
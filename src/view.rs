@@ -0,0 +1,294 @@
+//  _  _       _             _  _
+// | || |  ___| |_ _ __ ___ | || |
+// | || |_/ __| __| '_ ` _ \| || |_
+// |__   _\__ | |_| | | | | |__   _|
+//   |_| |___/\__|_|_|_| |_|  |_|
+//! # ehatrom — EEPROM HAT library for Raspberry Pi HATs
+//! - [Documentation (docs.rs)](https://docs.rs/ehatrom)
+//! - [GitHub](https://github.com/4stm4/ehatrom)
+//!
+//! ## Zero-copy view over a borrowed image
+//!
+//! [`EepromView`] reads an image in place, from any slice: a stack buffer
+//! filled over I2C works as well as a `'static` one. It only checks the header
+//! up front; every accessor walks the atoms with [`atoms`](crate::atoms) when
+//! it is called and returns data borrowed from the image. Vendor strings,
+//! GPIO pins, the DT blob and custom atoms (including unknown atom types, as
+//! in [`Eeprom::from_bytes`](crate::Eeprom::from_bytes)) are never copied.
+//!
+//! Like `from_bytes`, the view does not check CRCs; call
+//! [`EepromView::verify`] first if the image may be corrupt.
+
+use crate::{
+    AtomIter, AtomRef, AtomType, DtAtom, EEPROM_SIGNATURE, Eeprom, EepromHeader, GPIO_COUNT,
+    GPIO_COUNT_BANK1, GpioMapAtom, HEADER_SIZE, PinConfig, VENDOR_FIXED_SIZE, VendorInfoAtom,
+    decode_pin,
+};
+use core::str::Utf8Error;
+
+/// A borrowed, lazily parsed EEPROM image.
+#[derive(Debug, Clone, Copy)]
+pub struct EepromView<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> EepromView<'a> {
+    /// Wraps `data` after checking the header size and signature.
+    pub fn new(data: &'a [u8]) -> Result<Self, &'static str> {
+        if data.len() < HEADER_SIZE {
+            return Err("Not enough data for EEPROM header");
+        }
+        if data[0..4] != EEPROM_SIGNATURE {
+            return Err("Invalid EEPROM signature");
+        }
+        Ok(EepromView { data })
+    }
+
+    /// The image this view borrows.
+    pub fn bytes(&self) -> &'a [u8] {
+        self.data
+    }
+
+    /// The 12-byte header.
+    pub fn header(&self) -> EepromHeader {
+        let d = self.data;
+        EepromHeader {
+            signature: EEPROM_SIGNATURE,
+            version: d[4],
+            reserved: d[5],
+            numatoms: u16::from_le_bytes([d[6], d[7]]),
+            eeplen: u32::from_le_bytes([d[8], d[9], d[10], d[11]]),
+        }
+    }
+
+    /// Checks every per-atom CRC-16 (see [`Eeprom::verify`]).
+    pub fn verify(&self) -> bool {
+        Eeprom::verify(self.data)
+    }
+
+    /// All atoms in image order.
+    pub fn atoms(&self) -> AtomIter<'a> {
+        crate::atoms(self.data)
+    }
+
+    /// The first atom of type `kind`.
+    fn find(&self, kind: AtomType) -> Option<AtomRef<'a>> {
+        self.atoms().find(|atom| atom.kind() == kind)
+    }
+
+    /// The vendor-info atom, if present and long enough for its strings.
+    pub fn vendor_info(&self) -> Option<VendorView<'a>> {
+        VendorView::new(self.find(AtomType::VendorInfo)?.data)
+    }
+
+    /// The bank0 GPIO map atom.
+    pub fn gpio_bank0(&self) -> Option<GpioView<'a>> {
+        GpioView::new(self.find(AtomType::GpioMapBank0)?.data)
+    }
+
+    /// The bank1 GPIO map atom.
+    pub fn gpio_bank1(&self) -> Option<GpioView<'a>> {
+        GpioView::new(self.find(AtomType::GpioMapBank1)?.data)
+    }
+
+    /// The used pins of both GPIO maps with their BCM GPIO numbers, bank1
+    /// pins counting from [`GPIO_COUNT`].
+    pub fn used_pins(&self) -> impl Iterator<Item = (usize, PinConfig)> + 'a {
+        let bank0 = self.gpio_bank0().map_or(&[][..], |bank| bank.pins());
+        let bank1 = self.gpio_bank1().map_or(&[][..], |bank| bank.pins());
+        let bank1 = bank1.iter().take(GPIO_COUNT_BANK1).enumerate();
+        bank0
+            .iter()
+            .take(GPIO_COUNT)
+            .enumerate()
+            .chain(bank1.map(|(index, byte)| (GPIO_COUNT + index, byte)))
+            .map(|(gpio, &byte)| (gpio, decode_pin(byte)))
+            .filter(|(_, pin)| pin.used)
+    }
+
+    /// The DT atom payload, if present and non-empty.
+    pub fn dt_blob(&self) -> Option<&'a [u8]> {
+        Some(self.find(AtomType::DtBlob)?.data).filter(|data| !data.is_empty())
+    }
+
+    /// The DT atom, classified as a compiled blob or an overlay name.
+    pub fn dt_atom(&self) -> Option<DtAtom<'a>> {
+        self.dt_blob().map(DtAtom::parse)
+    }
+
+    /// The power-supply atom: required back-power current in mA.
+    pub fn power_supply(&self) -> Option<u32> {
+        match *self.find(AtomType::PowerSupply)?.data {
+            [a, b, c, d, ..] => Some(u32::from_le_bytes([a, b, c, d])),
+            _ => None,
+        }
+    }
+
+    /// Payloads of the custom atoms and of atoms with unknown types, in
+    /// image order.
+    pub fn custom_atoms(&self) -> impl Iterator<Item = &'a [u8]> + 'a {
+        self.atoms()
+            .filter(|atom| matches!(atom.kind(), AtomType::Custom | AtomType::Unknown))
+            .map(|atom| atom.data)
+    }
+}
+
+/// A borrowed vendor-info atom.
+#[derive(Debug, Clone, Copy)]
+pub struct VendorView<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> VendorView<'a> {
+    fn new(data: &'a [u8]) -> Option<Self> {
+        let view = VendorView { data };
+        let end = VENDOR_FIXED_SIZE + view.vslen() + view.pslen();
+        (data.len() >= VENDOR_FIXED_SIZE && data.len() >= end).then_some(view)
+    }
+
+    fn vslen(&self) -> usize {
+        self.data.get(20).map_or(0, |&n| n as usize)
+    }
+
+    fn pslen(&self) -> usize {
+        self.data.get(21).map_or(0, |&n| n as usize)
+    }
+
+    /// The 16-byte UUID, least-significant word first.
+    pub fn uuid(&self) -> &'a [u8; 16] {
+        self.data[..16].try_into().unwrap()
+    }
+
+    pub fn product_id(&self) -> u16 {
+        u16::from_le_bytes([self.data[16], self.data[17]])
+    }
+
+    pub fn product_ver(&self) -> u16 {
+        u16::from_le_bytes([self.data[18], self.data[19]])
+    }
+
+    /// The vendor string as stored (`vslen` bytes).
+    pub fn vendor_bytes(&self) -> &'a [u8] {
+        &self.data[VENDOR_FIXED_SIZE..VENDOR_FIXED_SIZE + self.vslen()]
+    }
+
+    /// The product string as stored (`pslen` bytes).
+    pub fn product_bytes(&self) -> &'a [u8] {
+        let start = VENDOR_FIXED_SIZE + self.vslen();
+        &self.data[start..start + self.pslen()]
+    }
+
+    pub fn vendor(&self) -> Result<&'a str, Utf8Error> {
+        core::str::from_utf8(self.vendor_bytes())
+    }
+
+    pub fn product(&self) -> Result<&'a str, Utf8Error> {
+        core::str::from_utf8(self.product_bytes())
+    }
+
+    /// Copies the atom into a [`VendorInfoAtom`] (strings truncated to 16 bytes).
+    pub fn to_atom(&self) -> VendorInfoAtom {
+        VendorInfoAtom::decode(self.data).expect("length checked in VendorView::new")
+    }
+}
+
+/// A borrowed GPIO map atom.
+#[derive(Debug, Clone, Copy)]
+pub struct GpioView<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> GpioView<'a> {
+    fn new(data: &'a [u8]) -> Option<Self> {
+        (data.len() >= 2).then_some(GpioView { data })
+    }
+
+    /// Drive, slew and hysteresis bits.
+    pub fn flags(&self) -> u8 {
+        self.data[0]
+    }
+
+    /// Back-power byte.
+    pub fn power(&self) -> u8 {
+        self.data[1]
+    }
+
+    /// Raw pin bytes, one per GPIO of the bank, as stored.
+    pub fn pins(&self) -> &'a [u8] {
+        &self.data[2..]
+    }
+
+    /// The decoded configuration of the bank's `index`-th pin.
+    pub fn pin(&self, index: usize) -> Option<PinConfig> {
+        self.pins().get(index).copied().map(decode_pin)
+    }
+
+    /// Copies the atom into a [`GpioMapAtom`].
+    pub fn to_atom(&self) -> GpioMapAtom {
+        GpioMapAtom::decode(self.data).expect("length checked in GpioView::new")
+    }
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    use super::*;
+    use crate::{PinFunc, PinPull, parse_settings};
+    use alloc::vec::Vec;
+
+    #[test]
+    fn reads_every_atom_in_place() {
+        let mut eeprom = parse_settings(
+            "product_id 0x1234\nproduct_ver 0x0002\nvendor \"ACME\"\nproduct \"Sensor HAT\"\n\
+             setgpio 4 OUTPUT UP\nsetgpio 30 ALT2 DEFAULT\ncurrent_supply 500\n\
+             dtoverlay acme-sensor,addr=0x51\n",
+        )
+        .unwrap();
+        eeprom.add_custom_atom(b"first".to_vec());
+        eeprom.add_custom_atom(b"second".to_vec());
+        let image = eeprom.serialize();
+        // A non-'static buffer, as filled from I2C.
+        let mut buf = [0u8; 256];
+        buf[..image.len()].copy_from_slice(&image);
+        let view = EepromView::new(&buf[..image.len()]).unwrap();
+
+        assert!(view.verify());
+        assert_eq!({ view.header().numatoms }, 7);
+        let vendor = view.vendor_info().unwrap();
+        assert_eq!((vendor.product_id(), vendor.product_ver()), (0x1234, 2));
+        assert_eq!(
+            (vendor.vendor(), vendor.product()),
+            (Ok("ACME"), Ok("Sensor HAT"))
+        );
+        let uuid = eeprom.vendor_info.uuid;
+        assert_eq!(vendor.uuid(), &uuid);
+
+        let bank0 = view.gpio_bank0().unwrap();
+        assert_eq!(bank0.pins().len(), 28);
+        let pin = bank0.pin(4).unwrap();
+        assert_eq!((pin.func, pin.pull), (PinFunc::Output, PinPull::Up));
+        assert_eq!(
+            view.gpio_bank1().unwrap().pin(2).unwrap().func,
+            PinFunc::Alt2
+        );
+        let used: Vec<_> = view
+            .used_pins()
+            .map(|(gpio, pin)| (gpio, pin.func))
+            .collect();
+        assert_eq!(used, [(4, PinFunc::Output), (30, PinFunc::Alt2)]);
+        assert_eq!(view.power_supply(), Some(500));
+        assert_eq!(
+            view.dt_atom(),
+            Some(DtAtom::Overlay {
+                name: "acme-sensor",
+                params: "addr=0x51"
+            })
+        );
+        let custom: Vec<_> = view.custom_atoms().collect();
+        assert_eq!(custom, [&b"first"[..], &b"second"[..]]);
+
+        assert_eq!(
+            EepromView::new(&image[..8]).err(),
+            Some("Not enough data for EEPROM header")
+        );
+    }
+}
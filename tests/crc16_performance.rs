@@ -0,0 +1,78 @@
+#![cfg(feature = "crc16-table")]
+
+use ehatrom::utils::crc16::{Crc16, crc16, crc16_bitwise};
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+/// Best of several runs, to keep scheduler noise out of the comparison.
+fn best_time(iterations: usize, mut f: impl FnMut()) -> Duration {
+    (0..5)
+        .map(|_| {
+            let start = Instant::now();
+            for _ in 0..iterations {
+                f();
+            }
+            start.elapsed()
+        })
+        .min()
+        .unwrap()
+}
+
+/// Wall-clock comparison of the two kernels. Timing depends on the machine
+/// and build, so it only runs on request:
+/// `cargo test --release --test crc16_performance -- --ignored --nocapture`.
+#[test]
+#[ignore = "timing-sensitive; run explicitly with --ignored"]
+fn test_crc16_table_speedup() {
+    // A DT-blob-sized payload (~145KB), as walked by `atoms()` and `validate`.
+    let data = b"This is a test string for performance measurement of our custom CRC16 implementation. Let's make it a bit longer to get more meaningful results.";
+    let large_data = data.repeat(1000);
+    let iterations = 5;
+
+    let bitwise = best_time(iterations, || {
+        black_box(crc16_bitwise(black_box(&large_data)));
+    });
+    let table = best_time(iterations, || {
+        black_box(crc16(black_box(&large_data)));
+    });
+
+    let speedup = bitwise.as_secs_f64() / table.as_secs_f64();
+    println!(
+        "CRC16 over {} bytes: bitwise {bitwise:?}, table {table:?} ({speedup:.1}x)",
+        large_data.len()
+    );
+
+    // The table kernel does one lookup per byte instead of eight branches;
+    // anything under 2x means it is not being used.
+    assert!(speedup > 2.0, "CRC16 table speedup only {speedup:.2}x");
+}
+
+#[test]
+fn test_crc16_table_matches_bitwise() {
+    let data: Vec<u8> = (0..=255u8).cycle().take(4099).collect();
+    assert_eq!(crc16(&data), crc16_bitwise(&data));
+    assert_eq!(crc16(b"123456789"), 0xBB3D);
+
+    let mut crc = Crc16::new();
+    for chunk in data.chunks(13) {
+        crc.update(chunk);
+    }
+    assert_eq!(crc.finalize(), crc16_bitwise(&data));
+}
+
+#[test]
+fn test_crc16_table_matches_bitwise_on_random_inputs() {
+    // xorshift32: deterministic pseudo-random lengths and bytes.
+    let mut state = 0x2545_F491u32;
+    let mut next = move || {
+        state ^= state << 13;
+        state ^= state >> 17;
+        state ^= state << 5;
+        state
+    };
+    for _ in 0..500 {
+        let len = (next() % 1024) as usize;
+        let data: Vec<u8> = (0..len).map(|_| next() as u8).collect();
+        assert_eq!(crc16(&data), crc16_bitwise(&data), "{data:02x?}");
+    }
+}
@@ -91,3 +91,40 @@ fn atoms_iterator_works_without_alloc() {
     let count = ehatrom::atoms(&GOLDEN).filter(|a| a.crc_valid()).count();
     assert_eq!(count, 2);
 }
+
+#[test]
+fn eeprom_view_reads_a_stack_buffer() {
+    let mut buf = [0u8; 128];
+    buf[..GOLDEN.len()].copy_from_slice(&GOLDEN);
+    let view = EepromView::new(&buf[..GOLDEN.len()]).unwrap();
+    assert!(view.verify());
+    let vendor = view.vendor_info().unwrap();
+    assert_eq!(vendor.product_id(), 0x5678);
+    assert_eq!(vendor.vendor(), Ok("testvendor"));
+    assert_eq!(vendor.product(), Ok("testproduct"));
+    let bank0 = view.gpio_bank0().unwrap();
+    assert_eq!((bank0.flags(), bank0.pins()), (0x05, &[1u8; 28][..]));
+    assert!(view.gpio_bank1().is_none());
+    assert_eq!(view.custom_atoms().count(), 0);
+}
+
+#[test]
+fn hat_image_macro_matches_golden() {
+    const VENDOR: VendorInfoAtom = VendorInfoAtom::new(
+        0x5678,
+        1,
+        "testvendor",
+        "testproduct",
+        [
+            0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0A, 0x0B, 0x0C, 0x0D, 0x0E,
+            0x0F, 0x10,
+        ],
+    );
+    const GPIO: GpioMapAtom = GpioMapAtom {
+        flags: 0x05,
+        power: 0x00,
+        pins: [1; 28],
+    };
+    const IMAGE: [u8; 105] = hat_image!(vendor_info(&VENDOR).gpio_bank0(&GPIO));
+    assert_eq!(IMAGE, GOLDEN);
+}